@@ -0,0 +1,116 @@
+//! Feeds arbitrary bytes and fds from a hostile server to a client using the core
+//! protocol and `xdg_shell`
+//!
+//! The first byte of the input is the number of fds (modulo 4) sent along the
+//! rest of the input. The client must not panic, must report any error of the
+//! connection on all later dispatches, and must deliver all the objects the server
+//! creates.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate nix;
+extern crate wayland_client;
+extern crate wayland_commons;
+extern crate wayland_protocols;
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+use nix::sys::signal::{signal, SigHandler, Signal};
+use wayland_client::protocol::{
+    wl_compositor, wl_data_device_manager, wl_output, wl_seat, wl_shm, wl_subcompositor,
+};
+use wayland_client::{
+    AnonymousObject, Argument, DispatchData, Display, EventQueue, Main, RawEvent,
+};
+use wayland_commons::socket::{Socket, MAX_BYTES_OUT};
+use wayland_protocols::xdg_shell::client::xdg_wm_base;
+
+fn close(fd: RawFd) {
+    drop(unsafe { UnixStream::from_raw_fd(fd) });
+}
+
+// read and dispatch all the available events, returns the number of objects
+// created by the server, or the first error
+fn dispatch_all(queue: &mut EventQueue) -> io::Result<usize> {
+    let mut created = 0;
+    let mut fallback = |event: RawEvent, _: Main<AnonymousObject>, _: DispatchData| {
+        for arg in event.args {
+            match arg {
+                Argument::Fd(fd) => close(fd),
+                Argument::NewId(Some(_)) => created += 1,
+                _ => {}
+            }
+        }
+    };
+    loop {
+        queue.dispatch_pending(&mut (), &mut fallback)?;
+        if let Some(guard) = queue.prepare_read() {
+            match guard.read_events() {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    queue.dispatch_pending(&mut (), &mut fallback)?;
+    Ok(created)
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    // like a regular rust program, don't get killed when writing to a closed socket
+    unsafe { signal(Signal::SIGPIPE, SigHandler::SigIgn) }.unwrap();
+    let (client_end, server_end) = UnixStream::pair().unwrap();
+    let display = unsafe { Display::from_fd(client_end.into_raw_fd()) }.unwrap();
+    let mut queue = display.create_event_queue();
+    let attached = (*display).clone().attach(queue.token());
+
+    // the objects the server can send events to, with predictable ids
+    let registry = attached.get_registry();
+    let compositor = registry.bind::<wl_compositor::WlCompositor>(4, 1);
+    let _subcompositor = registry.bind::<wl_subcompositor::WlSubcompositor>(1, 2);
+    let _shm = registry.bind::<wl_shm::WlShm>(1, 3);
+    let seat = registry.bind::<wl_seat::WlSeat>(6, 4);
+    let _output = registry.bind::<wl_output::WlOutput>(3, 5);
+    let manager = registry.bind::<wl_data_device_manager::WlDataDeviceManager>(3, 6);
+    let wm_base = registry.bind::<xdg_wm_base::XdgWmBase>(3, 7);
+    let surface = compositor.create_surface();
+    let _pointer = seat.get_pointer();
+    let _keyboard = seat.get_keyboard();
+    let _touch = seat.get_touch();
+    let _data_device = manager.get_data_device(&seat);
+    let xdg_surface = wm_base.get_xdg_surface(&surface);
+    let _toplevel = xdg_surface.get_toplevel();
+    let _ = display.flush();
+    let initial = display.object_counts().live;
+
+    // send the input, with its fds attached to the first chunk
+    let files: Vec<File> = (0..data[0] % 4).map(|_| File::open("/dev/null").unwrap()).collect();
+    let fds: Vec<RawFd> = files.iter().map(|f| f.as_raw_fd()).collect();
+    let socket = unsafe { Socket::from_raw_fd(server_end.into_raw_fd()) };
+    let mut fds = &fds[..];
+    for chunk in data[1..].chunks(MAX_BYTES_OUT) {
+        if socket.send_msg(chunk, fds).is_err() {
+            break;
+        }
+        fds = &[];
+    }
+
+    match dispatch_all(&mut queue) {
+        Ok(created) => {
+            assert!(display.protocol_error().is_none());
+            let counts = display.object_counts();
+            assert!(counts.live <= initial + created, "Objects were created but not delivered.");
+            assert_eq!(counts.zombies, 0);
+        }
+        Err(_) => {
+            // the connection is dead, and stays so
+            assert!(dispatch_all(&mut queue).is_err());
+        }
+    }
+});
@@ -0,0 +1,16 @@
+//! Feeds arbitrary protocol files to the scanner
+//!
+//! The scanner must not panic, ill-formed protocol files are reported as errors.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate wayland_scanner;
+
+use wayland_scanner::{try_generate_code_streams, Side};
+
+fuzz_target!(|data: &[u8]| {
+    for &side in &[Side::Client, Side::Server] {
+        let mut code = Vec::new();
+        let _ = try_generate_code_streams(data, &mut code, side, &[]);
+    }
+});
@@ -0,0 +1,224 @@
+//! Feeds arbitrary bytes and fds to a server exposing the core protocol and `xdg_shell`
+//!
+//! The first byte of the input is the number of fds (modulo 4) sent along the
+//! rest of the input. The server must not panic, must either process the requests
+//! or post a protocol error, and must destroy all the objects of the client once
+//! it disconnects.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate nix;
+extern crate wayland_commons;
+extern crate wayland_protocols;
+extern crate wayland_server;
+
+use std::cell::Cell;
+use std::fs::File;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::time::Duration;
+
+use nix::sys::signal::{signal, SigHandler, Signal};
+use wayland_commons::socket::{Socket, MAX_BYTES_OUT};
+use wayland_commons::MessageGroup;
+use wayland_protocols::xdg_shell::server::{xdg_surface, xdg_wm_base};
+use wayland_server::protocol::{
+    wl_compositor, wl_data_device_manager, wl_output, wl_seat, wl_shell, wl_shm, wl_shm_pool,
+    wl_subcompositor,
+};
+use wayland_server::{DispatchData, Display, Filter, Interface, Main, Resource, ResourceMap};
+
+// number of live resources created by the client
+type Live = Rc<Cell<usize>>;
+
+// assign a handler to a resource, keeping track of its lifetime
+fn track<I, F>(resource: &Main<I>, live: &Live, handler: F)
+where
+    I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    I::Request: MessageGroup<Map = ResourceMap>,
+    F: FnMut(Main<I>, I::Request, DispatchData) + 'static,
+{
+    live.set(live.get() + 1);
+    resource.quick_assign(handler);
+    let live = live.clone();
+    resource.assign_destructor(Filter::new(move |_: Resource<I>, _, _| live.set(live.get() - 1)));
+}
+
+// assign a no-op handler to a resource
+fn noop<I>(resource: &Main<I>, live: &Live)
+where
+    I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    I::Request: MessageGroup<Map = ResourceMap>,
+{
+    track(resource, live, |_, _, _| {});
+}
+
+fn close(fd: RawFd) {
+    drop(unsafe { UnixStream::from_raw_fd(fd) });
+}
+
+fn compositor(compositor: &Main<wl_compositor::WlCompositor>, live: &Live) {
+    let live2 = live.clone();
+    track(compositor, live, move |_, request, _| match request {
+        wl_compositor::Request::CreateSurface { id } => noop(&id, &live2),
+        wl_compositor::Request::CreateRegion { id } => noop(&id, &live2),
+        _ => {}
+    });
+}
+
+fn subcompositor(subcompositor: &Main<wl_subcompositor::WlSubcompositor>, live: &Live) {
+    let live2 = live.clone();
+    track(subcompositor, live, move |_, request, _| {
+        if let wl_subcompositor::Request::GetSubsurface { id, .. } = request {
+            noop(&id, &live2);
+        }
+    });
+}
+
+fn shm(shm: &Main<wl_shm::WlShm>, live: &Live) {
+    let live2 = live.clone();
+    track(shm, live, move |_, request, _| {
+        if let wl_shm::Request::CreatePool { id, fd, .. } = request {
+            close(fd);
+            let live3 = live2.clone();
+            track(&id, &live2, move |_, request, _| {
+                if let wl_shm_pool::Request::CreateBuffer { id, .. } = request {
+                    noop(&id, &live3);
+                }
+            });
+        }
+    });
+}
+
+fn seat(seat: &Main<wl_seat::WlSeat>, live: &Live) {
+    let live2 = live.clone();
+    track(seat, live, move |_, request, _| match request {
+        wl_seat::Request::GetPointer { id } => noop(&id, &live2),
+        wl_seat::Request::GetKeyboard { id } => noop(&id, &live2),
+        wl_seat::Request::GetTouch { id } => noop(&id, &live2),
+        _ => {}
+    });
+}
+
+fn data_device_manager(manager: &Main<wl_data_device_manager::WlDataDeviceManager>, live: &Live) {
+    let live2 = live.clone();
+    track(manager, live, move |_, request, _| match request {
+        wl_data_device_manager::Request::CreateDataSource { id } => noop(&id, &live2),
+        wl_data_device_manager::Request::GetDataDevice { id, .. } => noop(&id, &live2),
+        _ => {}
+    });
+}
+
+fn shell(shell: &Main<wl_shell::WlShell>, live: &Live) {
+    let live2 = live.clone();
+    track(shell, live, move |_, request, _| {
+        if let wl_shell::Request::GetShellSurface { id, .. } = request {
+            noop(&id, &live2);
+        }
+    });
+}
+
+fn wm_base(wm_base: &Main<xdg_wm_base::XdgWmBase>, live: &Live) {
+    let live2 = live.clone();
+    track(wm_base, live, move |_, request, _| match request {
+        xdg_wm_base::Request::CreatePositioner { id } => noop(&id, &live2),
+        xdg_wm_base::Request::GetXdgSurface { id, .. } => {
+            let live3 = live2.clone();
+            track(&id, &live2, move |_, request, _| match request {
+                xdg_surface::Request::GetToplevel { id } => noop(&id, &live3),
+                xdg_surface::Request::GetPopup { id, .. } => noop(&id, &live3),
+                _ => {}
+            });
+        }
+        _ => {}
+    });
+}
+
+macro_rules! global {
+    ($display:expr, $live:expr, $iface:ty, $version:expr, $handler:expr) => {{
+        let live = $live.clone();
+        $display.create_global::<$iface, _>(
+            $version,
+            Filter::new(move |(resource, _): (Main<$iface>, u32), _, _| $handler(&resource, &live)),
+        );
+    }};
+}
+
+// check if the server sent a wl_display.error event in this byte stream
+fn contains_protocol_error(bytes: &[u8]) -> bool {
+    let mut bytes = bytes;
+    while bytes.len() >= 8 {
+        let word = |i: usize| {
+            u32::from_ne_bytes([bytes[4 * i], bytes[4 * i + 1], bytes[4 * i + 2], bytes[4 * i + 3]])
+        };
+        let (sender, size, opcode) = (word(0), (word(1) >> 16) as usize, word(1) & 0xFFFF);
+        if sender == 1 && opcode == 0 {
+            return true;
+        }
+        if size < 8 || size > bytes.len() {
+            return false;
+        }
+        bytes = &bytes[size..];
+    }
+    false
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    // like a regular rust program, don't get killed when writing to a closed socket
+    unsafe { signal(Signal::SIGPIPE, SigHandler::SigIgn) }.unwrap();
+    let live = Rc::new(Cell::new(0));
+
+    let mut display = Display::new();
+    global!(display, live, wl_compositor::WlCompositor, 4, compositor);
+    global!(display, live, wl_subcompositor::WlSubcompositor, 1, subcompositor);
+    global!(display, live, wl_shm::WlShm, 1, shm);
+    global!(display, live, wl_seat::WlSeat, 6, seat);
+    global!(display, live, wl_output::WlOutput, 3, noop);
+    global!(display, live, wl_data_device_manager::WlDataDeviceManager, 3, data_device_manager);
+    global!(display, live, wl_shell::WlShell, 1, shell);
+    global!(display, live, xdg_wm_base::XdgWmBase, 3, wm_base);
+
+    let (client_end, server_end) = UnixStream::pair().unwrap();
+    let client = unsafe { display.create_client(server_end.into_raw_fd(), &mut ()) };
+
+    // send the input, with its fds attached to the first chunk
+    let files: Vec<File> = (0..data[0] % 4).map(|_| File::open("/dev/null").unwrap()).collect();
+    let fds: Vec<RawFd> = files.iter().map(|f| f.as_raw_fd()).collect();
+    let socket = unsafe { Socket::from_raw_fd(client_end.into_raw_fd()) };
+    let mut fds = &fds[..];
+    for chunk in data[1..].chunks(MAX_BYTES_OUT) {
+        if socket.send_msg(chunk, fds).is_err() {
+            break;
+        }
+        fds = &[];
+    }
+
+    display.dispatch(Duration::from_millis(0), &mut ()).unwrap();
+    display.flush_clients(&mut ());
+
+    // a killed client must have been told why
+    if !client.alive() {
+        let mut received = vec![0; 0x10000];
+        let mut bytes = Vec::new();
+        let mut in_fds = [0; 28];
+        while let Ok((n, nfds)) = socket.rcv_msg(&mut received, &mut in_fds) {
+            if n == 0 {
+                break;
+            }
+            bytes.extend_from_slice(&received[..n]);
+            in_fds[..nfds].iter().for_each(|&fd| close(fd));
+        }
+        assert!(contains_protocol_error(&bytes), "The client was killed without a protocol error.");
+    }
+
+    // disconnect, all the objects of the client must be destroyed
+    drop(socket);
+    display.dispatch(Duration::from_millis(0), &mut ()).unwrap();
+    display.flush_clients(&mut ());
+    assert!(!client.alive());
+    assert_eq!(live.get(), 0, "Some objects outlived their client.");
+});
@@ -22,4 +22,26 @@ fn main() {
     let globals = manager.list();
     assert!(globals.len() == 1);
     assert_eq!(globals[0], (1, "wl_output".into(), 1));
+
+    // an absolute WAYLAND_DISPLAY does not require XDG_RUNTIME_DIR
+    let mut socket_path: ::std::path::PathBuf =
+        ::std::env::var_os("XDG_RUNTIME_DIR").unwrap().into();
+    socket_path.push(&server.socket_name);
+    ::std::env::remove_var("XDG_RUNTIME_DIR");
+
+    assert!(wayc::Display::connect_to_env().is_err());
+
+    ::std::env::set_var("WAYLAND_DISPLAY", &socket_path);
+
+    let mut client = TestClient::new_auto();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // as well as connecting to an explicit path
+    ::std::env::remove_var("WAYLAND_DISPLAY");
+    let display = wayc::Display::connect_to_path(&socket_path).unwrap();
+    let event_queue = display.create_event_queue();
+    let display_proxy = (*display).clone().attach(event_queue.token());
+    let mut client =
+        TestClient { display: ::std::sync::Arc::new(display), display_proxy, event_queue };
+    roundtrip(&mut client, &mut server).unwrap();
 }
@@ -1,6 +1,6 @@
 mod helpers;
 
-use helpers::{ways, TestClient};
+use helpers::{ways, TestClient, TestServer};
 
 use std::cell::Cell;
 use std::ffi::OsStr;
@@ -109,3 +109,30 @@ fn client_dispatch() {
 
     server_thread.join().unwrap();
 }
+
+#[test]
+fn client_dispatch_timeout() {
+    let mut server = TestServer::new();
+    let mut client = TestClient::new(&server.socket_name);
+
+    // the server is not dispatching, so nothing will come
+    let err = client
+        .event_queue
+        .dispatch_timeout(&mut (), |_, _, _| unreachable!(), Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+    let err = client
+        .event_queue
+        .sync_roundtrip_timeout(&mut (), |_, _, _| unreachable!(), Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+    // once the server answers, the connection is still usable
+    server.answer();
+    let dispatched = client
+        .event_queue
+        .dispatch_timeout(&mut (), |_, _, _| unreachable!(), Duration::from_secs(1))
+        .unwrap();
+    assert!(dispatched > 0);
+}
@@ -0,0 +1,177 @@
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_compositor::{
+    Request as CompositorRequest, WlCompositor as ServerCompositor,
+};
+#[cfg(not(feature = "client_native"))]
+use ways::protocol::wl_shell::WlShell as ServerShell;
+
+use wayc::protocol::wl_compositor;
+#[cfg(not(feature = "client_native"))]
+use wayc::protocol::wl_shell;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+// a compositor global counting the surfaces created by the client
+fn insert_compositor(server: &mut TestServer) -> Rc<Cell<usize>> {
+    let surfaces = Rc::new(Cell::new(0));
+    let surfaces2 = surfaces.clone();
+    server.display.create_global::<ServerCompositor, _>(
+        1,
+        ways::Filter::new(move |(compositor, _): (ways::Main<ServerCompositor>, u32), _, _| {
+            let surfaces = surfaces2.clone();
+            compositor.quick_assign(move |_, request, _| {
+                if let CompositorRequest::CreateSurface { id } = request {
+                    id.quick_assign(|_, _, _| {});
+                    surfaces.set(surfaces.get() + 1);
+                }
+            });
+        }),
+    );
+    surfaces
+}
+
+#[test]
+fn flush_policy_threshold() {
+    let mut server = TestServer::new();
+    let surfaces = insert_compositor(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    client.display.set_flush_policy(wayc::FlushPolicy::Threshold {
+        messages: 3,
+        deadline: Duration::from_secs(3600),
+    });
+
+    let _s1 = compositor.create_surface();
+    let _s2 = compositor.create_surface();
+    server.answer();
+    assert_eq!(surfaces.get(), 0);
+
+    let _s3 = compositor.create_surface();
+    server.answer();
+    assert_eq!(surfaces.get(), 3);
+
+    // an expired deadline flushes at the next request
+    client.display.set_flush_policy(wayc::FlushPolicy::Threshold {
+        messages: 100,
+        deadline: Duration::from_millis(0),
+    });
+    let _s4 = compositor.create_surface();
+    server.answer();
+    assert_eq!(surfaces.get(), 4);
+}
+
+#[test]
+fn flush_policy_after_dispatch() {
+    let mut server = TestServer::new();
+    let surfaces = insert_compositor(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the default manual policy does not flush
+    let _s1 = compositor.create_surface();
+    client.event_queue.dispatch_pending(&mut (), |_, _, _| {}).unwrap();
+    server.answer();
+    assert_eq!(surfaces.get(), 0);
+
+    client.display.set_flush_policy(wayc::FlushPolicy::AfterDispatch);
+    let _s2 = compositor.create_surface();
+    server.answer();
+    assert_eq!(surfaces.get(), 0);
+    client.event_queue.dispatch_pending(&mut (), |_, _, _| {}).unwrap();
+    server.answer();
+    assert_eq!(surfaces.get(), 2);
+}
+
+#[test]
+fn flush_batch() {
+    let mut server = TestServer::new();
+    let surfaces = insert_compositor(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    client.display.set_flush_policy(wayc::FlushPolicy::Threshold {
+        messages: 1,
+        deadline: Duration::from_secs(3600),
+    });
+
+    #[cfg(not(feature = "client_native"))]
+    let flushes = client.display.stats().unwrap().socket.flushes;
+
+    let created = client.display.batch(|| {
+        let mut created = client
+            .display
+            .batch(|| (0..5).map(|_| compositor.create_surface()).collect::<Vec<_>>());
+        // the nested batch did not flush
+        server.answer();
+        assert_eq!(surfaces.get(), 0);
+        created.push(compositor.create_surface());
+        created
+    });
+    assert_eq!(created.len(), 6);
+
+    server.answer();
+    assert_eq!(surfaces.get(), 6);
+
+    // all the requests were sent at once, the buffer was empty when the batch began
+    #[cfg(not(feature = "client_native"))]
+    assert_eq!(client.display.stats().unwrap().socket.flushes, flushes + 1);
+}
+
+// the rust implementation flushes by itself when its buffer is full
+#[cfg(not(feature = "client_native"))]
+#[test]
+fn flush_policy_full_buffer() {
+    let mut server = TestServer::new();
+    insert_compositor(&mut server);
+    server.display.create_global::<ServerShell, _>(
+        1,
+        ways::Filter::new(|(shell, _): (ways::Main<ServerShell>, u32), _, _| {
+            shell.quick_assign(|_, _, _| {});
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+    let shell = manager.instantiate_exact::<wl_shell::WlShell>(1).unwrap();
+    let surface = compositor.create_surface();
+    let shell_surface = shell.get_shell_surface(&surface);
+    client.display.flush().unwrap();
+
+    client.display.set_flush_policy(wayc::FlushPolicy::Threshold {
+        messages: 2,
+        deadline: Duration::from_secs(3600),
+    });
+
+    let title = "a".repeat(3000);
+    let flushes = client.display.stats().unwrap().socket.flushes;
+    shell_surface.set_title(title.clone());
+    assert_eq!(client.display.stats().unwrap().socket.flushes, flushes);
+    // the buffer can't hold a second title and is flushed to make room, the
+    // threshold then only counts the request buffered since
+    shell_surface.set_title(title);
+    assert_eq!(client.display.stats().unwrap().socket.flushes, flushes + 1);
+}
@@ -0,0 +1,208 @@
+mod helpers;
+
+use helpers::{wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_compositor::WlCompositor as ServerCompositor;
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+use wayc::protocol::{wl_compositor, wl_output};
+
+use std::cell::Cell;
+#[cfg(not(feature = "client_native"))]
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+
+// a roundtrip without the sleeps of the generic helper, to run many of them
+fn quick_roundtrip(client: &mut TestClient, server: &mut TestServer) -> io::Result<()> {
+    let done = Rc::new(Cell::new(false));
+    let done2 = done.clone();
+    client.display_proxy.sync().quick_assign(move |_, _, _| done2.set(true));
+    while !done.get() {
+        client.display.flush()?;
+        server.answer();
+        match client.event_queue.dispatch_timeout(&mut (), |_, _, _| {}, Duration::from_millis(100))
+        {
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {}
+            ret => {
+                ret?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn recycled_id_with_pending_events() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(
+        3,
+        ways::Filter::new(|(output, _): (ways::Main<ServerOutput>, u32), _, _| {
+            output.quick_assign(|_, _, _| {});
+            output.scale(2);
+            output.done();
+        }),
+    );
+    server.display.create_global::<ServerCompositor, _>(1, ways::Filter::new(|_: (_, _), _, _| {}));
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    let mut other_queue = client.display.create_event_queue();
+
+    quick_roundtrip(&mut client, &mut server).unwrap();
+
+    let received = Rc::new(Cell::new(0));
+    let mut compositors = Vec::new();
+    for _ in 0..100 {
+        let output = manager.instantiate_exact::<wl_output::WlOutput>(3).unwrap();
+        output.as_ref().set_queue(other_queue.token());
+        let received2 = received.clone();
+        output.quick_assign(move |_, _, _| received2.set(received2.get() + 1));
+        #[cfg(not(feature = "client_native"))]
+        let output_id = output.as_ref().id();
+
+        // read the events of the output without dispatching them
+        client.display.flush().unwrap();
+        server.answer();
+        other_queue.prepare_read().unwrap().read_events().unwrap();
+
+        // destroy the output, and let the server release its id
+        output.release();
+        quick_roundtrip(&mut client, &mut server).unwrap();
+
+        // a new object of an other interface reuses the id
+        let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+        #[cfg(not(feature = "client_native"))]
+        assert_eq!(compositor.as_ref().id(), output_id);
+        compositors.push(compositor);
+
+        // the pending events of the destroyed output are discarded
+        other_queue.dispatch_pending(&mut (), |_, _, _| panic!("Unexpected event.")).unwrap();
+        assert_eq!(received.get(), 0);
+    }
+
+    quick_roundtrip(&mut client, &mut server).unwrap();
+    assert!(client.display.protocol_error().is_none());
+    #[cfg(not(feature = "client_native"))]
+    {
+        // the display, the registry and the compositors
+        let counts = client.display.object_counts();
+        assert_eq!(counts, wayc::ObjectCounts { live: 102, zombies: 0 });
+    }
+}
+
+#[cfg(not(feature = "client_native"))]
+#[test]
+fn zombie_objects() {
+    let mut server = TestServer::new();
+    let server_output = Rc::new(RefCell::new(None));
+    let server_output2 = server_output.clone();
+    server.display.create_global::<ServerOutput, _>(
+        3,
+        ways::Filter::new(move |(output, _): (ways::Main<ServerOutput>, u32), _, _| {
+            output.quick_assign(|_, _, _| {});
+            *server_output2.borrow_mut() = Some(output);
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    quick_roundtrip(&mut client, &mut server).unwrap();
+
+    let output = manager.instantiate_exact::<wl_output::WlOutput>(3).unwrap();
+    output.quick_assign(|_, _, _| panic!("Unexpected event."));
+
+    quick_roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(client.display.object_counts(), wayc::ObjectCounts { live: 3, zombies: 0 });
+
+    // the object remains a zombie until the server releases its id
+    output.release();
+    assert_eq!(client.display.object_counts(), wayc::ObjectCounts { live: 2, zombies: 1 });
+
+    // events sent by the server before it processes the destruction are ignored
+    server_output.borrow().as_ref().unwrap().scale(2);
+    server_output.borrow().as_ref().unwrap().done();
+    server.display.flush_clients(&mut ());
+
+    quick_roundtrip(&mut client, &mut server).unwrap();
+    assert!(client.display.protocol_error().is_none());
+    assert_eq!(client.display.object_counts(), wayc::ObjectCounts { live: 2, zombies: 0 });
+}
+
+#[cfg(not(feature = "client_native"))]
+#[test]
+fn exhausted_ids() {
+    use wayc::protocol::wl_region;
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerCompositor, _>(
+        1,
+        ways::Filter::new(|(compositor, _): (ways::Main<ServerCompositor>, u32), _, _| {
+            compositor.quick_assign(|_, request, _| {
+                if let ways::protocol::wl_compositor::Request::CreateRegion { id } = request {
+                    id.quick_assign(|_, _, _| {});
+                }
+            });
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    quick_roundtrip(&mut client, &mut server).unwrap();
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+
+    // the display, the registry and the compositor use the 3 first ids
+    client.display.set_object_id_limit(16);
+    let mut regions = Vec::new();
+    for _ in 0..50 {
+        while let Ok(region) = compositor
+            .as_ref()
+            .try_send::<wl_region::WlRegion>(wl_compositor::Request::CreateRegion {}, None)
+        {
+            regions.push(region.unwrap());
+        }
+        assert_eq!(regions.len(), 13);
+        assert!(regions.iter().all(|region| region.as_ref().id() <= 16));
+        assert_eq!(
+            compositor
+                .as_ref()
+                .try_send::<wl_region::WlRegion>(wl_compositor::Request::CreateRegion {}, None),
+            Err(wayc::SendError::IdsExhausted)
+        );
+        // without `try_send()`, the object is inert
+        let inert = compositor.create_region();
+        assert!(!inert.as_ref().is_alive());
+        assert_eq!(inert.as_ref().id(), 0);
+
+        // the ids of destroyed objects are not reused until the server releases them
+        for region in regions.drain(..8) {
+            region.destroy();
+        }
+        assert_eq!(client.display.object_counts(), wayc::ObjectCounts { live: 8, zombies: 8 });
+        assert!(compositor
+            .as_ref()
+            .try_send::<wl_region::WlRegion>(wl_compositor::Request::CreateRegion {}, None)
+            .is_err());
+
+        // a roundtrip needs an id for its callback, read the events directly
+        while client.display.object_counts().zombies > 0 {
+            client.display.flush().unwrap();
+            server.answer();
+            match client.event_queue.dispatch_timeout(
+                &mut (),
+                |_, _, _| {},
+                Duration::from_millis(10),
+            ) {
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {}
+                ret => {
+                    ret.unwrap();
+                }
+            }
+        }
+    }
+
+    quick_roundtrip(&mut client, &mut server).unwrap();
+    assert!(client.display.protocol_error().is_none());
+}
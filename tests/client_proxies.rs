@@ -9,6 +9,9 @@ use wayc::protocol::wl_compositor;
 use wayc::protocol::wl_output;
 use wayc::Proxy;
 
+use std::cell::RefCell;
+use std::rc::Rc;
+
 #[test]
 fn proxy_equals() {
     let mut server = TestServer::new();
@@ -179,6 +182,35 @@ fn dead_proxies() {
     assert!(!output2.as_ref().is_alive());
 }
 
+#[test]
+fn weak_proxies() {
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(3, ways::Filter::new(|_: (_, _), _, _| {}));
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let output = manager.instantiate_exact::<wl_output::WlOutput>(3).unwrap();
+    output.as_ref().user_data().set_threadsafe(|| 0xDEADBEEFusize);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let weak = output.as_ref().downgrade();
+    let weak2 = weak.clone();
+
+    let upgraded = weak.upgrade().unwrap();
+    assert!(upgraded == *output.as_ref());
+    assert!(upgraded.user_data().get::<usize>() == Some(&0xDEADBEEF));
+
+    // kill the output
+    output.release();
+
+    assert!(weak.upgrade().is_none());
+    assert!(weak2.upgrade().is_none());
+}
+
 #[test]
 fn dead_connection() {
     fn get_output() -> wl_output::WlOutput {
@@ -199,3 +231,57 @@ fn dead_connection() {
     // and not a crash into freed memory
     output.release();
 }
+
+#[test]
+fn proxy_set_queue() {
+    let mut server = TestServer::new();
+    let server_output = Rc::new(RefCell::new(None));
+    let server_output2 = server_output.clone();
+    server.display.create_global::<ServerOutput, _>(
+        3,
+        ways::Filter::new(move |(output, _): (ways::Main<ServerOutput>, u32), _, _| {
+            output.scale(1);
+            output.scale(2);
+            output.done();
+            *server_output2.borrow_mut() = Some(output);
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received2 = received.clone();
+    let output = manager.instantiate_exact::<wl_output::WlOutput>(3).unwrap();
+    output.quick_assign(move |_, event, _| match event {
+        wl_output::Event::Scale { factor } => received2.borrow_mut().push(factor),
+        wl_output::Event::Done => received2.borrow_mut().push(0),
+        _ => {}
+    });
+
+    let mut other_queue = client.display.create_event_queue();
+
+    // read the events of the output without dispatching them
+    client.display.flush().unwrap();
+    server.answer();
+    other_queue.prepare_read().unwrap().read_events().unwrap();
+
+    output.as_ref().set_queue(other_queue.token());
+
+    // the already read events are no longer dispatched by the original queue
+    client.event_queue.dispatch_pending(&mut (), |_, _, _| {}).unwrap();
+    assert!(received.borrow().is_empty());
+
+    other_queue.dispatch_pending(&mut (), |_, _, _| {}).unwrap();
+    assert_eq!(*received.borrow(), vec![1, 2, 0]);
+
+    // and new events go to the new queue as well
+    received.borrow_mut().clear();
+    server_output.borrow().as_ref().unwrap().scale(3);
+    roundtrip(&mut client, &mut server).unwrap();
+    assert!(received.borrow().is_empty());
+    other_queue.dispatch_pending(&mut (), |_, _, _| {}).unwrap();
+    assert_eq!(*received.borrow(), vec![3]);
+}
@@ -0,0 +1,156 @@
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::{wl_buffer as server_buffer, wl_shm as server_shm, wl_shm_pool};
+
+use wayc::protocol::wl_shm;
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::FromRawFd;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct ShmState {
+    file: Option<File>,
+    size: i32,
+    buffers: Vec<(ways::Main<server_buffer::WlBuffer>, i32)>,
+    destroyed: usize,
+}
+
+fn insert_shm(server: &mut TestServer) -> Rc<RefCell<ShmState>> {
+    let state = Rc::new(RefCell::new(ShmState::default()));
+    let state2 = state.clone();
+    server.display.create_global::<server_shm::WlShm, _>(
+        1,
+        ways::Filter::new(move |(shm, _): (ways::Main<server_shm::WlShm>, u32), _, _| {
+            let state = state2.clone();
+            shm.quick_assign(move |_, request, _| {
+                if let server_shm::Request::CreatePool { id, fd, size } = request {
+                    {
+                        let mut state = state.borrow_mut();
+                        state.file = Some(unsafe { File::from_raw_fd(fd) });
+                        state.size = size;
+                    }
+                    let state = state.clone();
+                    id.quick_assign(move |_, request, _| match request {
+                        wl_shm_pool::Request::CreateBuffer { id, offset, .. } => {
+                            let state2 = state.clone();
+                            id.quick_assign(move |_, _, _| state2.borrow_mut().destroyed += 1);
+                            state.borrow_mut().buffers.push((id, offset));
+                        }
+                        wl_shm_pool::Request::Resize { size } => state.borrow_mut().size = size,
+                        _ => {}
+                    });
+                }
+            });
+        }),
+    );
+    state
+}
+
+#[test]
+fn shm_pool_buffers() {
+    let mut server = TestServer::new();
+    let state = insert_shm(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
+    let mut pool = wayc::ShmPool::new(&shm, 1024).unwrap();
+
+    let buffer1 = pool.create_buffer(8, 8, 32, wl_shm::Format::Argb8888).unwrap();
+    let buffer2 = pool.create_buffer(8, 8, 32, wl_shm::Format::Argb8888).unwrap();
+    assert_eq!(buffer1.offset(), 0);
+    assert_eq!(buffer2.offset(), 256);
+    assert_eq!(pool.len(), 1024);
+
+    for byte in pool.canvas(&buffer1).unwrap() {
+        *byte = 0xAB;
+    }
+
+    roundtrip(&mut client, &mut server).unwrap();
+    {
+        let state = state.borrow();
+        assert_eq!(state.size, 1024);
+        assert_eq!(state.buffers.iter().map(|&(_, o)| o).collect::<Vec<_>>(), vec![0, 256]);
+        // the server sees the contents written by the client
+        let mut contents = [0u8; 256];
+        state.file.as_ref().unwrap().read_exact_at(&mut contents, 0).unwrap();
+        assert!(contents.iter().all(|&b| b == 0xAB));
+    }
+
+    // a buffer too large for the remaining space grows the pool
+    let buffer3 = pool.create_buffer(16, 16, 64, wl_shm::Format::Argb8888).unwrap();
+    assert_eq!(buffer3.offset(), 512);
+    assert!(pool.len() >= 1536);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow_mut().size as usize, pool.len());
+
+    // busy buffers cannot be accessed until released
+    buffer1.mark_busy();
+    buffer2.mark_busy();
+    assert!(pool.canvas(&buffer1).is_none());
+
+    state.borrow_mut().buffers[0].0.release();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert!(!buffer1.is_busy());
+    assert!(buffer2.is_busy());
+    assert!(pool.canvas(&buffer1).is_some());
+
+    // dropping a busy buffer only frees it once released
+    drop(buffer2);
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow_mut().destroyed, 0);
+
+    state.borrow_mut().buffers[1].0.release();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the freed memory is reused
+    let buffer4 = pool.create_buffer(8, 8, 32, wl_shm::Format::Argb8888).unwrap();
+    assert_eq!(buffer4.offset(), 256);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow_mut().destroyed, 1);
+
+    drop(buffer1);
+    let buffer5 = pool.create_buffer(4, 4, 16, wl_shm::Format::Xrgb8888).unwrap();
+    assert_eq!(buffer5.offset(), 0);
+    assert_eq!(buffer5.format(), wl_shm::Format::Xrgb8888);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow_mut().destroyed, 2);
+}
+
+#[test]
+fn shm_pool_invalid_buffers() {
+    let mut server = TestServer::new();
+    let _state = insert_shm(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
+    let mut pool = wayc::ShmPool::new(&shm, 1024).unwrap();
+
+    let invalid = |result: std::io::Result<wayc::ShmBuffer>| {
+        result.err().map(|e| e.kind()) == Some(std::io::ErrorKind::InvalidInput)
+    };
+    assert!(invalid(pool.create_buffer(0, 8, 32, wl_shm::Format::Argb8888)));
+    assert!(invalid(pool.create_buffer(8, -1, 32, wl_shm::Format::Argb8888)));
+    // the stride is in bytes, not in pixels
+    assert!(invalid(pool.create_buffer(8, 8, 8, wl_shm::Format::Argb8888)));
+    assert!(invalid(pool.create_buffer(8, 8, 31, wl_shm::Format::Xrgb8888)));
+    assert!(invalid(pool.create_buffer(8, 8, 23, wl_shm::Format::Rgb888)));
+    assert!(pool.create_buffer(8, 8, 24, wl_shm::Format::Rgb888).is_ok());
+    assert!(pool.create_buffer(8, 8, 16, wl_shm::Format::Rgb565).is_ok());
+    assert!(pool.create_buffer(8, 8, 32, wl_shm::Format::Argb8888).is_ok());
+}
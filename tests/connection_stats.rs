@@ -0,0 +1,100 @@
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_output::WlOutput as ServerOutput;
+
+use wayc::protocol::wl_output;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// the statistics are only collected by the rust implementations
+#[cfg(not(any(feature = "client_native", feature = "server_native")))]
+#[test]
+fn connection_stats() {
+    let mut server = TestServer::new();
+    let clients = Rc::new(RefCell::new(Vec::new()));
+    let clients2 = clients.clone();
+    server.display.create_global::<ServerOutput, _>(
+        3,
+        ways::Filter::new(move |(output, _): (ways::Main<ServerOutput>, u32), _, _| {
+            output.quick_assign(|_, _, _| {});
+            for scale in 1..4 {
+                output.scale(scale);
+            }
+            output.done();
+            clients2.borrow_mut().push(output.as_ref().client().unwrap());
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let output1 = manager.instantiate_exact::<wl_output::WlOutput>(3).unwrap();
+    let output2 = manager.instantiate_exact::<wl_output::WlOutput>(3).unwrap();
+    output1.quick_assign(|_, _, _| {});
+    output2.quick_assign(|_, _, _| {});
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let client_stats = client.display.stats().unwrap();
+    let server_stats = clients.borrow()[0].stats().unwrap();
+
+    // everything sent by one side was received by the other
+    assert_eq!(client_stats.socket.messages_sent, server_stats.socket.messages_received);
+    assert_eq!(client_stats.socket.bytes_sent, server_stats.socket.bytes_received);
+    assert_eq!(server_stats.socket.messages_sent, client_stats.socket.messages_received);
+    assert_eq!(server_stats.socket.bytes_sent, client_stats.socket.bytes_received);
+    // get_registry, 2 binds and 2 syncs
+    assert_eq!(client_stats.socket.messages_sent, 5);
+    assert!(client_stats.socket.flushes >= 2);
+
+    assert_eq!(client_stats.live_objects.get("wl_output"), Some(&2));
+    assert_eq!(client_stats.live_objects.get("wl_registry"), Some(&1));
+    assert_eq!(server_stats.live_objects.get("wl_output"), Some(&2));
+    assert_eq!(server_stats.live_objects.get("wl_registry"), Some(&1));
+
+    // the events of both outputs were pending at the same time
+    assert!(client.event_queue.max_depth().unwrap() >= 8);
+
+    output1.release();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    assert_eq!(client.display.stats().unwrap().live_objects.get("wl_output"), Some(&1));
+    assert_eq!(clients.borrow()[0].stats().unwrap().live_objects.get("wl_output"), Some(&1));
+
+    // no stats for dead clients
+    clients.borrow()[0].kill();
+    assert!(clients.borrow()[0].stats().is_none());
+}
+
+#[test]
+fn stats_availability() {
+    let mut server = TestServer::new();
+    let clients = Rc::new(RefCell::new(Vec::new()));
+    let clients2 = clients.clone();
+    server.display.create_global::<ServerOutput, _>(
+        3,
+        ways::Filter::new(move |(output, _): (ways::Main<ServerOutput>, u32), _, _| {
+            output.quick_assign(|_, _, _| {});
+            clients2.borrow_mut().push(output.as_ref().client().unwrap());
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    manager.instantiate_exact::<wl_output::WlOutput>(3).unwrap().quick_assign(|_, _, _| {});
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the system libraries do not expose them
+    let client_rust = cfg!(not(feature = "client_native"));
+    let server_rust = cfg!(not(feature = "server_native"));
+    assert_eq!(client.display.stats().is_some(), client_rust);
+    assert_eq!(client.event_queue.max_depth().is_some(), client_rust);
+    assert_eq!(clients.borrow()[0].stats().is_some(), server_rust);
+}
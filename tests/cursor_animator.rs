@@ -0,0 +1,213 @@
+extern crate tempfile;
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::{wl_callback, wl_compositor, wl_surface};
+
+use wayland_cursor::{Cursor, CursorAnimator, CursorTheme, CursorThemeBuilder};
+
+#[derive(Default)]
+struct SurfaceState {
+    // offsets of the attached buffers
+    attaches: Vec<(i32, i32)>,
+    frames: Vec<ways::Main<wl_callback::WlCallback>>,
+    frame_requests: usize,
+}
+
+// a compositor global recording the requests of its surfaces
+fn insert_compositor(server: &mut TestServer) -> Rc<RefCell<SurfaceState>> {
+    let state = Rc::new(RefCell::new(SurfaceState::default()));
+    let state2 = state.clone();
+    server.display.create_global::<wl_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(
+            move |(compositor, _): (ways::Main<wl_compositor::WlCompositor>, u32), _, _| {
+                let state = state2.clone();
+                compositor.quick_assign(move |_, request, _| {
+                    if let wl_compositor::Request::CreateSurface { id } = request {
+                        let state = state.clone();
+                        id.quick_assign(move |_, request, _| match request {
+                            wl_surface::Request::Attach { x, y, .. } => {
+                                state.borrow_mut().attaches.push((x, y))
+                            }
+                            wl_surface::Request::Frame { callback } => {
+                                let mut state = state.borrow_mut();
+                                state.frame_requests += 1;
+                                state.frames.push(callback);
+                            }
+                            _ => {}
+                        });
+                    }
+                });
+            },
+        ),
+    );
+    state
+}
+
+// write a cursor file made of 4x4 frames with the given hotspots and delays
+fn write_cursor(dir: &Path, name: &str, frames: &[((u32, u32), u32)]) {
+    let mut data = Vec::new();
+    let mut push = |value: u32| data.extend_from_slice(&value.to_le_bytes());
+    let chunk_size = 36 + 4 * 4 * 4;
+    // "Xcur", header size, version and number of entries of the table of contents
+    for &value in &[0x7275_6358, 16, 0x1_0000, frames.len() as u32] {
+        push(value);
+    }
+    for i in 0..frames.len() as u32 {
+        push(0xfffd_0002);
+        push(24);
+        push(16 + 12 * frames.len() as u32 + i * chunk_size);
+    }
+    for &((xhot, yhot), delay) in frames {
+        for &value in &[36, 0xfffd_0002, 24, 1, 4, 4, xhot, yhot, delay] {
+            push(value);
+        }
+        for _ in 0..16 {
+            push(0xffff_ffff);
+        }
+    }
+    let cursors = dir.join("cursors");
+    fs::create_dir_all(&cursors).unwrap();
+    fs::write(cursors.join(name), data).unwrap();
+}
+
+struct Setup {
+    server: TestServer,
+    client: TestClient,
+    state: Rc<RefCell<SurfaceState>>,
+    surface: wayc::Attached<wayc::protocol::wl_surface::WlSurface>,
+    theme: CursorTheme,
+    _dir: tempfile::TempDir,
+}
+
+fn setup() -> Setup {
+    let mut server = TestServer::new();
+    ways::init_shm_global(&mut server.display, Vec::new());
+    let state = insert_compositor(&mut server);
+
+    // the first frame of `animated` is shown for 1ms, the second for 1000s
+    let dir = tempfile::tempdir().unwrap();
+    let theme_dir = dir.path().join("wayland-rs-test");
+    write_cursor(&theme_dir, "animated", &[((1, 1), 1), ((3, 2), 1_000_000)]);
+    write_cursor(&theme_dir, "other", &[((2, 0), 1), ((0, 3), 1_000_000)]);
+    write_cursor(&theme_dir, "static", &[((1, 2), 0)]);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wayc::protocol::wl_shm::WlShm>(1).unwrap();
+    let compositor =
+        manager.instantiate_exact::<wayc::protocol::wl_compositor::WlCompositor>(1).unwrap();
+    let surface = compositor.create_surface();
+    let theme = CursorThemeBuilder::new(24)
+        .name("wayland-rs-test")
+        .ignore_env()
+        .search_path(dir.path())
+        .build(&shm);
+
+    Setup { server, client, state, surface: (*surface).clone(), theme, _dir: dir }
+}
+
+fn get_cursor(theme: &mut CursorTheme, name: &str) -> Cursor {
+    let cursor = theme.get_cursor(name).unwrap().clone();
+    assert!(!cursor.is_fallback());
+    cursor
+}
+
+// complete the pending frame callbacks
+fn send_frames(state: &Rc<RefCell<SurfaceState>>) {
+    for callback in state.borrow_mut().frames.drain(..) {
+        callback.done(0);
+    }
+}
+
+#[test]
+fn frame_callbacks() {
+    let Setup { mut server, mut client, state, surface, mut theme, _dir } = setup();
+    let animated = get_cursor(&mut theme, "animated");
+    assert_eq!(animated.image_count(), 2);
+
+    let mut animator = CursorAnimator::new(surface);
+    animator.set_cursor(&animated);
+    assert_eq!(animator.hotspot(), (1, 1));
+    roundtrip(&mut client, &mut server).unwrap();
+    // the first frame is attached so that the hotspot moves from (0, 0) to (1, 1)
+    assert_eq!(state.borrow().attaches, vec![(-1, -1)]);
+    assert_eq!(state.borrow().frame_requests, 1);
+
+    // the first frame is over, the second one is attached keeping the hotspot in place
+    send_frames(&state);
+    // one roundtrip to deliver the callback, one to receive the new frame
+    roundtrip(&mut client, &mut server).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches, vec![(-1, -1), (-2, -1)]);
+    assert_eq!(animator.hotspot(), (3, 2));
+    assert_eq!(state.borrow().frame_requests, 2);
+
+    // the frame callbacks of the previous cursor are ignored once it is changed
+    let other = get_cursor(&mut theme, "other");
+    animator.set_cursor(&other);
+    assert_eq!(animator.hotspot(), (2, 0));
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches, vec![(-1, -1), (-2, -1), (1, 2)]);
+    assert_eq!(state.borrow().frame_requests, 3);
+
+    let stale = state.borrow_mut().frames.remove(0);
+    stale.done(0);
+    roundtrip(&mut client, &mut server).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches.len(), 3);
+    assert_eq!(animator.hotspot(), (2, 0));
+
+    send_frames(&state);
+    roundtrip(&mut client, &mut server).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches[3..], [(2, -3)]);
+    assert_eq!(animator.hotspot(), (0, 3));
+
+    // static cursors do not request frame callbacks
+    let fixed = get_cursor(&mut theme, "static");
+    animator.set_cursor(&fixed);
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches[4..], [(-1, 1)]);
+    assert_eq!(state.borrow().frame_requests, 4);
+}
+
+#[test]
+fn timer() {
+    let Setup { mut server, mut client, state, surface, mut theme, _dir } = setup();
+    let animated = get_cursor(&mut theme, "animated");
+
+    let mut animator = CursorAnimator::with_timer(surface).unwrap();
+    // nothing happens before a cursor is set
+    animator.dispatch_timer().unwrap();
+
+    animator.set_cursor(&animated);
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches, vec![(-1, -1)]);
+
+    // the timer expired during the roundtrip
+    animator.dispatch_timer().unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches, vec![(-1, -1), (-2, -1)]);
+    assert_eq!(animator.hotspot(), (3, 2));
+
+    // the second frame lasts much longer
+    ::std::thread::sleep(Duration::from_millis(10));
+    animator.dispatch_timer().unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(state.borrow().attaches.len(), 2);
+
+    // the animation does not use frame callbacks
+    assert_eq!(state.borrow().frame_requests, 0);
+}
@@ -0,0 +1,112 @@
+extern crate tempfile;
+
+use std::fs;
+use std::path::Path;
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use wayland_cursor::{Cursor, CursorTheme, CursorThemeBuilder};
+
+// write a static cursor file with one square image per (size, hotspot) entry
+fn write_cursor(dir: &Path, name: &str, images: &[(u32, (u32, u32))]) {
+    let mut data = Vec::new();
+    let mut push = |value: u32| data.extend_from_slice(&value.to_le_bytes());
+    // "Xcur", header size, version and number of entries of the table of contents
+    for &value in &[0x7275_6358, 16, 0x1_0000, images.len() as u32] {
+        push(value);
+    }
+    let mut position = 16 + 12 * images.len() as u32;
+    for &(size, _) in images {
+        push(0xfffd_0002);
+        push(size);
+        push(position);
+        position += 36 + 4 * size * size;
+    }
+    for &(size, (xhot, yhot)) in images {
+        for &value in &[36, 0xfffd_0002, size, 1, size, size, xhot, yhot, 0] {
+            push(value);
+        }
+        for _ in 0..size * size {
+            push(0xffff_ffff);
+        }
+    }
+    let cursors = dir.join("cursors");
+    fs::create_dir_all(&cursors).unwrap();
+    fs::write(cursors.join(name), data).unwrap();
+}
+
+struct Setup {
+    server: TestServer,
+    client: TestClient,
+    theme: CursorTheme,
+    _dir: tempfile::TempDir,
+}
+
+fn setup() -> Setup {
+    let mut server = TestServer::new();
+    ways::init_shm_global(&mut server.display, Vec::new());
+
+    let dir = tempfile::tempdir().unwrap();
+    let theme_dir = dir.path().join("wayland-rs-test");
+    write_cursor(&theme_dir, "default", &[(24, (3, 6)), (48, (7, 13))]);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wayc::protocol::wl_shm::WlShm>(1).unwrap();
+    let theme = CursorThemeBuilder::new(24)
+        .name("wayland-rs-test")
+        .ignore_env()
+        .search_path(dir.path())
+        .build(&shm);
+
+    Setup { server, client, theme, _dir: dir }
+}
+
+fn get_cursor(theme: &mut CursorTheme, scale: u32) -> Cursor {
+    let cursor = theme.get_cursor_scaled("default", scale).unwrap().clone();
+    assert_eq!(cursor.location().unwrap().theme, "wayland-rs-test");
+    assert_eq!(cursor.scale(), scale);
+    cursor
+}
+
+fn buffer_id(cursor: &Cursor) -> u32 {
+    cursor[0].as_ref().id()
+}
+
+#[test]
+fn cursor_cache_per_scale() {
+    let Setup { mut server, mut client, mut theme, _dir } = setup();
+
+    let cursor_1x = get_cursor(&mut theme, 1);
+    assert_eq!(cursor_1x[0].dimensions(), (24, 24));
+    let cursor_2x = get_cursor(&mut theme, 2);
+    assert_eq!(cursor_2x[0].dimensions(), (48, 48));
+    assert_ne!(buffer_id(&cursor_1x), buffer_id(&cursor_2x));
+
+    // both scales are kept, asking for them again does not create new buffers
+    assert_eq!(buffer_id(&get_cursor(&mut theme, 1)), buffer_id(&cursor_1x));
+    assert_eq!(buffer_id(&get_cursor(&mut theme, 2)), buffer_id(&cursor_2x));
+    assert_eq!(buffer_id(&get_cursor(&mut theme, 1)), buffer_id(&cursor_1x));
+    roundtrip(&mut client, &mut server).unwrap();
+}
+
+#[test]
+fn surface_hotspot() {
+    let Setup { mut server, mut client, mut theme, _dir } = setup();
+
+    let cursor_1x = get_cursor(&mut theme, 1);
+    assert_eq!(cursor_1x[0].scale(), 1);
+    assert_eq!(cursor_1x[0].hotspot(), (3, 6));
+    assert_eq!(cursor_1x[0].surface_hotspot(), (3, 6));
+
+    // the hotspot is given in buffer pixels, and divided by the scale for the surface
+    let cursor_2x = get_cursor(&mut theme, 2);
+    assert_eq!(cursor_2x[0].scale(), 2);
+    assert_eq!(cursor_2x[0].hotspot(), (7, 13));
+    assert_eq!(cursor_2x[0].surface_hotspot(), (3, 6));
+    roundtrip(&mut client, &mut server).unwrap();
+}
@@ -41,6 +41,45 @@ fn resource_destructor_request() {
     assert!(*destructor_called.lock().unwrap());
 }
 
+#[test]
+fn resource_destructor_request_then_protocol_error() {
+    let destructor_called = Arc::new(Mutex::new(false));
+    let destructor_called_global = destructor_called.clone();
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(
+        3,
+        ways::Filter::new(move |(newo, _): (ways::Main<ServerOutput>, _), _, _| {
+            let destructor_called_resource = destructor_called_global.clone();
+            newo.quick_assign(|_, _, _| {});
+            newo.assign_destructor(ways::Filter::new(move |_: ways::Resource<_>, _, _| {
+                *destructor_called_resource.lock().unwrap() = true;
+            }));
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let output = manager.instantiate_exact::<WlOutput>(3).unwrap();
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the server reads the destructor request and a request to an invalid
+    // object at once, and kills the client
+    output.release();
+    client.display.flush().unwrap();
+    let invalid_request: [u32; 2] = [42, 8 << 16];
+    let bytes = unsafe { ::std::slice::from_raw_parts(invalid_request.as_ptr() as *const u8, 8) };
+    nix::unistd::write(client.display.get_connection_fd(), bytes).unwrap();
+
+    server.answer();
+
+    assert!(*destructor_called.lock().unwrap());
+}
+
 #[test]
 fn resource_destructor_cleanup() {
     let destructor_called = Arc::new(Mutex::new(false));
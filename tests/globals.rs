@@ -238,3 +238,126 @@ fn two_step_binding() {
 
     roundtrip(&mut client, &mut server).unwrap();
 }
+
+#[test]
+fn global_registry_tracking() {
+    use wayc::protocol::wl_output::WlOutput;
+    use wayc::GlobalUpdate;
+
+    let mut server = TestServer::new();
+    server.display.create_global::<ServerOutput, _>(2, ways::Filter::new(|_: (_, _), _, _| {}));
+    server.display.create_global::<ServerCompositor, _>(1, ways::Filter::new(|_: (_, _), _, _| {}));
+
+    let mut client = TestClient::new(&server.socket_name);
+    let registry = wayc::GlobalRegistry::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events2 = events.clone();
+    // the output already advertised is bound immediately
+    let outputs = registry.track::<WlOutput, _>(2, 3, move |update, _| {
+        let event = match update {
+            GlobalUpdate::New { name, global } => ("new", name, global.as_ref().version()),
+            GlobalUpdate::Removed { name, global } => ("removed", name, global.as_ref().version()),
+            GlobalUpdate::VersionTooLow { name, version } => ("too_low", name, version),
+        };
+        events2.lock().unwrap().push(event);
+    });
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs.first().unwrap().as_ref().version(), 2);
+    assert_eq!(&*events.lock().unwrap(), &[("new", 1, 2)]);
+
+    let output =
+        server.display.create_global::<ServerOutput, _>(3, ways::Filter::new(|_: (_, _), _, _| {}));
+    server.display.create_global::<ServerOutput, _>(1, ways::Filter::new(|_: (_, _), _, _| {}));
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs.get(3).is_some());
+    assert!(outputs.get(4).is_none());
+    assert_eq!(&events.lock().unwrap()[1..], &[("new", 3, 3), ("too_low", 4, 1)]);
+
+    // make sure the server has processed the bind before removing the global
+    roundtrip(&mut client, &mut server).unwrap();
+    output.destroy();
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs.get(3).is_none());
+    assert_eq!(&events.lock().unwrap()[3..], &[("removed", 3, 3)]);
+
+    // untracked globals are still available through the manager
+    assert_eq!(registry.global_manager().list().len(), 3);
+}
+
+#[test]
+fn disable_global() {
+    use wayc::protocol::wl_output::WlOutput;
+
+    let mut server = TestServer::new();
+    let bound = Arc::new(Mutex::new(0));
+    let bound2 = bound.clone();
+    let output = server.display.create_global::<ServerOutput, _>(
+        1,
+        ways::Filter::new(move |_: (_, _), _, _| *bound2.lock().unwrap() += 1),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(manager.list().len(), 1);
+
+    // the client binds the global concurrently to its removal, the bind is honoured
+    output.disable();
+    output.disable();
+    manager.instantiate_exact::<WlOutput>(1).unwrap();
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(*bound.lock().unwrap(), 1);
+    assert_eq!(manager.list().len(), 0);
+
+    // new clients do not see it
+    let mut client2 = TestClient::new(&server.socket_name);
+    let manager2 = wayc::GlobalManager::new(&client2.display_proxy);
+    roundtrip(&mut client2, &mut server).unwrap();
+    assert_eq!(manager2.list().len(), 0);
+
+    output.destroy();
+    roundtrip(&mut client, &mut server).unwrap();
+}
+
+// the system library posts an error for binds of destroyed globals
+#[cfg(not(feature = "server_native"))]
+#[test]
+fn bind_destroyed_global() {
+    use wayc::protocol::wl_compositor::WlCompositor;
+
+    let mut server = TestServer::new();
+    let bound = Arc::new(Mutex::new(0));
+    let bound2 = bound.clone();
+    let compositor = server.display.create_global::<ServerCompositor, _>(
+        1,
+        ways::Filter::new(move |_: (_, _), _, _| *bound2.lock().unwrap() += 1),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the client binds the global concurrently to its destruction, it gets an inert object
+    compositor.destroy();
+    let compositor = manager.instantiate_exact::<WlCompositor>(1).unwrap();
+    let surface = compositor.create_surface();
+    surface.destroy();
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(*bound.lock().unwrap(), 0);
+    assert_eq!(manager.list().len(), 0);
+
+    // the object and its children can still be used
+    compositor.create_region().destroy();
+    roundtrip(&mut client, &mut server).unwrap();
+}
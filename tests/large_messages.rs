@@ -0,0 +1,119 @@
+// the message size limit is only configurable with the rust implementations
+#![cfg(not(any(feature = "client_native", feature = "server_native")))]
+
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_compositor::{
+    Request as CompositorRequest, WlCompositor as ServerCompositor,
+};
+use ways::protocol::wl_keyboard::WlKeyboard as ServerKeyboard;
+use ways::protocol::wl_seat::{Request as SeatRequest, WlSeat as ServerSeat};
+use ways::protocol::wl_surface::WlSurface as ServerSurface;
+
+use wayc::protocol::{wl_compositor, wl_keyboard, wl_seat};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Focus = Rc<RefCell<Option<(ServerKeyboard, ServerSurface)>>>;
+
+// globals storing the last created keyboard and surface
+fn insert_globals(server: &mut TestServer) -> Focus {
+    let keyboard = Rc::new(RefCell::new(None));
+    let surface = Rc::new(RefCell::new(None));
+    let focus = Rc::new(RefCell::new(None));
+    let (keyboard2, surface2) = (keyboard.clone(), surface.clone());
+    let (focus2, focus3) = (focus.clone(), focus.clone());
+    server.display.create_global::<ServerSeat, _>(
+        1,
+        ways::Filter::new(move |(seat, _): (ways::Main<ServerSeat>, u32), _, _| {
+            let keyboard = keyboard2.clone();
+            let surface = surface2.clone();
+            let focus = focus2.clone();
+            seat.quick_assign(move |_, request, _| {
+                if let SeatRequest::GetKeyboard { id } = request {
+                    id.quick_assign(|_, _, _| {});
+                    *keyboard.borrow_mut() = Some((*id).clone());
+                    if let Some(surface) = surface.borrow().clone() {
+                        *focus.borrow_mut() = Some(((*id).clone(), surface));
+                    }
+                }
+            });
+        }),
+    );
+    server.display.create_global::<ServerCompositor, _>(
+        1,
+        ways::Filter::new(move |(compositor, _): (ways::Main<ServerCompositor>, u32), _, _| {
+            let keyboard = keyboard.clone();
+            let surface = surface.clone();
+            let focus = focus3.clone();
+            compositor.quick_assign(move |_, request, _| {
+                if let CompositorRequest::CreateSurface { id } = request {
+                    id.quick_assign(|_, _, _| {});
+                    *surface.borrow_mut() = Some((*id).clone());
+                    if let Some(keyboard) = keyboard.borrow().clone() {
+                        *focus.borrow_mut() = Some((keyboard, (*id).clone()));
+                    }
+                }
+            });
+        }),
+    );
+    focus
+}
+
+fn focused_keyboard(
+    client: &mut TestClient,
+    server: &mut TestServer,
+) -> Rc<RefCell<Option<Vec<u8>>>> {
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(client, server).unwrap();
+
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+    let seat = manager.instantiate_exact::<wl_seat::WlSeat>(1).unwrap();
+    compositor.create_surface().quick_assign(|_, _, _| {});
+    let keys = Rc::new(RefCell::new(None));
+    let keys2 = keys.clone();
+    seat.get_keyboard().quick_assign(move |_, event, _| {
+        if let wl_keyboard::Event::Enter { keys, .. } = event {
+            *keys2.borrow_mut() = Some(keys);
+        }
+    });
+    roundtrip(client, server).unwrap();
+    keys
+}
+
+#[test]
+fn large_event() {
+    let mut server = TestServer::new();
+    let focus = insert_globals(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let keys = focused_keyboard(&mut client, &mut server);
+    client.display.set_max_message_size(0xFFFC);
+
+    let (keyboard, surface) = focus.borrow().clone().unwrap();
+    keyboard.as_ref().client().unwrap().set_max_message_size(0xFFFC);
+    keyboard.enter(1, &surface, vec![42; 20_000]);
+
+    roundtrip(&mut client, &mut server).unwrap();
+    assert_eq!(keys.borrow().as_ref().unwrap(), &vec![42; 20_000]);
+}
+
+#[test]
+fn large_event_rejected() {
+    let mut server = TestServer::new();
+    let focus = insert_globals(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let keys = focused_keyboard(&mut client, &mut server);
+
+    // only the server accepts large messages
+    let (keyboard, surface) = focus.borrow().clone().unwrap();
+    keyboard.as_ref().client().unwrap().set_max_message_size(0xFFFC);
+    keyboard.enter(1, &surface, vec![42; 20_000]);
+
+    assert!(roundtrip(&mut client, &mut server).is_err());
+    assert!(keys.borrow().is_none());
+}
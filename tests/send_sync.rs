@@ -7,12 +7,16 @@ fn ensure_both<I: Send + Sync>() {}
 fn send_sync_client() {
     ensure_both::<wayc::Display>();
     ensure_both::<wayc::Proxy<::wayc::protocol::wl_callback::WlCallback>>();
+    ensure_both::<wayc::WeakProxy<::wayc::protocol::wl_callback::WlCallback>>();
     ensure_both::<::wayc::protocol::wl_callback::WlCallback>();
 }
 
 #[test]
 fn send_sync_server() {
     ensure_both::<ways::Resource<::ways::protocol::wl_callback::WlCallback>>();
+    ensure_both::<ways::WeakResource<::ways::protocol::wl_callback::WlCallback>>();
     ensure_both::<::ways::protocol::wl_callback::WlCallback>();
     ensure_both::<::ways::Client>();
+    ensure_both::<::ways::SerialCounter>();
+    ensure_both::<::ways::SerialTracker<u32>>();
 }
@@ -0,0 +1,245 @@
+mod helpers;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::{wl_compositor, wl_output, wl_shm};
+
+#[test]
+fn display_global_filter() {
+    let mut server = TestServer::new();
+
+    server.display.create_global::<wl_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(|_: (_, _), _, _| {}),
+    );
+    server.display.create_global::<wl_shm::WlShm, _>(1, ways::Filter::new(|_: (_, _), _, _| {}));
+    // the global filter applies along with the filter of the global
+    server.display.create_global_with_filter::<wl_output::WlOutput, _, _>(
+        1,
+        ways::Filter::new(|_: (_, _), _, _| {}),
+        |_| false,
+    );
+    server.display.set_global_filter(|client, interface| {
+        assert!(client.alive());
+        interface != "wl_shm"
+    });
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let globals = manager.list();
+    assert_eq!(globals.len(), 1);
+    assert_eq!(globals[0].1, "wl_compositor");
+
+    server.display.unset_global_filter();
+    let mut client2 = TestClient::new(&server.socket_name);
+    let manager2 = wayc::GlobalManager::new(&client2.display_proxy);
+    roundtrip(&mut client2, &mut server).unwrap();
+    assert_eq!(manager2.list().len(), 2);
+}
+
+#[test]
+fn protocol_logger() {
+    let mut server = TestServer::new();
+
+    server.display.create_global::<wl_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(|_: (_, _), _, _| {}),
+    );
+
+    let messages = Rc::new(RefCell::new(Vec::new()));
+    let log = messages.clone();
+    let logger = server.display.add_protocol_logger(move |message| {
+        log.borrow_mut().push((
+            message.direction,
+            message.interface.to_owned(),
+            message.name.to_owned(),
+        ));
+    });
+
+    let mut client = TestClient::new(&server.socket_name);
+    let _manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(&mut client, &mut server).unwrap();
+
+    {
+        let messages = messages.borrow();
+        assert!(messages.contains(&(
+            ways::MessageDirection::Request,
+            "wl_display".into(),
+            "get_registry".into()
+        )));
+        assert!(messages.contains(&(
+            ways::MessageDirection::Event,
+            "wl_registry".into(),
+            "global".into()
+        )));
+    }
+
+    logger.destroy();
+    messages.borrow_mut().clear();
+    roundtrip(&mut client, &mut server).unwrap();
+    assert!(messages.borrow().is_empty());
+}
+
+// The callbacks of the roundtrips are notified as well, but they are destroyed since
+fn alive_ids(resources: &[ways::Resource<ways::AnonymousObject>]) -> Vec<u32> {
+    resources.iter().filter(|resource| resource.is_alive()).map(|resource| resource.id()).collect()
+}
+
+#[test]
+fn client_resources() {
+    let mut server = TestServer::new();
+
+    let server_client = Rc::new(RefCell::new(None));
+    let created = Rc::new(RefCell::new(Vec::new()));
+    let (server_client2, created2) = (server_client.clone(), created.clone());
+    server.display.create_global::<wl_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(
+            move |(compositor, _): (ways::Main<wl_compositor::WlCompositor>, u32), _, _| {
+                let client = compositor.as_ref().client().unwrap();
+                let created = created2.clone();
+                client.add_resource_created_listener(ways::Filter::new(
+                    move |resource: ways::Resource<ways::AnonymousObject>, _, _| {
+                        created.borrow_mut().push(resource);
+                    },
+                ));
+                *server_client2.borrow_mut() = Some(client);
+                compositor.quick_assign(|_, _, _| {});
+            },
+        ),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(&mut client, &mut server).unwrap();
+    let compositor =
+        manager.instantiate_exact::<wayc::protocol::wl_compositor::WlCompositor>(1).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+    let surface = compositor.create_surface();
+    let region = compositor.create_region();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let (surface_id, region_id) = (surface.as_ref().id(), region.as_ref().id());
+    assert_eq!(alive_ids(&created.borrow()), vec![surface_id, region_id]);
+
+    // the display, the registry, the compositor, the surface and the region
+    let server_client = server_client.borrow_mut().take().unwrap();
+    let mut ids = Vec::new();
+    server_client.for_each_resource(|resource| ids.push(resource.id()));
+    ids.sort();
+    assert_eq!(ids.len(), 5);
+    assert!(ids.contains(&surface_id) && ids.contains(&region_id));
+}
+
+#[test]
+fn resource_created_from_callbacks() {
+    use ways::protocol::wl_callback::WlCallback;
+
+    let mut server = TestServer::new();
+
+    // the listener is notified of the resources created by the server from the bind
+    // callback and the request handlers, while they borrow the dispatch data
+    let created = Rc::new(RefCell::new(Vec::new()));
+    let server_created = Rc::new(RefCell::new(Vec::new()));
+    let (created2, server_created2) = (created.clone(), server_created.clone());
+    server.display.create_global::<wl_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(
+            move |(compositor, _): (ways::Main<wl_compositor::WlCompositor>, u32), _, _| {
+                let client = compositor.as_ref().client().unwrap();
+                let created = created2.clone();
+                client.add_resource_created_listener(ways::Filter::new(
+                    move |resource: ways::Resource<ways::AnonymousObject>, _, _| {
+                        created.borrow_mut().push(resource);
+                    },
+                ));
+                let callback = client.create_resource::<WlCallback>(1).unwrap();
+                server_created2.borrow_mut().push(callback.as_ref().id());
+                let server_created = server_created2.clone();
+                compositor.quick_assign(move |compositor, _, _| {
+                    let client = compositor.as_ref().client().unwrap();
+                    let callback = client.create_resource::<WlCallback>(1).unwrap();
+                    server_created.borrow_mut().push(callback.as_ref().id());
+                });
+            },
+        ),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+    roundtrip(&mut client, &mut server).unwrap();
+    let compositor =
+        manager.instantiate_exact::<wayc::protocol::wl_compositor::WlCompositor>(1).unwrap();
+    roundtrip(&mut client, &mut server).unwrap();
+    let region = compositor.create_region();
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let server_created = server_created.borrow();
+    assert_eq!(server_created.len(), 2);
+    assert_eq!(
+        alive_ids(&created.borrow()),
+        vec![server_created[0], region.as_ref().id(), server_created[1]]
+    );
+}
+
+#[test]
+fn timer_source() {
+    let mut server = TestServer::new();
+
+    let fired = Rc::new(Cell::new(0));
+    let fired2 = fired.clone();
+    let timer = server.display.add_timer(move |_| fired2.set(fired2.get() + 1));
+    assert!(timer.is_active());
+
+    // the timer is created disarmed
+    server.display.dispatch(Duration::from_millis(20), &mut ()).unwrap();
+    assert_eq!(fired.get(), 0);
+
+    assert!(timer.set_delay(Some(Duration::from_millis(1))));
+    server.display.dispatch(Duration::from_millis(100), &mut ()).unwrap();
+    assert_eq!(fired.get(), 1);
+
+    timer.set_delay(Some(Duration::from_millis(1)));
+    timer.set_delay(None);
+    server.display.dispatch(Duration::from_millis(20), &mut ()).unwrap();
+    assert_eq!(fired.get(), 1);
+
+    let timer2 = server.display.add_timer(|_| {});
+    timer.remove();
+    drop(server);
+    // the display removed the timer when it was destroyed
+    assert!(!timer2.is_active());
+    assert!(!timer2.set_delay(Some(Duration::from_millis(1))));
+}
+
+#[test]
+fn signal_source() {
+    use nix::sys::signal::{raise, Signal};
+
+    let mut server = TestServer::new();
+
+    let received = Rc::new(Cell::new(0));
+    let received2 = received.clone();
+    let source = server.display.add_signal(Signal::SIGUSR1 as i32, move |signal, _| {
+        assert_eq!(signal, Signal::SIGUSR1 as i32);
+        received2.set(received2.get() + 1);
+    });
+    assert!(source.is_active());
+
+    // the signal is blocked in this thread by add_signal, and received through the source
+    raise(Signal::SIGUSR1).unwrap();
+    server.display.dispatch(Duration::from_millis(100), &mut ()).unwrap();
+    assert_eq!(received.get(), 1);
+
+    source.remove();
+
+    // an invalid signal gives an inactive source
+    let invalid = server.display.add_signal(-1, |_, _| {});
+    assert!(!invalid.is_active());
+}
@@ -120,3 +120,48 @@ fn dead_resources() {
         assert!(!cloned.as_ref().is_alive());
     }
 }
+
+#[test]
+fn weak_resources() {
+    let mut server = TestServer::new();
+
+    let outputs = Arc::new(Mutex::new(Vec::new()));
+    let outputs2 = outputs.clone();
+
+    server.display.create_global::<wl_output::WlOutput, _>(
+        3,
+        ways::Filter::new(move |(newo, _): (ways::Main<wl_output::WlOutput>, u32), _, _| {
+            newo.quick_assign(|_, _, _| {});
+            outputs2.lock().unwrap().push(newo.as_ref().downgrade());
+        }),
+    );
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let client_output1 = manager.instantiate_exact::<ClientOutput>(3).unwrap();
+    manager.instantiate_exact::<ClientOutput>(3).unwrap();
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    {
+        let outputs_lock = outputs.lock().unwrap();
+        let output1 = outputs_lock[0].upgrade().unwrap();
+        let output2 = outputs_lock[1].upgrade().unwrap();
+        assert!(output1.is_alive());
+        assert!(output1 != output2);
+        assert!(outputs_lock[0].clone().upgrade().unwrap() == output1);
+    }
+
+    client_output1.release();
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    {
+        let outputs_lock = outputs.lock().unwrap();
+        assert!(outputs_lock[0].upgrade().is_none());
+        assert!(outputs_lock[1].upgrade().is_some());
+    }
+}
@@ -0,0 +1,115 @@
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::wl_output;
+
+use wayc::protocol::wl_output::WlOutput as ClientOutput;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum SerialKind {
+    Press,
+    Release,
+}
+
+#[test]
+fn serial_counter() {
+    let counter = ways::SerialCounter::new();
+    let first = counter.next_serial();
+    assert_ne!(first, 0);
+    assert_eq!(counter.next_serial(), first + 1);
+}
+
+#[test]
+fn serial_tracker() {
+    let mut server = TestServer::new();
+
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    let outputs2 = outputs.clone();
+    server.display.create_global::<wl_output::WlOutput, _>(
+        3,
+        ways::Filter::new(move |(newo, _): (ways::Main<wl_output::WlOutput>, u32), _, _| {
+            newo.quick_assign(|_, _, _| {});
+            outputs2.borrow_mut().push(newo);
+        }),
+    );
+
+    let mut client1 = TestClient::new(&server.socket_name);
+    let manager1 = wayc::GlobalManager::new(&client1.display_proxy);
+    let mut client2 = TestClient::new(&server.socket_name);
+    let manager2 = wayc::GlobalManager::new(&client2.display_proxy);
+
+    roundtrip(&mut client1, &mut server).unwrap();
+    roundtrip(&mut client2, &mut server).unwrap();
+
+    let client_output1 = manager1.instantiate_exact::<ClientOutput>(3).unwrap();
+    roundtrip(&mut client1, &mut server).unwrap();
+    manager2.instantiate_exact::<ClientOutput>(3).unwrap();
+    roundtrip(&mut client2, &mut server).unwrap();
+    manager1.instantiate_exact::<ClientOutput>(3).unwrap();
+    roundtrip(&mut client1, &mut server).unwrap();
+
+    let outputs = outputs.borrow();
+    let output1: &ways::Resource<_> = outputs[0].as_ref();
+    let output2: &ways::Resource<_> = outputs[1].as_ref();
+    // an other object of the first client
+    let output1b: &ways::Resource<_> = outputs[2].as_ref();
+
+    let counter = Arc::new(ways::SerialCounter::new());
+    let tracker = ways::SerialTracker::with_capacity(counter.clone(), 4);
+
+    let press = tracker.next_serial(SerialKind::Press, output1);
+    let release = tracker.next_serial(SerialKind::Release, output1);
+    assert_ne!(press, release);
+
+    // serials from other sources can be recorded too
+    let other_press = counter.next_serial();
+    tracker.record(other_press, SerialKind::Press, output2);
+
+    let record = tracker.get(press).unwrap();
+    assert_eq!(record.kind, SerialKind::Press);
+    assert_eq!(record.object_id, output1.id());
+    assert_eq!(record.interface, "wl_output");
+    assert!(record.resource.upgrade().unwrap().same_client_as(output1));
+
+    assert!(tracker.validate(press, &SerialKind::Press, output1));
+    assert!(tracker.validate_object(press, &SerialKind::Press, output1));
+    assert!(!tracker.validate(release, &SerialKind::Press, output1));
+    assert!(!tracker.validate_object(release, &SerialKind::Press, output1));
+    // the serial was sent to an other client
+    assert!(!tracker.validate(press, &SerialKind::Press, output2));
+    assert!(!tracker.validate_object(press, &SerialKind::Press, output2));
+    // the serial was sent with an other object of the client
+    assert!(tracker.validate(press, &SerialKind::Press, output1b));
+    assert!(!tracker.validate_object(press, &SerialKind::Press, output1b));
+    assert!(tracker.validate(other_press, &SerialKind::Press, output2));
+    assert!(tracker.get(counter.next_serial()).is_none());
+
+    assert_eq!(tracker.latest(&SerialKind::Press).unwrap().serial, other_press);
+    assert_eq!(tracker.latest(&SerialKind::Release).unwrap().serial, release);
+
+    // the serials sent with an object are no longer valid once it is destroyed
+    client_output1.release();
+    roundtrip(&mut client1, &mut server).unwrap();
+    assert!(!output1.is_alive());
+    assert!(!tracker.validate(release, &SerialKind::Release, output1));
+    assert!(!tracker.validate(release, &SerialKind::Release, output1b));
+    assert!(!tracker.validate_object(release, &SerialKind::Release, output1));
+    assert!(tracker.get(release).unwrap().resource.upgrade().is_none());
+
+    // old serials are forgotten
+    let release1b = tracker.next_serial(SerialKind::Release, output1b);
+    tracker.next_serial(SerialKind::Release, output1b);
+    assert!(tracker.get(press).is_none());
+    assert!(!tracker.validate(press, &SerialKind::Press, output1b));
+    assert!(tracker.get(release).is_some());
+
+    tracker.forget_client(&output1b.client().unwrap());
+    assert!(tracker.get(release).is_none());
+    assert!(tracker.get(release1b).is_none());
+    assert!(tracker.validate(other_press, &SerialKind::Press, output2));
+}
@@ -0,0 +1,162 @@
+mod helpers;
+
+use helpers::{roundtrip, wayc, ways, TestClient, TestServer};
+
+use ways::protocol::{
+    wl_compositor as server_compositor, wl_shm as server_shm, wl_surface as server_surface,
+};
+
+use wayc::protocol::{wl_compositor, wl_shm};
+
+use std::cell::RefCell;
+use std::os::unix::io::AsRawFd;
+use std::rc::Rc;
+
+type AccessResults = Rc<RefCell<Vec<Result<(Vec<u8>, ways::BufferData), ways::BufferAccessError>>>>;
+
+// a compositor global whose surfaces read the contents of the buffers attached to them
+fn insert_compositor(server: &mut TestServer) -> AccessResults {
+    let results: AccessResults = Rc::new(RefCell::new(Vec::new()));
+    let results2 = results.clone();
+    server.display.create_global::<server_compositor::WlCompositor, _>(
+        1,
+        ways::Filter::new(
+            move |(compositor, _): (ways::Main<server_compositor::WlCompositor>, u32), _, _| {
+                let results = results2.clone();
+                compositor.quick_assign(move |_, request, _| {
+                    if let server_compositor::Request::CreateSurface { id } = request {
+                        let results = results.clone();
+                        id.quick_assign(move |_, request, _| {
+                            if let server_surface::Request::Attach {
+                                buffer: Some(buffer), ..
+                            } = request
+                            {
+                                results
+                                    .borrow_mut()
+                                    .push(ways::with_buffer_contents(&buffer, |contents, data| {
+                                        (contents.to_vec(), data)
+                                    }));
+                            }
+                        });
+                    }
+                });
+            },
+        ),
+    );
+    results
+}
+
+#[test]
+fn shm_buffer_contents() {
+    let mut server = TestServer::new();
+    ways::init_shm_global(&mut server.display, vec![server_shm::Format::Rgb565]);
+    let results = insert_compositor(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+    let surface = compositor.create_surface();
+
+    let mut pool = wayc::ShmPool::new(&shm, 64).unwrap();
+    let _padding = pool.create_buffer(2, 2, 8, wl_shm::Format::Argb8888).unwrap();
+    let buffer = pool.create_buffer(4, 2, 16, wl_shm::Format::Xrgb8888).unwrap();
+    for (i, byte) in pool.canvas(&buffer).unwrap().iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    buffer.attach_to(&surface, 0, 0);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let results = results.borrow();
+    assert_eq!(results.len(), 1);
+    let (contents, data) = results[0].as_ref().unwrap();
+    assert_eq!(contents, &(0..32).collect::<Vec<u8>>());
+    assert_eq!((data.offset, data.width, data.height, data.stride), (16, 4, 2, 16));
+    assert_eq!(data.format, server_shm::Format::Xrgb8888);
+}
+
+#[test]
+fn shm_unsupported_format() {
+    let mut server = TestServer::new();
+    ways::init_shm_global(&mut server.display, Vec::new());
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
+    let mut pool = wayc::ShmPool::new(&shm, 64).unwrap();
+    let _buffer = pool.create_buffer(4, 2, 16, wl_shm::Format::Rgb565).unwrap();
+
+    assert!(roundtrip(&mut client, &mut server).is_err());
+    let error = client.display.protocol_error().unwrap();
+    assert_eq!(error.code, wl_shm::Error::InvalidFormat.to_raw());
+    assert_eq!(error.object_interface, "wl_shm_pool");
+}
+
+#[test]
+fn shm_invalid_stride() {
+    let mut server = TestServer::new();
+    ways::init_shm_global(&mut server.display, Vec::new());
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
+    let file = tempfile::tempfile().unwrap();
+    file.set_len(64).unwrap();
+    let pool = shm.create_pool(file.as_raw_fd(), 64);
+    // a row of 4 pixels is 16 bytes
+    let _buffer = pool.create_buffer(0, 4, 2, 16, wl_shm::Format::Xrgb8888);
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // a stride of 4 pixels, rather than 4 bytes per pixel
+    let _buffer = pool.create_buffer(0, 4, 2, 4, wl_shm::Format::Argb8888);
+    assert!(roundtrip(&mut client, &mut server).is_err());
+    let error = client.display.protocol_error().unwrap();
+    assert_eq!(error.code, wl_shm::Error::InvalidStride.to_raw());
+    assert_eq!(error.object_interface, "wl_shm_pool");
+}
+
+#[test]
+fn shm_truncated_pool() {
+    let mut server = TestServer::new();
+    ways::init_shm_global(&mut server.display, Vec::new());
+    let results = insert_compositor(&mut server);
+
+    let mut client = TestClient::new(&server.socket_name);
+    let manager = wayc::GlobalManager::new(&client.display_proxy);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    let shm = manager.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
+    let compositor = manager.instantiate_exact::<wl_compositor::WlCompositor>(1).unwrap();
+    let surface = compositor.create_surface();
+
+    let file = tempfile::tempfile().unwrap();
+    file.set_len(8192).unwrap();
+    let pool = shm.create_pool(file.as_raw_fd(), 8192);
+    let buffer = pool.create_buffer(0, 2048, 1, 8192, wl_shm::Format::Argb8888);
+
+    roundtrip(&mut client, &mut server).unwrap();
+
+    // the client shrinks the file behind the back of the server
+    file.set_len(0).unwrap();
+    surface.attach(Some(&buffer), 0, 0);
+
+    assert!(roundtrip(&mut client, &mut server).is_err());
+    match results.borrow()[0] {
+        Err(ways::BufferAccessError::BadMap) => {}
+        ref other => panic!("Unexpected access result: {:?}", other),
+    }
+    let error = client.display.protocol_error().unwrap();
+    assert_eq!(error.code, wl_shm::Error::InvalidFd.to_raw());
+    assert_eq!(error.object_interface, "wl_buffer");
+}
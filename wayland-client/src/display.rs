@@ -4,8 +4,9 @@ use std::io;
 use std::ops::Deref;
 use std::os::unix::io::{IntoRawFd, RawFd};
 use std::os::unix::net::UnixStream;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use nix::fcntl;
 
@@ -88,6 +89,91 @@ impl ::std::fmt::Display for ProtocolError {
     }
 }
 
+/// Policy deciding when the requests buffered by a `Display` are flushed to the server
+///
+/// Regardless of the policy, the requests are always flushed when the internal buffer
+/// is full, and when calling `Display::flush()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Only flush when explicitly requested, this is the default
+    Manual,
+    /// Flush after each dispatch of an event queue
+    AfterDispatch,
+    /// Flush once a number of requests are buffered, or once the oldest buffered request
+    /// is older than a deadline
+    ///
+    /// As there is no timer involved, the deadline is only checked when a request is
+    /// sent or an event queue is dispatched.
+    Threshold {
+        /// Number of buffered requests triggering a flush
+        messages: usize,
+        /// Maximum time a request may stay buffered
+        deadline: Duration,
+    },
+}
+
+// Tracking of the buffered requests of a connection, to apply its flush policy
+pub(crate) struct FlushState {
+    policy: FlushPolicy,
+    batch_depth: usize,
+    pending: usize,
+    pending_since: Option<Instant>,
+}
+
+impl FlushState {
+    pub(crate) fn new() -> FlushState {
+        FlushState { policy: FlushPolicy::Manual, batch_depth: 0, pending: 0, pending_since: None }
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: FlushPolicy) {
+        self.policy = policy;
+    }
+
+    // record a newly buffered request, returns whether the buffer should now be flushed
+    pub(crate) fn request_buffered(&mut self) -> bool {
+        self.pending += 1;
+        let since = *self.pending_since.get_or_insert_with(Instant::now);
+        if self.batch_depth > 0 {
+            return false;
+        }
+        match self.policy {
+            FlushPolicy::Threshold { messages, deadline } => {
+                self.pending >= messages || since.elapsed() >= deadline
+            }
+            FlushPolicy::Manual | FlushPolicy::AfterDispatch => false,
+        }
+    }
+
+    // whether the buffer should be flushed after dispatching an event queue
+    pub(crate) fn flush_after_dispatch(&self) -> bool {
+        if self.batch_depth > 0 || self.pending == 0 {
+            return false;
+        }
+        match (self.policy, self.pending_since) {
+            (FlushPolicy::AfterDispatch, _) => true,
+            (FlushPolicy::Threshold { deadline, .. }, Some(since)) => since.elapsed() >= deadline,
+            _ => false,
+        }
+    }
+
+    // returns whether this is the outermost batch
+    pub(crate) fn begin_batch(&mut self) -> bool {
+        self.batch_depth += 1;
+        self.batch_depth == 1
+    }
+
+    // returns whether this was the outermost batch
+    pub(crate) fn end_batch(&mut self) -> bool {
+        self.batch_depth -= 1;
+        self.batch_depth == 0
+    }
+
+    pub(crate) fn flushed(&mut self) {
+        self.pending = 0;
+        self.pending_since = None;
+    }
+}
+
 /// A connection to a wayland server
 ///
 /// This object both represent the connection to the server and contains the
@@ -113,7 +199,8 @@ impl Display {
     /// Otherwise, it will try to connect to the socket name defined in the `WAYLAND_DISPLAY`
     /// environment variable, and error if it is not set.
     ///
-    /// This requires the `XDG_RUNTIME_DIR` variable to be properly set.
+    /// This requires the `XDG_RUNTIME_DIR` variable to be properly set, unless `WAYLAND_DISPLAY`
+    /// is an absolute path, in which case it is used as the path of the socket.
     pub fn connect_to_env() -> Result<Display, ConnectError> {
         if let Ok(txt) = env::var("WAYLAND_SOCKET") {
             // We should connect to the provided WAYLAND_SOCKET
@@ -135,15 +222,8 @@ impl Display {
                 }
             }
         } else {
-            let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
-                .map(Into::<PathBuf>::into)
-                .ok_or(ConnectError::XdgRuntimeDirNotSet)?;
-            socket_path
-                .push(env::var_os("WAYLAND_DISPLAY").ok_or(ConnectError::NoCompositorListening)?);
-
-            let socket = UnixStream::connect(socket_path)
-                .map_err(|_| ConnectError::NoCompositorListening)?;
-            unsafe { Display::from_fd(socket.into_raw_fd()) }
+            let name = env::var_os("WAYLAND_DISPLAY").ok_or(ConnectError::NoCompositorListening)?;
+            Display::connect_to_path(socket_path(name)?)
         }
     }
 
@@ -152,15 +232,20 @@ impl Display {
     /// On success, you are given the `Display` object as well as the main `EventQueue` hosting
     /// the `WlDisplay` wayland object.
     ///
-    /// This requires the `XDG_RUNTIME_DIR` variable to be properly set.
+    /// This requires the `XDG_RUNTIME_DIR` variable to be properly set, unless `name` is an
+    /// absolute path.
     pub fn connect_to_name<S: Into<OsString>>(name: S) -> Result<Display, ConnectError> {
-        let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
-            .map(Into::<PathBuf>::into)
-            .ok_or(ConnectError::XdgRuntimeDirNotSet)?;
-        socket_path.push(name.into());
+        Display::connect_to_path(socket_path(name.into())?)
+    }
 
+    /// Attempt to connect to a wayland server socket at given path
+    ///
+    /// Unlike `connect_to_name`, relative paths are not resolved against `XDG_RUNTIME_DIR`,
+    /// which does not need to be set. This is useful when the socket lives outside of the
+    /// runtime directory, for example for containers or nested compositors.
+    pub fn connect_to_path<P: AsRef<Path>>(path: P) -> Result<Display, ConnectError> {
         let socket =
-            UnixStream::connect(socket_path).map_err(|_| ConnectError::NoCompositorListening)?;
+            UnixStream::connect(path.as_ref()).map_err(|_| ConnectError::NoCompositorListening)?;
         unsafe { Display::from_fd(socket.into_raw_fd()) }
     }
 
@@ -190,6 +275,43 @@ impl Display {
         self.inner.flush()
     }
 
+    /// Set the policy deciding when the buffered requests are flushed to the server
+    ///
+    /// See `FlushPolicy` for the available policies, the default is `FlushPolicy::Manual`.
+    ///
+    /// With the `use_system_lib` backend, the requests sent through objects that were
+    /// created by the server rather than by your requests do not trigger the
+    /// `FlushPolicy::Threshold` policy.
+    pub fn set_flush_policy(&self, policy: FlushPolicy) {
+        self.inner.set_flush_policy(policy)
+    }
+
+    /// Send a group of requests together
+    ///
+    /// The requests sent during the execution of the closure are not flushed by the
+    /// flush policy. Once it returns, they are flushed together with the requests
+    /// buffered before the batch, which is useful for example to send all the requests
+    /// of a surface commit at once. The outgoing buffer is still written to the socket
+    /// whenever it is full, so a large batch can reach the server in several parts.
+    ///
+    /// Batches can be nested, in which case only the outermost batch is flushed. A batch
+    /// only concerns the flush policy of this connection: the requests sent by other
+    /// threads in the meantime are part of the batch, and a flush from another thread,
+    /// explicit or done when dispatching an event queue, can send part of the batch
+    /// before it ends.
+    pub fn batch<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        // end the batch even if the closure panics
+        struct BatchGuard<'a>(&'a DisplayInner);
+        impl<'a> Drop for BatchGuard<'a> {
+            fn drop(&mut self) {
+                self.0.end_batch();
+            }
+        }
+        self.inner.begin_batch();
+        let _guard = BatchGuard(&self.inner);
+        f()
+    }
+
     /// Create a new event queue associated with this wayland connection
     pub fn create_event_queue(&self) -> EventQueue {
         let evq_inner = DisplayInner::create_event_queue(&self.inner);
@@ -218,6 +340,54 @@ impl Display {
         self.inner.get_connection_fd()
     }
 
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Count the protocol objects held by this connection
+    ///
+    /// Destroyed objects remain as zombies until the server acknowledges their
+    /// destruction, only then can their id be reused for a new object.
+    ///
+    /// This is not available with the `use_system_lib` backend, as the system library
+    /// does not expose its object map.
+    pub fn object_counts(&self) -> ObjectCounts {
+        self.inner.object_counts()
+    }
+
+    /// Retrieve statistics about the traffic and the objects of this connection
+    ///
+    /// Returns `None` with the `use_system_lib` backend, as the system library does
+    /// not expose them.
+    pub fn stats(&self) -> Option<crate::ConnectionStats> {
+        self.inner.stats()
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the maximum size in bytes of the messages exchanged on this connection
+    ///
+    /// Sending a bigger request fails, and receiving a bigger event is treated as a
+    /// protocol error. The default is 4096 bytes, the limit of the system wayland library,
+    /// and it can be raised up to 65532 bytes, the limit of the wire format. Only raise it
+    /// if the server is known to accept bigger messages.
+    ///
+    /// This is not available with the `use_system_lib` backend, as the system library
+    /// has a fixed limit.
+    pub fn set_max_message_size(&self, size: usize) {
+        self.inner.set_max_message_size(size)
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Limit the number of objects this client can create
+    ///
+    /// The objects created by the client get ids from 1 to `limit`. Once they are
+    /// all in use, the requests creating objects are not sent and `Proxy::try_send()`
+    /// fails with `SendError::IdsExhausted`. The default is the whole range allowed by
+    /// the protocol, `0xFEFFFFFF`, which a larger limit is clamped to.
+    ///
+    /// This is not available with the `use_system_lib` backend, as the system library
+    /// does not expose its object map.
+    pub fn set_object_id_limit(&self, limit: u32) {
+        self.inner.set_object_id_limit(limit)
+    }
+
     #[cfg(feature = "use_system_lib")]
     /// Create a Display and from an external display
     ///
@@ -247,6 +417,28 @@ impl Display {
     }
 }
 
+/// The number of protocol objects held by a connection
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ObjectCounts {
+    /// Number of live objects, including the display
+    pub live: usize,
+    /// Number of destroyed objects whose id has not yet been released
+    pub zombies: usize,
+}
+
+// Resolve the path of the socket with given name, absolute names are used as-is
+fn socket_path(name: OsString) -> Result<PathBuf, ConnectError> {
+    let name = PathBuf::from(name);
+    if name.is_absolute() {
+        return Ok(name);
+    }
+    let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
+        .map(Into::<PathBuf>::into)
+        .ok_or(ConnectError::XdgRuntimeDirNotSet)?;
+    socket_path.push(name);
+    Ok(socket_path)
+}
+
 impl Deref for Display {
     type Target = Proxy<crate::protocol::wl_display::WlDisplay>;
     fn deref(&self) -> &Proxy<crate::protocol::wl_display::WlDisplay> {
@@ -1,4 +1,9 @@
-use std::{io, rc::Rc};
+use std::{
+    cell::Cell,
+    io,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use crate::imp::EventQueueInner;
 use crate::{AnonymousObject, DispatchData, Display, Main, RawEvent};
@@ -137,7 +142,9 @@ impl EventQueue {
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
         let mut data = DispatchData::wrap(data);
-        self.inner.dispatch(data.reborrow(), fallback)
+        let ret = self.inner.dispatch(data.reborrow(), fallback);
+        self.display.inner.flush_after_dispatch();
+        ret
     }
 
     /// Dispatches pending events from the internal buffer.
@@ -161,7 +168,9 @@ impl EventQueue {
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
         let mut data = DispatchData::wrap(data);
-        self.inner.dispatch_pending(data.reborrow(), fallback)
+        let ret = self.inner.dispatch_pending(data.reborrow(), fallback);
+        self.display.inner.flush_after_dispatch();
+        ret
     }
 
     /// Synchronous roundtrip
@@ -188,7 +197,72 @@ impl EventQueue {
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
         let mut data = DispatchData::wrap(data);
-        self.inner.sync_roundtrip(data.reborrow(), fallback)
+        let ret = self.inner.sync_roundtrip(data.reborrow(), fallback);
+        self.display.inner.flush_after_dispatch();
+        ret
+    }
+
+    /// Dispatches events from the internal buffer, waiting at most for a given duration
+    ///
+    /// This method behaves like `dispatch()`, except that if no events are received from
+    /// the server before the timeout expires, it returns an error of kind
+    /// `io::ErrorKind::TimedOut`. This allows you to detect a server that stopped answering,
+    /// and does not mean the connection is lost: you can keep using it afterwards.
+    pub fn dispatch_timeout<T: std::any::Any, F>(
+        &mut self,
+        data: &mut T,
+        fallback: F,
+        timeout: Duration,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        let mut data = DispatchData::wrap(data);
+        let ret =
+            self.inner.dispatch_deadline(data.reborrow(), fallback, Some(Instant::now() + timeout));
+        self.display.inner.flush_after_dispatch();
+        ret
+    }
+
+    /// Synchronous roundtrip, waiting at most for a given duration
+    ///
+    /// This method behaves like `sync_roundtrip()`, except that if the server has not
+    /// processed all pending requests before the timeout expires, it returns an error of
+    /// kind `io::ErrorKind::TimedOut`. Events received in the meantime are still dispatched.
+    ///
+    /// A timeout likely means the server is hung. It does not mean the connection is lost,
+    /// and the pending roundtrip will silently complete if the server later catches up.
+    pub fn sync_roundtrip_timeout<T: std::any::Any, F>(
+        &mut self,
+        data: &mut T,
+        mut fallback: F,
+        timeout: Duration,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        let deadline = Instant::now() + timeout;
+        let done = Rc::new(Cell::new(false));
+        let done2 = done.clone();
+        let callback = self.display.attach(self.token()).sync();
+        callback.quick_assign(move |_, _, _| done2.set(true));
+
+        let mut data = DispatchData::wrap(data);
+        let mut dispatched = 0;
+        while !done.get() {
+            let ret = self.inner.dispatch_deadline(data.reborrow(), &mut fallback, Some(deadline));
+            self.display.inner.flush_after_dispatch();
+            dispatched += ret?;
+        }
+        Ok(dispatched)
+    }
+
+    /// Maximum number of events that were pending in this event queue when it was dispatched
+    ///
+    /// Returns `None` with the `use_system_lib` backend, as the system library does
+    /// not expose the contents of its event queues.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.inner.max_depth()
     }
 
     /// Create a new token associated with this event queue
@@ -263,3 +337,25 @@ impl Drop for ReadEventsGuard {
         }
     }
 }
+
+// Timeout to give to poll() to wait until a deadline, -1 meaning no timeout
+pub(crate) fn poll_timeout(deadline: Option<Instant>) -> i32 {
+    match deadline {
+        Some(deadline) => {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            // round up, so that we don't spin with a 0 timeout just before the deadline
+            let millis =
+                remaining.as_millis() + u128::from(remaining.subsec_nanos() % 1_000_000 != 0);
+            if millis > std::i32::MAX as u128 {
+                std::i32::MAX
+            } else {
+                millis as i32
+            }
+        }
+        None => -1,
+    }
+}
+
+pub(crate) fn timeout_error() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "Timed out waiting for the wayland server.")
+}
@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use crate::protocol::wl_display;
@@ -115,15 +117,15 @@ impl GlobalManager {
             .send::<wl_registry::WlRegistry>(wl_display::Request::GetRegistry {}, None)
             .unwrap();
         registry.quick_assign(move |proxy, msg, data| {
-            let mut inner = inner.lock().unwrap();
-            let inner = &mut *inner;
+            // the lock is released before invoking the callback, so that it can
+            // freely use the global manager
             match msg {
                 wl_registry::Event::Global {
                     name,
                     interface,
                     version,
                 } => {
-                    inner.list.push((name, interface.clone(), version));
+                    inner.lock().unwrap().list.push((name, interface.clone(), version));
                     callback(
                         GlobalEvent::New {
                             id: name,
@@ -135,8 +137,15 @@ impl GlobalManager {
                     );
                 }
                 wl_registry::Event::GlobalRemove { name } => {
-                    if let Some((i, _)) = inner.list.iter().enumerate().find(|&(_, &(n, _, _))| n == name) {
-                        let (id, interface, _) = inner.list.swap_remove(i);
+                    let removed = {
+                        let mut inner = inner.lock().unwrap();
+                        inner
+                            .list
+                            .iter()
+                            .position(|&(n, _, _)| n == name)
+                            .map(|i| inner.list.swap_remove(i))
+                    };
+                    if let Some((id, interface, _)) = removed {
                         callback(GlobalEvent::Removed { id, interface }, (*proxy).clone(), data);
                     } else {
                         panic!(
@@ -220,6 +229,233 @@ impl GlobalManager {
     }
 }
 
+type TrackerCallback = dyn FnMut(&GlobalEvent, &Attached<wl_registry::WlRegistry>, DispatchData);
+type BoundGlobals<I> = Vec<(u32, Main<I>)>;
+type Trackers = Rc<RefCell<Vec<(&'static str, Rc<RefCell<TrackerCallback>>)>>>;
+
+/// A typed registry of bound globals
+///
+/// This utility builds on top of `GlobalManager` to automatically bind all
+/// instances of the globals you are interested in as they are advertised, and
+/// keep track of them by their global name. It is most useful for globals that
+/// can exist with multiplicity and come and go during the lifetime of your
+/// program, like `wl_output` or `wl_seat`.
+///
+/// For each interface you are interested in, call `track()` to get a
+/// `GlobalList<I>` holding the typed handles of all bound instances of this
+/// global. Your callback is notified whenever a global is bound and when it
+/// is removed by the server.
+///
+/// ```no_run
+/// # use wayland_client::{Display, GlobalRegistry, GlobalUpdate};
+/// use wayland_client::protocol::wl_output;
+///
+/// # let display = Display::connect_to_env().unwrap();
+/// # let mut event_queue = display.create_event_queue();
+/// let registry = GlobalRegistry::new(&display.attach(event_queue.token()));
+/// let outputs = registry.track::<wl_output::WlOutput, _>(1, 3, |update, _| match update {
+///     GlobalUpdate::New { global, .. } => {
+///         global.quick_assign(|_, _, _| { /* handle the output events */ });
+///     }
+///     GlobalUpdate::Removed { global, .. } => {
+///         if global.as_ref().version() >= 3 {
+///             global.release();
+///         }
+///     }
+///     GlobalUpdate::VersionTooLow { .. } => {}
+/// });
+///
+/// event_queue.sync_roundtrip(&mut (), |_, _, _| unreachable!()).unwrap();
+/// println!("There are {} outputs.", outputs.len());
+/// ```
+///
+/// Like `GlobalManager`, it is managed by the event queue the display handle
+/// used to create it is attached to.
+#[derive(Clone)]
+pub struct GlobalRegistry {
+    manager: GlobalManager,
+    trackers: Trackers,
+}
+
+/// An update of the globals tracked by a `GlobalRegistry`
+pub enum GlobalUpdate<I: Interface + AsRef<Proxy<I>> + From<Proxy<I>>> {
+    /// A new global was advertised and has been bound
+    ///
+    /// You should assign it to a filter if you plan to process its events.
+    New {
+        /// Name of the global
+        name: u32,
+        /// The newly bound object
+        global: Main<I>,
+    },
+    /// A bound global was removed by the server
+    ///
+    /// It is no longer part of the `GlobalList`. The object itself is not
+    /// destroyed, it is up to you to send its destructor request if its
+    /// interface has one.
+    Removed {
+        /// Name of the global
+        name: u32,
+        /// The object that was bound to this global
+        global: Main<I>,
+    },
+    /// A global was advertised with a version lower than the minimal version
+    /// you requested, and has not been bound
+    VersionTooLow {
+        /// Name of the global
+        name: u32,
+        /// Version advertised by the server
+        version: u32,
+    },
+}
+
+impl GlobalRegistry {
+    /// Create a global registry
+    ///
+    /// You need to provide an attached handle of the Wayland display, and the
+    /// registry will be managed by the associated event queue.
+    pub fn new(display: &Attached<wl_display::WlDisplay>) -> GlobalRegistry {
+        let trackers: Trackers = Rc::new(RefCell::new(Vec::new()));
+        let trackers2 = trackers.clone();
+
+        let manager = GlobalManager::new_with_cb(display, move |event, registry, mut data| {
+            let interface = match event {
+                GlobalEvent::New { ref interface, .. } => interface,
+                GlobalEvent::Removed { ref interface, .. } => interface,
+            };
+            // collect the matching trackers first, so that callbacks can track
+            // new interfaces
+            let matching = trackers2
+                .borrow()
+                .iter()
+                .filter(|&&(iface, _)| iface == interface)
+                .map(|(_, tracker)| tracker.clone())
+                .collect::<Vec<_>>();
+            for tracker in matching {
+                (*tracker.borrow_mut())(&event, &registry, data.reborrow());
+            }
+        });
+
+        GlobalRegistry { manager, trackers }
+    }
+
+    /// Start tracking all globals of a given interface
+    ///
+    /// All instances of this global, already advertised or to be advertised later,
+    /// will be bound with the highest version between `min_version` and `max_version`
+    /// supported by the server, and stored in the returned `GlobalList`. Globals
+    /// advertised with a version lower than `min_version` are not bound.
+    ///
+    /// The callback is invoked each time a global is bound or removed. For globals
+    /// that were already advertised when this method is called, it is invoked
+    /// before this method returns, with an empty `DispatchData`.
+    ///
+    /// As for `GlobalManager::instantiate_range`, you should not use `I::VERSION`
+    /// as `max_version`.
+    pub fn track<I, F>(&self, min_version: u32, max_version: u32, mut callback: F) -> GlobalList<I>
+    where
+        I: Interface + Clone + AsRef<Proxy<I>> + From<Proxy<I>>,
+        F: FnMut(GlobalUpdate<I>, DispatchData) + 'static,
+    {
+        let list = GlobalList { inner: Rc::new(RefCell::new(Vec::new())) };
+        let list2 = list.clone();
+
+        let tracker = Rc::new(RefCell::new(
+            move |event: &GlobalEvent,
+                  registry: &Attached<wl_registry::WlRegistry>,
+                  data: DispatchData| match *event {
+                GlobalEvent::New { id, version, .. } => {
+                    if version < min_version {
+                        callback(GlobalUpdate::VersionTooLow { name: id, version }, data);
+                        return;
+                    }
+                    let global = registry.bind::<I>(::std::cmp::min(version, max_version), id);
+                    list2.inner.borrow_mut().push((id, global.clone()));
+                    callback(GlobalUpdate::New { name: id, global }, data);
+                }
+                GlobalEvent::Removed { id, .. } => {
+                    let removed = {
+                        let mut inner = list2.inner.borrow_mut();
+                        inner.iter().position(|&(n, _)| n == id).map(|i| inner.remove(i))
+                    };
+                    if let Some((name, global)) = removed {
+                        callback(GlobalUpdate::Removed { name, global }, data);
+                    }
+                }
+            },
+        ));
+
+        // bind the globals that have already been advertised
+        for (id, interface, version) in self.manager.list() {
+            if interface == I::NAME {
+                let event = GlobalEvent::New { id, interface, version };
+                (*tracker.borrow_mut())(
+                    &event,
+                    &self.manager.registry,
+                    DispatchData::wrap(&mut ()),
+                );
+            }
+        }
+
+        self.trackers.borrow_mut().push((I::NAME, tracker));
+
+        list
+    }
+
+    /// Access the underlying `GlobalManager`
+    ///
+    /// It can notably be used to instantiate globals that are not tracked.
+    pub fn global_manager(&self) -> &GlobalManager {
+        &self.manager
+    }
+}
+
+/// The list of bound instances of a global
+///
+/// It is obtained from `GlobalRegistry::track()`, and is kept up to date
+/// as globals are advertised and removed. Cloning it creates a new handle
+/// to the same list.
+pub struct GlobalList<I: Interface + AsRef<Proxy<I>> + From<Proxy<I>>> {
+    inner: Rc<RefCell<BoundGlobals<I>>>,
+}
+
+impl<I: Interface + Clone + AsRef<Proxy<I>> + From<Proxy<I>>> GlobalList<I> {
+    /// Number of currently bound globals
+    pub fn len(&self) -> usize {
+        self.inner.borrow().len()
+    }
+
+    /// Whether there is no currently bound global
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().is_empty()
+    }
+
+    /// Retrieve the object bound to the global of given name
+    pub fn get(&self, name: u32) -> Option<Main<I>> {
+        self.inner.borrow().iter().find(|&&(n, _)| n == name).map(|(_, g)| g.clone())
+    }
+
+    /// Retrieve the first bound global
+    ///
+    /// This is mostly useful for singleton globals, like `wl_compositor`.
+    pub fn first(&self) -> Option<Main<I>> {
+        self.inner.borrow().first().map(|(_, g)| g.clone())
+    }
+
+    /// Retrieve the list of bound globals, along with their names
+    ///
+    /// They are listed in the order in which they were advertised.
+    pub fn list(&self) -> Vec<(u32, Main<I>)> {
+        self.inner.borrow().clone()
+    }
+}
+
+impl<I: Interface + AsRef<Proxy<I>> + From<Proxy<I>>> Clone for GlobalList<I> {
+    fn clone(&self) -> GlobalList<I> {
+        GlobalList { inner: self.inner.clone() }
+    }
+}
+
 /// A trait for implementation of the global advertisement
 ///
 /// It is automatically implemented for `FnMut(Main<I>, DispatchData)` closures,
@@ -102,15 +102,21 @@ mod display;
 mod event_queue;
 mod globals;
 mod proxy;
+mod shm;
 
 pub use anonymous_object::AnonymousObject;
-pub use display::{ConnectError, Display, ProtocolError};
+pub use display::{ConnectError, Display, FlushPolicy, ObjectCounts, ProtocolError};
 pub use event_queue::{EventQueue, QueueToken, ReadEventsGuard};
-pub use globals::{GlobalError, GlobalEvent, GlobalImplementor, GlobalManager};
+pub use globals::{
+    GlobalError, GlobalEvent, GlobalImplementor, GlobalList, GlobalManager, GlobalRegistry,
+    GlobalUpdate,
+};
 pub use imp::ProxyMap;
-pub use proxy::{Attached, Main, Proxy};
+pub use proxy::{Attached, Main, Proxy, SendError, WeakProxy};
+pub use shm::{ShmBuffer, ShmPool};
 pub use wayland_commons::{
     filter::{DispatchData, Filter},
+    socket::{ConnectionStats, SocketStats},
     user_data::UserData,
     Interface, MessageGroup, NoMessage,
 };
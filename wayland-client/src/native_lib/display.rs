@@ -1,11 +1,12 @@
 use std::io;
 use std::os::unix::io::RawFd;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use crate::display::FlushState;
 use crate::protocol::wl_display::WlDisplay;
 use wayland_sys::client::*;
 
-use crate::{ConnectError, Proxy};
+use crate::{ConnectError, FlushPolicy, Proxy};
 
 use super::{EventQueueInner, ProxyInner};
 
@@ -17,6 +18,32 @@ pub(crate) struct DisplayInner {
 pub(crate) struct DisplayGuard {
     ptr: *mut wl_display,
     external: bool,
+    flush_state: Mutex<FlushState>,
+}
+
+impl DisplayGuard {
+    fn new(ptr: *mut wl_display, external: bool) -> DisplayGuard {
+        DisplayGuard { ptr, external, flush_state: Mutex::new(FlushState::new()) }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        // hold the lock so that the requests buffered meanwhile are accounted for
+        let mut flush_state = self.flush_state.lock().unwrap();
+        let ret = unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_flush, self.ptr) };
+        if ret >= 0 {
+            flush_state.flushed();
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub(crate) fn request_buffered(&self) {
+        if self.flush_state.lock().unwrap().request_buffered() {
+            // errors are reported by the next explicit flush or dispatch
+            let _ = self.flush();
+        }
+    }
 }
 
 unsafe impl Send for DisplayInner {}
@@ -29,7 +56,7 @@ unsafe fn make_display(ptr: *mut wl_display) -> Result<Arc<DisplayInner>, Connec
 
     let mut inner = DisplayInner {
         proxy: Proxy::from_c_ptr(ptr as *mut _),
-        display: Arc::new(DisplayGuard { ptr, external: false }),
+        display: Arc::new(DisplayGuard::new(ptr, false)),
     };
 
     inner.proxy.inner.display = Some(Arc::downgrade(&inner.display));
@@ -57,11 +84,33 @@ impl DisplayInner {
     }
 
     pub(crate) fn flush(&self) -> io::Result<()> {
-        let ret = unsafe { ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_flush, self.ptr()) };
-        if ret >= 0 {
-            Ok(())
-        } else {
-            Err(io::Error::last_os_error())
+        self.display.flush()
+    }
+
+    pub(crate) fn stats(&self) -> Option<crate::ConnectionStats> {
+        None
+    }
+
+    pub(crate) fn set_flush_policy(&self, policy: FlushPolicy) {
+        self.display.flush_state.lock().unwrap().set_policy(policy);
+    }
+
+    pub(crate) fn begin_batch(&self) {
+        if self.display.flush_state.lock().unwrap().begin_batch() {
+            // make room for the batch
+            let _ = self.display.flush();
+        }
+    }
+
+    pub(crate) fn end_batch(&self) {
+        if self.display.flush_state.lock().unwrap().end_batch() {
+            let _ = self.display.flush();
+        }
+    }
+
+    pub(crate) fn flush_after_dispatch(&self) {
+        if self.display.flush_state.lock().unwrap().flush_after_dispatch() {
+            let _ = self.display.flush();
         }
     }
 
@@ -105,7 +154,7 @@ impl DisplayInner {
     pub(crate) unsafe fn from_external(display_ptr: *mut wl_display) -> Arc<DisplayInner> {
         Arc::new(DisplayInner {
             proxy: Proxy::wrap(ProxyInner::from_external_display(display_ptr as *mut _)),
-            display: Arc::new(DisplayGuard { ptr: display_ptr, external: true }),
+            display: Arc::new(DisplayGuard::new(display_ptr, true)),
         })
     }
 }
@@ -1,18 +1,51 @@
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::io;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use nix::poll::{poll, PollFd, PollFlags};
+
+use crate::event_queue::{poll_timeout, timeout_error};
 use crate::{AnonymousObject, DispatchData, Main, RawEvent};
 use wayland_sys::client::*;
 
+use super::proxy::ProxyInternal;
 use super::DisplayInner;
 
 scoped_tls::scoped_thread_local! {
     pub(crate) static DISPATCH_METADATA: RefCell<(&mut dyn FnMut(RawEvent, Main<AnonymousObject>, DispatchData), DispatchData)>
 }
 
+scoped_tls::scoped_thread_local! {
+    static DISPATCHING_QUEUE: MigratedEvents
+}
+
+/// An event handed over by the queue its object was moved away from
+pub(crate) struct MigratedEvent {
+    internal: Arc<ProxyInternal>,
+    dispatch: Box<dyn FnOnce()>,
+}
+
+// Like the events queued by the C library, a migrated event is only dispatched
+// by the thread dispatching its queue
+unsafe impl Send for MigratedEvent {}
+
+impl MigratedEvent {
+    pub(crate) fn new(internal: Arc<ProxyInternal>, dispatch: Box<dyn FnOnce()>) -> MigratedEvent {
+        MigratedEvent { internal, dispatch }
+    }
+}
+
+pub(crate) type MigratedEvents = Arc<Mutex<VecDeque<MigratedEvent>>>;
+
+/// Whether the events of an object moved to this queue can be dispatched right away
+pub(crate) fn is_dispatching(queue: &MigratedEvents) -> bool {
+    !DISPATCHING_QUEUE.is_set() || DISPATCHING_QUEUE.with(|q| Arc::ptr_eq(q, queue))
+}
+
 #[allow(clippy::transmute_ptr_to_ptr)]
-fn with_dispatch_meta<T, FB, F>(mut fb: FB, data: DispatchData, f: F) -> T
+fn with_dispatch_meta<T, FB, F>(queue: &MigratedEvents, mut fb: FB, data: DispatchData, f: F) -> T
 where
     FB: FnMut(RawEvent, Main<AnonymousObject>, DispatchData),
     F: FnOnce() -> T,
@@ -21,24 +54,30 @@ where
     // it's safe as it'll only last until the end of this function call anyway
     let fb = unsafe { std::mem::transmute(&mut fb as &mut dyn FnMut(_, _, _)) };
     let data = unsafe { std::mem::transmute(data) };
-    DISPATCH_METADATA.set(&RefCell::new((fb, data)), || f())
+    DISPATCH_METADATA.set(&RefCell::new((fb, data)), || DISPATCHING_QUEUE.set(queue, f))
 }
 
 pub(crate) struct EventQueueInner {
     wlevq: *mut wl_event_queue,
     inner: Arc<super::DisplayInner>,
+    pub(crate) migrated: MigratedEvents,
 }
 
 impl EventQueueInner {
     pub(crate) fn new(inner: Arc<DisplayInner>, wlevq: *mut wl_event_queue) -> EventQueueInner {
-        EventQueueInner { inner, wlevq }
+        EventQueueInner { inner, wlevq, migrated: Arc::new(Mutex::new(VecDeque::new())) }
     }
 
     pub(crate) fn dispatch<F>(&self, data: DispatchData, fallback: F) -> io::Result<u32>
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
-        with_dispatch_meta(fallback, data, || {
+        with_dispatch_meta(&self.migrated, fallback, data, || {
+            // don't block if there are events handed over by another queue
+            let migrated = self.dispatch_migrated();
+            if migrated > 0 {
+                return self.raw_dispatch_pending().map(|n| n + migrated);
+            }
             let ret = unsafe {
                 ffi_dispatch!(
                     WAYLAND_CLIENT_HANDLE,
@@ -55,32 +94,132 @@ impl EventQueueInner {
         })
     }
 
+    pub(crate) fn dispatch_deadline<F>(
+        &self,
+        data: DispatchData,
+        fallback: F,
+        deadline: Option<Instant>,
+    ) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        if deadline.is_none() {
+            return self.dispatch(data, fallback);
+        }
+        // this follows the logic of wl_display_dispatch_queue, but with bounded waits
+        with_dispatch_meta(&self.migrated, fallback, data, || {
+            let migrated = self.dispatch_migrated();
+            if migrated > 0 {
+                return self.raw_dispatch_pending().map(|n| n + migrated);
+            }
+            if self.prepare_read().is_err() {
+                return self.raw_dispatch_pending();
+            }
+
+            let fd = self.inner.get_connection_fd();
+            loop {
+                match self.inner.flush() {
+                    Ok(()) => break,
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        match poll(
+                            &mut [PollFd::new(fd, PollFlags::POLLOUT)],
+                            poll_timeout(deadline),
+                        ) {
+                            Ok(0) => {
+                                self.cancel_read();
+                                return Err(timeout_error());
+                            }
+                            Ok(_) => continue,
+                            Err(::nix::Error::Sys(e)) => {
+                                self.cancel_read();
+                                return Err(e.into());
+                            }
+                            Err(_) => unreachable!(),
+                        }
+                    }
+                    // don't abort on EPIPE, so we can continue reading to get the protocol error
+                    Err(ref e) if e.raw_os_error() == Some(::nix::libc::EPIPE) => break,
+                    Err(e) => {
+                        self.cancel_read();
+                        return Err(e);
+                    }
+                }
+            }
+
+            match poll(&mut [PollFd::new(fd, PollFlags::POLLIN)], poll_timeout(deadline)) {
+                Ok(0) => {
+                    self.cancel_read();
+                    return Err(timeout_error());
+                }
+                Ok(_) => (),
+                Err(::nix::Error::Sys(e)) => {
+                    self.cancel_read();
+                    return Err(e.into());
+                }
+                Err(_) => unreachable!(),
+            }
+
+            self.read_events()?;
+            self.raw_dispatch_pending()
+        })
+    }
+
     pub(crate) fn dispatch_pending<F>(&self, data: DispatchData, fallback: F) -> io::Result<u32>
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
-        with_dispatch_meta(fallback, data, || {
-            let ret = unsafe {
-                ffi_dispatch!(
-                    WAYLAND_CLIENT_HANDLE,
-                    wl_display_dispatch_queue_pending,
-                    self.inner.ptr(),
-                    self.wlevq
-                )
+        with_dispatch_meta(&self.migrated, fallback, data, || {
+            let migrated = self.dispatch_migrated();
+            self.raw_dispatch_pending().map(|n| n + migrated)
+        })
+    }
+
+    // Dispatch the events handed over by other queues, they come before the events
+    // still queued by the C library
+    fn dispatch_migrated(&self) -> u32 {
+        let mut count = 0;
+        loop {
+            // don't hold the lock while dispatching, the callbacks may move objects
+            let event = self.migrated.lock().unwrap().pop_front();
+            let event = match event {
+                Some(event) => event,
+                None => return count,
             };
-            if ret >= 0 {
-                Ok(ret as u32)
-            } else {
-                Err(io::Error::last_os_error())
+            match event.internal.queue() {
+                // the object was moved again in the meantime
+                Some(ref queue) if !Arc::ptr_eq(queue, &self.migrated) => {
+                    queue.lock().unwrap().push_back(event)
+                }
+                _ => {
+                    (event.dispatch)();
+                    count += 1;
+                }
             }
-        })
+        }
+    }
+
+    fn raw_dispatch_pending(&self) -> io::Result<u32> {
+        let ret = unsafe {
+            ffi_dispatch!(
+                WAYLAND_CLIENT_HANDLE,
+                wl_display_dispatch_queue_pending,
+                self.inner.ptr(),
+                self.wlevq
+            )
+        };
+        if ret >= 0 {
+            Ok(ret as u32)
+        } else {
+            Err(io::Error::last_os_error())
+        }
     }
 
     pub(crate) fn sync_roundtrip<F>(&self, data: DispatchData, fallback: F) -> io::Result<u32>
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
-        with_dispatch_meta(fallback, data, || {
+        with_dispatch_meta(&self.migrated, fallback, data, || {
+            let migrated = self.dispatch_migrated();
             let ret = unsafe {
                 ffi_dispatch!(
                     WAYLAND_CLIENT_HANDLE,
@@ -90,13 +229,17 @@ impl EventQueueInner {
                 )
             };
             if ret >= 0 {
-                Ok(ret as u32)
+                Ok(ret as u32 + migrated + self.dispatch_migrated())
             } else {
                 Err(io::Error::last_os_error())
             }
         })
     }
 
+    pub(crate) fn max_depth(&self) -> Option<usize> {
+        None
+    }
+
     pub(crate) fn prepare_read(&self) -> Result<(), ()> {
         let ret = unsafe {
             ffi_dispatch!(
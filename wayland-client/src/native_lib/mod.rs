@@ -4,7 +4,7 @@ mod proxy;
 
 pub(crate) use self::display::DisplayInner;
 pub(crate) use self::event_queue::EventQueueInner;
-pub(crate) use self::proxy::ProxyInner;
+pub(crate) use self::proxy::{ProxyInner, WeakProxyInner};
 
 use crate::{Interface, Main, Proxy};
 
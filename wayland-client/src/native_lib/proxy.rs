@@ -1,14 +1,15 @@
 use std::cell::RefCell;
 use std::os::raw::{c_int, c_void};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Weak};
+use std::sync::{Arc, Mutex, Weak};
 
-use crate::{Interface, Main, Proxy, RawEvent};
+use crate::{Interface, Main, Proxy, RawEvent, SendError};
 use wayland_commons::filter::Filter;
 use wayland_commons::user_data::UserData;
 use wayland_commons::wire::ArgumentType;
 use wayland_commons::MessageGroup;
 
+use super::event_queue::{MigratedEvent, MigratedEvents};
 use super::EventQueueInner;
 
 use wayland_sys::client::*;
@@ -17,11 +18,17 @@ use wayland_sys::common::*;
 pub struct ProxyInternal {
     alive: AtomicBool,
     user_data: UserData,
+    // the queue the object was moved to with set_queue
+    queue: Mutex<Option<MigratedEvents>>,
 }
 
 impl ProxyInternal {
     pub fn new(user_data: UserData) -> ProxyInternal {
-        ProxyInternal { alive: AtomicBool::new(true), user_data }
+        ProxyInternal { alive: AtomicBool::new(true), user_data, queue: Mutex::new(None) }
+    }
+
+    pub(crate) fn queue(&self) -> Option<MigratedEvents> {
+        self.queue.lock().unwrap().clone()
     }
 }
 
@@ -94,7 +101,11 @@ impl ProxyInner {
         }
     }
 
-    pub(crate) fn send<I, J>(&self, msg: I::Request, version: Option<u32>) -> Option<ProxyInner>
+    pub(crate) fn send<I, J>(
+        &self,
+        msg: I::Request,
+        version: Option<u32>,
+    ) -> Result<Option<ProxyInner>, SendError>
     where
         I: Interface,
         J: Interface + AsRef<Proxy<J>> + From<Proxy<J>>,
@@ -143,6 +154,11 @@ impl ProxyInner {
                             version
                         )
                     });
+                    if ptr.is_null() {
+                        // the object could not be created, for example because all
+                        // the object ids are in use
+                        return Err(SendError::IdsExhausted);
+                    }
                     let mut new_proxy = ProxyInner::init_from_c_ptr::<J>(ptr);
                     new_proxy.display = self.display.clone();
                     Some(new_proxy)
@@ -167,6 +183,12 @@ impl ProxyInner {
             None
         };
 
+        if alive {
+            if let Some(display) = self.display.as_ref().and_then(Weak::upgrade) {
+                display.request_buffered();
+            }
+        }
+
         if destructor && alive {
             // we need to destroy the proxy now
             if let Some(ref internal) = self.internal {
@@ -177,7 +199,7 @@ impl ProxyInner {
             }
         }
 
-        ret
+        Ok(ret)
     }
 
     pub(crate) fn equals(&self, other: &ProxyInner) -> bool {
@@ -191,6 +213,14 @@ impl ProxyInner {
         }
     }
 
+    pub(crate) fn downgrade(&self) -> WeakProxyInner {
+        WeakProxyInner {
+            internal: self.internal.as_ref().map(Arc::downgrade),
+            ptr: self.ptr,
+            display: self.display.clone(),
+        }
+    }
+
     pub(crate) fn detach(&mut self) {
         if !self.is_external() && !self.is_alive() {
             return;
@@ -220,6 +250,21 @@ impl ProxyInner {
         self.wrapping = Some(wrapper_ptr);
     }
 
+    pub(crate) fn set_queue(&mut self, queue: &EventQueueInner) {
+        if !self.is_external() && !self.is_alive() {
+            return;
+        }
+
+        unsafe {
+            queue.assign_proxy(self.ptr);
+        }
+        // the C library does not move the events already queued, the previous queue
+        // hands them over to the new one when dispatching them
+        if let Some(ref internal) = self.internal {
+            *internal.queue.lock().unwrap() = Some(queue.migrated.clone());
+        }
+    }
+
     pub(crate) fn c_ptr(&self) -> *mut wl_proxy {
         self.wrapping.unwrap_or(self.ptr)
     }
@@ -269,11 +314,14 @@ impl ProxyInner {
         ProxyInner { internal: Some(internal), ptr, wrapping: Some(ptr), display: None }
     }
 
-    fn dead() -> Self {
+    // An inert object, standing for a child that could not be created or was created
+    // by a dead object
+    pub(crate) fn dead() -> Self {
         ProxyInner {
             internal: Some(Arc::new(ProxyInternal {
                 alive: AtomicBool::new(false),
                 user_data: UserData::new(),
+                queue: Mutex::new(None),
             })),
             ptr: std::ptr::null_mut(),
             wrapping: None,
@@ -343,6 +391,33 @@ impl Drop for ProxyInner {
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct WeakProxyInner {
+    internal: Option<Weak<ProxyInternal>>,
+    ptr: *mut wl_proxy,
+    display: Option<Weak<super::display::DisplayGuard>>,
+}
+
+unsafe impl Send for WeakProxyInner {}
+unsafe impl Sync for WeakProxyInner {}
+
+impl WeakProxyInner {
+    pub(crate) fn upgrade(&self) -> Option<ProxyInner> {
+        let internal = match self.internal {
+            Some(ref weak) => Some(weak.upgrade()?),
+            // external proxies can't be tracked, same as `is_alive()`
+            None => None,
+        };
+        let inner =
+            ProxyInner { internal, ptr: self.ptr, wrapping: None, display: self.display.clone() };
+        if inner.is_alive() {
+            Some(inner)
+        } else {
+            None
+        }
+    }
+}
+
 type BoxedCallback<I> = Box<dyn Fn(<I as Interface>::Event, Main<I>, crate::DispatchData<'_>)>;
 
 struct ProxyUserData<I: Interface + From<Proxy<I>> + AsRef<Proxy<I>>> {
@@ -359,6 +434,12 @@ impl<I: Interface + From<Proxy<I>> + AsRef<Proxy<I>>> ProxyUserData<I> {
     }
 }
 
+// An event parsed for the callback of its object, or for the fallback of its queue
+enum ParsedEvent<I: Interface> {
+    Event(I::Event),
+    Raw(RawEvent),
+}
+
 unsafe extern "C" fn proxy_dispatcher<I: Interface>(
     _implem: *const c_void,
     proxy: *mut c_void,
@@ -374,50 +455,31 @@ where
     // We don't need to worry about panic-safeness, because if there is a panic,
     // we'll abort the process, so no access to corrupted data is possible.
     let ret = ::std::panic::catch_unwind(move || {
-        let must_destroy = I::Event::MESSAGES[opcode as usize].destructor;
-        // retrieve the impl
-        let user_data = ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_get_user_data, proxy);
-        {
-            let user_data = &mut *(user_data as *mut ProxyUserData<I>);
-            let implem = user_data.implem.borrow();
-
-            if must_destroy {
-                user_data.internal.alive.store(false, Ordering::Release);
-                ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, proxy);
-            }
-            // if there is an implem, call it, otherwise call the fallback
-            match implem.as_ref() {
-                Some(implem) => {
-                    // parse the message:
-                    let msg = I::Event::from_raw_c(proxy as *mut _, opcode, args)?;
-                    // create the proxy object
-                    let mut proxy_inner = ProxyInner::from_c_ptr::<I>(proxy);
-                    // This proxy must be a Main, so it as attached wrapping itself
-                    proxy_inner.wrapping = Some(proxy_inner.ptr);
-                    let proxy_obj = crate::Main::wrap(proxy_inner);
-                    super::event_queue::DISPATCH_METADATA.with(|meta| {
-                        let mut meta = meta.borrow_mut();
-                        let (_, ref mut dispatch_data) = *meta;
-                        implem(msg, proxy_obj, dispatch_data.reborrow());
-                    })
-                }
-                None => {
-                    // parse the message:
-                    let msg = parse_raw_event::<I>(opcode, args);
-                    // create the proxy object
-                    let proxy_obj = crate::Main::wrap(ProxyInner::from_c_ptr::<I>(proxy));
-                    super::event_queue::DISPATCH_METADATA.with(|meta| {
-                        let mut meta = meta.borrow_mut();
-                        let (ref mut fallback, ref mut dispatch_data) = *meta;
-                        (&mut *fallback)(msg, proxy_obj, dispatch_data.reborrow());
-                    })
-                }
+        let user_data = ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_get_user_data, proxy)
+            as *mut ProxyUserData<I>;
+        // parse the message:
+        let event = if (*user_data).implem.borrow().is_some() {
+            ParsedEvent::Event(I::Event::from_raw_c(proxy as *mut _, opcode, args)?)
+        } else {
+            ParsedEvent::Raw(parse_raw_event::<I>(opcode, args))
+        };
+        if let Some(queue) = (*user_data).internal.queue() {
+            if !super::event_queue::is_dispatching(&queue) {
+                // this event was queued before the object was moved to another queue,
+                // hand it over to its new queue
+                let internal = (*user_data).internal.clone();
+                let dispatch = Box::new(move || {
+                    // the object may have been destroyed since
+                    if internal.alive.load(Ordering::Acquire) {
+                        dispatch_event::<I>(proxy, opcode, event);
+                    }
+                });
+                let internal = (*user_data).internal.clone();
+                queue.lock().unwrap().push_back(MigratedEvent::new(internal, dispatch));
+                return Ok(());
             }
         }
-        if must_destroy {
-            // final cleanup
-            let _ = Box::from_raw(user_data as *mut ProxyUserData<I>);
-        }
+        dispatch_event::<I>(proxy, opcode, event);
         Ok(())
     });
     // check the return status
@@ -438,6 +500,55 @@ where
     }
 }
 
+// Give an event to the callback of its object, or to the fallback of the queue
+unsafe fn dispatch_event<I>(proxy: *mut wl_proxy, opcode: u32, event: ParsedEvent<I>)
+where
+    I: Interface + From<Proxy<I>> + AsRef<Proxy<I>>,
+{
+    let must_destroy = I::Event::MESSAGES[opcode as usize].destructor;
+    // retrieve the impl
+    let user_data = ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_get_user_data, proxy);
+    {
+        let user_data = &mut *(user_data as *mut ProxyUserData<I>);
+        let implem = user_data.implem.borrow();
+
+        if must_destroy {
+            user_data.internal.alive.store(false, Ordering::Release);
+            ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, proxy);
+        }
+        // if there is an implem, call it, otherwise call the fallback
+        match (event, implem.as_ref()) {
+            (ParsedEvent::Event(msg), Some(implem)) => {
+                // create the proxy object
+                let mut proxy_inner = ProxyInner::from_c_ptr::<I>(proxy);
+                // This proxy must be a Main, so it as attached wrapping itself
+                proxy_inner.wrapping = Some(proxy_inner.ptr);
+                let proxy_obj = crate::Main::wrap(proxy_inner);
+                super::event_queue::DISPATCH_METADATA.with(|meta| {
+                    let mut meta = meta.borrow_mut();
+                    let (_, ref mut dispatch_data) = *meta;
+                    implem(msg, proxy_obj, dispatch_data.reborrow());
+                })
+            }
+            (ParsedEvent::Raw(msg), _) => {
+                // create the proxy object
+                let proxy_obj = crate::Main::wrap(ProxyInner::from_c_ptr::<I>(proxy));
+                super::event_queue::DISPATCH_METADATA.with(|meta| {
+                    let mut meta = meta.borrow_mut();
+                    let (ref mut fallback, ref mut dispatch_data) = *meta;
+                    (&mut *fallback)(msg, proxy_obj, dispatch_data.reborrow());
+                })
+            }
+            // an implementation can not be removed once assigned
+            (ParsedEvent::Event(_), None) => unreachable!(),
+        }
+    }
+    if must_destroy {
+        // final cleanup
+        let _ = Box::from_raw(user_data as *mut ProxyUserData<I>);
+    }
+}
+
 unsafe fn parse_raw_event<I: Interface>(opcode: u32, args: *const wl_argument) -> RawEvent {
     let desc = &I::Event::MESSAGES[opcode as usize];
 
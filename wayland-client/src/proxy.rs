@@ -9,10 +9,30 @@ use wayland_sys::client::*;
 
 use crate::event_queue::QueueToken;
 
-use crate::imp::ProxyInner;
+use crate::imp::{ProxyInner, WeakProxyInner};
 
 use wayland_commons::{filter::Filter, MessageGroup};
 
+/// An error preventing a request from being sent
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SendError {
+    /// The request creates an object, but all the object ids of the connection are in use
+    ///
+    /// With the `use_system_lib` backend, this is also reported if the system library
+    /// fails to create the object for another reason, such as a failed allocation.
+    IdsExhausted,
+}
+
+impl ::std::error::Error for SendError {}
+
+impl ::std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match *self {
+            SendError::IdsExhausted => f.write_str("All the object ids are in use."),
+        }
+    }
+}
+
 /// An handle to a wayland proxy
 ///
 /// This represents a wayland object instantiated in your client
@@ -76,7 +96,61 @@ where
     /// but rather use the appropriate methods on the Rust object.
     ///
     /// This is the generic method to send requests.
+    ///
+    /// If the request creates an object but no object id is available anymore, the
+    /// request is not sent and the returned object is inert, as if it was already
+    /// destroyed. Use `try_send()` to detect this.
     pub fn send<J>(&self, msg: I::Request, version: Option<u32>) -> Option<Main<J>>
+    where
+        J: Interface + AsRef<Proxy<J>> + From<Proxy<J>>,
+    {
+        let opcode = msg.opcode() as usize;
+        match self.send_inner::<J>(msg, version) {
+            Ok(ret) => ret.map(Main::wrap),
+            Err(SendError::IdsExhausted) => {
+                eprintln!(
+                    "[wayland-client] Cannot create a new {} object: all the object ids are in \
+                    use. The request {} of {}@{} is not sent.",
+                    J::NAME,
+                    I::Request::MESSAGES[opcode].name,
+                    I::NAME,
+                    self.id()
+                );
+                // the native backend does not keep the interface of dead objects
+                #[cfg(feature = "use_system_lib")]
+                let child = ProxyInner::dead();
+                #[cfg(not(feature = "use_system_lib"))]
+                let child = self.inner.dead_child::<J>(version);
+                Some(Main::wrap(child))
+            }
+        }
+    }
+
+    /// Send a request through this object, failing if it can't create its object
+    ///
+    /// **Warning:** This method is mostly intended to be used by code generated
+    /// by `wayland-scanner`, and you should probably never need to use it directly,
+    /// but rather use the appropriate methods on the Rust object.
+    ///
+    /// This is the same as `send()`, except that if the request creates an object
+    /// but no object id is available anymore, the request is not sent and
+    /// `SendError::IdsExhausted` is returned.
+    pub fn try_send<J>(
+        &self,
+        msg: I::Request,
+        version: Option<u32>,
+    ) -> Result<Option<Main<J>>, SendError>
+    where
+        J: Interface + AsRef<Proxy<J>> + From<Proxy<J>>,
+    {
+        self.send_inner::<J>(msg, version).map(|ret| ret.map(Main::wrap))
+    }
+
+    fn send_inner<J>(
+        &self,
+        msg: I::Request,
+        version: Option<u32>,
+    ) -> Result<Option<ProxyInner>, SendError>
     where
         J: Interface + AsRef<Proxy<J>> + From<Proxy<J>>,
     {
@@ -91,7 +165,7 @@ where
                 self.version()
             );
         }
-        self.inner.send::<I, J>(msg, version).map(Main::wrap)
+        self.inner.send::<I, J>(msg, version)
     }
 
     /// Check if the object associated with this proxy is still alive
@@ -135,6 +209,17 @@ where
         self.inner.equals(&other.inner)
     }
 
+    /// Create a weak handle to this object
+    ///
+    /// The weak handle does not keep the object or its `UserData` alive, and
+    /// can be stored in the `UserData` of other objects without risking to
+    /// create reference cycles.
+    ///
+    /// See [`WeakProxy`](struct.WeakProxy.html) for details.
+    pub fn downgrade(&self) -> WeakProxy<I> {
+        WeakProxy { _i: ::std::marker::PhantomData, inner: self.inner.downgrade() }
+    }
+
     /// Attach this proxy to the event queue represented by this token
     ///
     /// Once a proxy is attached, you can use it to send requests that
@@ -149,6 +234,27 @@ where
         Attached { inner: other.into(), _s: std::marker::PhantomData }
     }
 
+    /// Move this object to the event queue represented by this token
+    ///
+    /// All future events of this object will be handled by this event queue
+    /// rather than the one it was previously assigned to. The events of this
+    /// object that were already read from the socket but not yet dispatched are
+    /// moved to the new event queue as well, preserving their order, after the
+    /// events already pending in it.
+    ///
+    /// **Note:** with the `use_system_lib` backend, the system library can not
+    /// move the events already read, so they are handed over to the new event
+    /// queue when the previous one is next dispatched. Until then, the new queue
+    /// may dispatch events of this object received later.
+    ///
+    /// This does not change the queue handling the objects created by this
+    /// handle, for this see `attach()`. Objects created from it by the server
+    /// (via a `new_id` argument in an event) will now be handled by the new
+    /// event queue.
+    pub fn set_queue(&self, token: QueueToken) {
+        self.inner.clone().set_queue(&token.inner);
+    }
+
     /// Erase the actual type of this proxy
     pub fn anonymize(self) -> Proxy<AnonymousObject> {
         Proxy { _i: ::std::marker::PhantomData, inner: self.inner }
@@ -166,6 +272,49 @@ impl Proxy<AnonymousObject> {
     }
 }
 
+/// A weak handle to a wayland proxy
+///
+/// This handle does not keep the Rust-side resources associated with the
+/// object (notably its `UserData`) alive. It can be upgraded back to a
+/// `Proxy<I>` as long as the protocol object it refers to is still alive,
+/// upgrading it once the object has been destroyed returns `None`.
+///
+/// It is obtained from the `Proxy::downgrade()` method.
+pub struct WeakProxy<I: Interface> {
+    _i: ::std::marker::PhantomData<&'static I>,
+    inner: WeakProxyInner,
+}
+
+impl<I: Interface> WeakProxy<I>
+where
+    I: AsRef<Proxy<I>> + From<Proxy<I>>,
+{
+    /// Try to recover a strong handle to the object
+    ///
+    /// Returns `None` if the object has been destroyed.
+    pub fn upgrade(&self) -> Option<Proxy<I>> {
+        self.inner.upgrade().map(Proxy::wrap)
+    }
+}
+
+impl<I: Interface> Clone for WeakProxy<I> {
+    fn clone(&self) -> WeakProxy<I> {
+        WeakProxy { _i: ::std::marker::PhantomData, inner: self.inner.clone() }
+    }
+}
+
+impl<I: Interface> Debug for WeakProxy<I>
+where
+    I: AsRef<Proxy<I>> + From<Proxy<I>>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.upgrade() {
+            Some(proxy) => write!(f, "{:?}[WEAK]", proxy),
+            None => write!(f, "{}@0[WEAK]", I::NAME),
+        }
+    }
+}
+
 impl<I: Interface + Debug> Debug for Attached<I> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}[ATTACHED]", self.inner)
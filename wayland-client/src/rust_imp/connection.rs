@@ -11,6 +11,7 @@ use wayland_commons::wire::{Argument, ArgumentType, Message, MessageParseError};
 use super::proxy::ObjectMeta;
 use super::queues::QueueBuffer;
 
+use crate::display::FlushState;
 use crate::ProtocolError;
 
 #[derive(Clone, Debug)]
@@ -25,6 +26,7 @@ pub(crate) struct Connection {
     pub(crate) map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
     pub(crate) last_error: Arc<Mutex<Option<Error>>>,
     pub(crate) display_buffer: QueueBuffer,
+    pub(crate) flush_state: FlushState,
 }
 
 impl Connection {
@@ -41,15 +43,28 @@ impl Connection {
             map: Arc::new(Mutex::new(map)),
             last_error: Arc::new(Mutex::new(None)),
             display_buffer,
+            flush_state: FlushState::new(),
         }
     }
 
     pub(crate) fn write_message(&mut self, msg: &Message) -> NixResult<()> {
-        self.socket.write_message(msg)
+        let flushes = self.socket.stats().flushes;
+        self.socket.write_message(msg)?;
+        if self.socket.stats().flushes != flushes {
+            // the buffer was full and the socket flushed it, only this request remains
+            self.flush_state.flushed();
+        }
+        if self.flush_state.request_buffered() {
+            // errors are reported by the next explicit flush or dispatch
+            let _ = self.flush();
+        }
+        Ok(())
     }
 
     pub(crate) fn flush(&mut self) -> NixResult<()> {
-        self.socket.flush()
+        self.socket.flush()?;
+        self.flush_state.flushed();
+        Ok(())
     }
 
     pub(crate) fn read_events(&mut self) -> Result<usize, Error> {
@@ -135,7 +150,7 @@ impl Connection {
                         }
                     }
                     Some(obj) => {
-                        obj.meta.buffer.lock().unwrap().push_back(msg);
+                        obj.meta.buffer.lock().unwrap().push_back((msg, obj.meta.alive.clone()));
                     }
                 };
 
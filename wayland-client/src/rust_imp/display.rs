@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::io;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::sync::atomic::Ordering;
@@ -10,7 +11,7 @@ use wayland_commons::MessageGroup;
 
 use crate::protocol::wl_display::{self, WlDisplay};
 
-use crate::{ConnectError, ProtocolError, Proxy};
+use crate::{ConnectError, ConnectionStats, FlushPolicy, ObjectCounts, ProtocolError, Proxy};
 
 use super::connection::{Connection, Error as CxError};
 use super::proxy::{ObjectMeta, ProxyInner};
@@ -66,6 +67,32 @@ impl DisplayInner {
         }
     }
 
+    pub(crate) fn set_flush_policy(&self, policy: FlushPolicy) {
+        self.connection.lock().unwrap().flush_state.set_policy(policy);
+    }
+
+    pub(crate) fn begin_batch(&self) {
+        let mut cx = self.connection.lock().unwrap();
+        if cx.flush_state.begin_batch() {
+            // make room for the batch
+            let _ = cx.flush();
+        }
+    }
+
+    pub(crate) fn end_batch(&self) {
+        let mut cx = self.connection.lock().unwrap();
+        if cx.flush_state.end_batch() {
+            let _ = cx.flush();
+        }
+    }
+
+    pub(crate) fn flush_after_dispatch(&self) {
+        let mut cx = self.connection.lock().unwrap();
+        if cx.flush_state.flush_after_dispatch() {
+            let _ = cx.flush();
+        }
+    }
+
     pub(crate) fn create_event_queue(me: &Arc<DisplayInner>) -> EventQueueInner {
         EventQueueInner::new(me.connection.clone(), None)
     }
@@ -74,6 +101,38 @@ impl DisplayInner {
         &self.proxy
     }
 
+    pub(crate) fn object_counts(&self) -> ObjectCounts {
+        let cx = self.connection.lock().unwrap();
+        let mut counts = ObjectCounts { live: 0, zombies: 0 };
+        cx.map.lock().unwrap().with_all(|_, obj| {
+            if obj.meta.client_destroyed {
+                counts.zombies += 1;
+            } else {
+                counts.live += 1;
+            }
+        });
+        counts
+    }
+
+    pub(crate) fn stats(&self) -> Option<ConnectionStats> {
+        let cx = self.connection.lock().unwrap();
+        let mut live_objects = HashMap::new();
+        cx.map.lock().unwrap().with_all(|_, obj| {
+            if !obj.meta.client_destroyed {
+                *live_objects.entry(obj.interface).or_insert(0) += 1;
+            }
+        });
+        Some(ConnectionStats { socket: cx.socket.stats(), live_objects })
+    }
+
+    pub(crate) fn set_max_message_size(&self, size: usize) {
+        self.connection.lock().unwrap().socket.set_max_message_size(size);
+    }
+
+    pub(crate) fn set_object_id_limit(&self, limit: u32) {
+        self.connection.lock().unwrap().map.lock().unwrap().set_client_id_limit(limit);
+    }
+
     pub(crate) fn protocol_error(&self) -> Option<ProtocolError> {
         let cx = self.connection.lock().unwrap();
         let last_error = cx.last_error.lock().unwrap();
@@ -17,7 +17,7 @@ mod proxy;
 mod queues;
 
 pub(crate) use self::display::DisplayInner;
-pub(crate) use self::proxy::ProxyInner;
+pub(crate) use self::proxy::{ProxyInner, WeakProxyInner};
 pub(crate) use self::queues::EventQueueInner;
 
 /// Flag to toggle debug output.
@@ -1,9 +1,9 @@
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 use wayland_commons::debug;
 use wayland_commons::filter::Filter;
-use wayland_commons::map::{Object, ObjectMap, ObjectMetadata};
+use wayland_commons::map::{IdsExhausted, Object, ObjectMap, ObjectMetadata};
 use wayland_commons::user_data::UserData;
 use wayland_commons::wire::{Argument, ArgumentType};
 use wayland_commons::MessageGroup;
@@ -11,7 +11,7 @@ use wayland_commons::MessageGroup;
 use super::connection::Connection;
 use super::queues::QueueBuffer;
 use super::{Dispatcher, EventQueueInner, WAYLAND_DEBUG};
-use crate::{Interface, Main, Proxy};
+use crate::{Interface, Main, Proxy, SendError};
 
 #[derive(Clone)]
 pub(crate) struct ObjectMeta {
@@ -117,7 +117,30 @@ impl ProxyInner {
         self.queue = Some(queue.buffer.clone())
     }
 
-    pub(crate) fn send<I, J>(&self, msg: I::Request, version: Option<u32>) -> Option<ProxyInner>
+    pub(crate) fn set_queue(&mut self, queue: &EventQueueInner) {
+        // hold the connection lock so that no event can be read and routed to the
+        // old queue while we are migrating
+        let _connection = self.connection.lock().unwrap();
+        let mut map = self.map.lock().unwrap();
+        let alive = &self.object.meta.alive;
+        let old_buffer = map.with(self.id, |obj| {
+            if Arc::ptr_eq(&obj.meta.alive, alive) {
+                Some(::std::mem::replace(&mut obj.meta.buffer, queue.buffer.clone()))
+            } else {
+                None
+            }
+        });
+        if let Ok(Some(old_buffer)) = old_buffer {
+            super::queues::migrate_messages(alive, &old_buffer, &queue.buffer);
+        }
+        self.object.meta.buffer = queue.buffer.clone();
+    }
+
+    pub(crate) fn send<I, J>(
+        &self,
+        msg: I::Request,
+        version: Option<u32>,
+    ) -> Result<Option<ProxyInner>, SendError>
     where
         I: Interface,
         J: Interface,
@@ -165,8 +188,16 @@ impl ProxyInner {
             );
             let mut new_id = 0;
             if alive {
-                new_id = self.map.lock().unwrap().client_insert_new(new_object.clone());
-                msg.args[nid_idx] = Argument::NewId(new_id);
+                match self.map.lock().unwrap().client_insert_new(new_object.clone()) {
+                    Ok(id) => {
+                        new_id = id;
+                        msg.args[nid_idx] = Argument::NewId(new_id);
+                    }
+                    Err(IdsExhausted) => {
+                        // the request is not sent
+                        return Err(SendError::IdsExhausted);
+                    }
+                }
             }
             Some(ProxyInner {
                 map: self.map.clone(),
@@ -191,7 +222,7 @@ impl ProxyInner {
 
         // Only actually send the message (& process destructor) if the object is alive.
         if !alive {
-            return ret;
+            return Ok(ret);
         }
 
         conn_lock.write_message(&msg).expect("Sending a message failed.");
@@ -213,13 +244,36 @@ impl ProxyInner {
             }
         }
 
-        ret
+        Ok(ret)
+    }
+
+    // An inert object standing for a child that could not be created
+    pub(crate) fn dead_child<J: Interface>(&self, version: Option<u32>) -> ProxyInner {
+        ProxyInner {
+            map: self.map.clone(),
+            connection: self.connection.clone(),
+            id: 0,
+            object: Object::from_interface::<J>(
+                version.unwrap_or(self.object.version),
+                ObjectMeta::dead(),
+            ),
+            queue: self.queue.clone(),
+        }
     }
 
     pub(crate) fn equals(&self, other: &ProxyInner) -> bool {
         self.is_alive() && Arc::ptr_eq(&self.object.meta.alive, &other.object.meta.alive)
     }
 
+    pub(crate) fn downgrade(&self) -> WeakProxyInner {
+        WeakProxyInner {
+            map: Arc::downgrade(&self.map),
+            connection: Arc::downgrade(&self.connection),
+            alive: Arc::downgrade(&self.object.meta.alive),
+            id: self.id,
+        }
+    }
+
     pub fn assign<I, E>(&self, filter: Filter<E>)
     where
         I: Interface + AsRef<Proxy<I>> + From<Proxy<I>> + Sync,
@@ -232,3 +286,28 @@ impl ProxyInner {
         });
     }
 }
+
+#[derive(Clone)]
+pub(crate) struct WeakProxyInner {
+    map: Weak<Mutex<ObjectMap<ObjectMeta>>>,
+    connection: Weak<Mutex<Connection>>,
+    alive: Weak<AtomicBool>,
+    id: u32,
+}
+
+impl WeakProxyInner {
+    pub(crate) fn upgrade(&self) -> Option<ProxyInner> {
+        let alive = self.alive.upgrade()?;
+        if !alive.load(Ordering::Acquire) {
+            return None;
+        }
+        let map = self.map.upgrade()?;
+        let connection = self.connection.upgrade()?;
+        // the id may have been reused since, make sure this is still the same object
+        let object = map.lock().unwrap().find(self.id)?;
+        if !Arc::ptr_eq(&object.meta.alive, &alive) {
+            return None;
+        }
+        Some(ProxyInner { map, connection, object, id: self.id, queue: None })
+    }
+}
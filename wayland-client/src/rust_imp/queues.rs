@@ -3,30 +3,57 @@ use std::collections::VecDeque;
 use std::io;
 use std::os::unix::io::AsRawFd;
 use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use nix::poll::{poll, PollFd, PollFlags};
 
 use wayland_commons::map::ObjectMap;
-use wayland_commons::wire::{Argument, Message};
+use wayland_commons::wire::{Argument, Message, MessageParseError};
 
 use super::connection::{Connection, Error as CError};
 use super::proxy::{ObjectMeta, ProxyInner};
 use super::Dispatched;
 
+use crate::event_queue::{poll_timeout, timeout_error};
 use crate::{AnonymousObject, DispatchData, Filter, Main, RawEvent};
 
-pub(crate) type QueueBuffer = Arc<Mutex<VecDeque<Message>>>;
+// Messages are queued along with the liveness flag of the object that sent them,
+// as its id may have been reused by another object by the time they are dispatched
+pub(crate) type QueueBuffer = Arc<Mutex<VecDeque<(Message, Arc<AtomicBool>)>>>;
 
 pub(crate) fn create_queue_buffer() -> QueueBuffer {
     Arc::new(Mutex::new(VecDeque::new()))
 }
 
+// Move the messages sent by given object from a buffer to another, preserving their
+// order. They are appended after the messages already in the destination buffer.
+pub(crate) fn migrate_messages(alive: &Arc<AtomicBool>, from: &QueueBuffer, to: &QueueBuffer) {
+    if Arc::ptr_eq(from, to) {
+        return;
+    }
+    // always lock the buffers in the same order, so that two objects migrating in
+    // opposite directions at the same time can not deadlock
+    let (mut from, mut to) = if (&**from as *const _) < (&**to as *const _) {
+        let from = from.lock().unwrap();
+        (from, to.lock().unwrap())
+    } else {
+        let to = to.lock().unwrap();
+        (from.lock().unwrap(), to)
+    };
+    let (moved, kept): (VecDeque<_>, VecDeque<_>) =
+        from.drain(..).partition(|(_, sender)| Arc::ptr_eq(sender, alive));
+    *from = kept;
+    to.extend(moved);
+}
+
 pub(crate) struct EventQueueInner {
     pub(crate) connection: Arc<Mutex<Connection>>,
     pub(crate) map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
     pub(crate) buffer: QueueBuffer,
     display_buffer: QueueBuffer,
+    max_depth: Cell<usize>,
 }
 
 impl EventQueueInner {
@@ -43,10 +70,23 @@ impl EventQueueInner {
             map,
             buffer: buffer.unwrap_or_else(create_queue_buffer),
             display_buffer,
+            max_depth: Cell::new(0),
         }
     }
 
-    pub(crate) fn dispatch<F>(&self, mut data: DispatchData, mut fallback: F) -> io::Result<u32>
+    pub(crate) fn dispatch<F>(&self, data: DispatchData, fallback: F) -> io::Result<u32>
+    where
+        F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
+    {
+        self.dispatch_deadline(data, fallback, None)
+    }
+
+    pub(crate) fn dispatch_deadline<F>(
+        &self,
+        mut data: DispatchData,
+        mut fallback: F,
+        deadline: Option<Instant>,
+    ) -> io::Result<u32>
     where
         F: FnMut(RawEvent, Main<AnonymousObject>, DispatchData<'_>),
     {
@@ -66,8 +106,15 @@ impl EventQueueInner {
                     Ok(_) => break,
                     Err(::nix::Error::Sys(::nix::errno::Errno::EAGAIN)) => {
                         // EAGAIN, we need to wait before writing, so we poll the socket
-                        let poll_ret = poll(&mut [PollFd::new(socket_fd, PollFlags::POLLOUT)], -1);
+                        let poll_ret = poll(
+                            &mut [PollFd::new(socket_fd, PollFlags::POLLOUT)],
+                            poll_timeout(deadline),
+                        );
                         match poll_ret {
+                            Ok(0) => {
+                                self.cancel_read();
+                                return Err(timeout_error());
+                            }
                             Ok(_) => continue,
                             Err(::nix::Error::Sys(e)) => {
                                 self.cancel_read();
@@ -90,7 +137,11 @@ impl EventQueueInner {
         }
 
         // wait for incoming messages to arrive
-        match poll(&mut [PollFd::new(socket_fd, PollFlags::POLLIN)], -1) {
+        match poll(&mut [PollFd::new(socket_fd, PollFlags::POLLIN)], poll_timeout(deadline)) {
+            Ok(0) => {
+                self.cancel_read();
+                return Err(timeout_error());
+            }
             Ok(_) => (),
             Err(::nix::Error::Sys(e)) => {
                 self.cancel_read();
@@ -120,7 +171,7 @@ impl EventQueueInner {
 
     fn dispatch_buffer<F>(
         &self,
-        buffer: &Mutex<VecDeque<Message>>,
+        buffer: &Mutex<VecDeque<(Message, Arc<AtomicBool>)>>,
         mut data: DispatchData,
         mut fallback: F,
     ) -> io::Result<u32>
@@ -131,20 +182,25 @@ impl EventQueueInner {
         let mut proxymap = super::ProxyMap::make(self.map.clone(), self.connection.clone());
         loop {
             let msg = { buffer.lock().unwrap().pop_front() };
-            let msg = match msg {
+            let (msg, sender) = match msg {
                 Some(m) => m,
                 None => break,
             };
             let id = msg.sender_id;
-            if let Some(proxy) = ProxyInner::from_id(id, self.map.clone(), self.connection.clone())
-            {
-                let object = proxy.object.clone();
-                if object.meta.client_destroyed {
+            let proxy = match ProxyInner::from_id(id, self.map.clone(), self.connection.clone()) {
+                Some(proxy)
+                    if Arc::ptr_eq(&proxy.object.meta.alive, &sender)
+                        && !proxy.object.meta.client_destroyed =>
+                {
+                    proxy
+                }
+                _ => {
                     // This is a potential race, if we reach here it means that the proxy was
-                    // destroyed by the user between this message was queued and now. To handle it
-                    // correctly, we must close any FDs it contains, mark any child object as
-                    // destroyed (but the server will never know about it, so the ids will be
-                    // leaked) and discard the event.
+                    // destroyed by the user between this message was queued and now, and its
+                    // id may even have been released and reused by a new object since. To
+                    // handle it correctly, we must close any FDs it contains, mark any child
+                    // object as destroyed (but the server will never know about it, so the ids
+                    // will be leaked) and discard the event.
                     for arg in msg.args {
                         match arg {
                             Argument::Fd(fd) => {
@@ -152,38 +208,39 @@ impl EventQueueInner {
                             }
                             Argument::NewId(id) => {
                                 let mut map = self.map.lock().unwrap();
-                                map.with(id, |obj| {
+                                let _ = map.with(id, |obj| {
                                     obj.meta.client_destroyed = true;
-                                })
-                                .unwrap();
+                                });
                             }
                             _ => {}
                         }
                     }
                     continue;
                 }
-                let mut dispatcher = object.meta.dispatcher.lock().unwrap();
-                match dispatcher.dispatch(msg, proxy, &mut proxymap, data.reborrow()) {
-                    Dispatched::Yes => {
-                        count += 1;
-                    }
-                    Dispatched::NoDispatch(msg, proxy) => {
-                        let raw_event = message_to_rawevent(msg, &proxy, &mut proxymap);
-                        fallback(raw_event, Main::wrap(proxy), data.reborrow());
-                        count += 1;
-                    }
-                    Dispatched::BadMsg => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::Other,
-                            format!("Dispatch for object {}@{} errored.", object.interface, id),
-                        ))
+            };
+            let object = proxy.object.clone();
+            let mut dispatcher = object.meta.dispatcher.lock().unwrap();
+            match dispatcher.dispatch(msg, proxy, &mut proxymap, data.reborrow()) {
+                Dispatched::Yes => {
+                    count += 1;
+                }
+                Dispatched::NoDispatch(msg, proxy) => {
+                    let raw_event = message_to_rawevent(msg, &proxy, &mut proxymap);
+                    fallback(raw_event, Main::wrap(proxy), data.reborrow());
+                    count += 1;
+                }
+                Dispatched::BadMsg => {
+                    // the server sent an invalid event, the connection is unusable
+                    let cx = self.connection.lock().unwrap();
+                    let mut last_error = cx.last_error.lock().unwrap();
+                    if last_error.is_none() {
+                        *last_error = Some(CError::Parse(MessageParseError::Malformed));
                     }
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("Dispatch for object {}@{} errored.", object.interface, id),
+                    ))
                 }
-            } else {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Received an event for unknown object {}.", id),
-                ));
             }
         }
         Ok(count)
@@ -198,11 +255,17 @@ impl EventQueueInner {
             self.dispatch_buffer(&self.display_buffer, data.reborrow(), |_, _, _| unreachable!())?;
 
         // Then our actual buffer
+        let depth = self.buffer.lock().unwrap().len();
+        self.max_depth.set(::std::cmp::max(self.max_depth.get(), depth));
         let self_dispatched = self.dispatch_buffer(&self.buffer, data.reborrow(), fallback)?;
 
         Ok(display_dispatched + self_dispatched)
     }
 
+    pub(crate) fn max_depth(&self) -> Option<usize> {
+        Some(self.max_depth.get())
+    }
+
     pub(crate) fn sync_roundtrip<F>(
         &self,
         mut data: DispatchData,
@@ -219,7 +282,10 @@ impl EventQueueInner {
         display.attach(&self);
 
         let done = Rc::new(Cell::new(false));
-        let cb = display.send::<WlDisplay, WlCallback>(DRequest::Sync {}, Some(1)).unwrap();
+        let cb = display
+            .send::<WlDisplay, WlCallback>(DRequest::Sync {}, Some(1))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+            .unwrap();
         let done2 = done.clone();
         cb.assign::<WlCallback, _>(Filter::new(move |(_, CbEvent::Done { .. }), _, _| {
             done2.set(true);
@@ -0,0 +1,416 @@
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::PathBuf;
+use std::ptr;
+use std::rc::{Rc, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nix::errno::Errno;
+use nix::sys::mman;
+
+use crate::protocol::{wl_buffer, wl_shm, wl_shm_pool, wl_surface};
+use crate::{Attached, Main};
+
+/// A growable shared memory pool to allocate buffers from
+///
+/// This utility manages a `wl_shm_pool` and the memory backing it, and allows
+/// you to allocate `wl_buffer`s from it. The memory is taken from a memfd when
+/// the platform supports it, and from an unlinked temporary file otherwise.
+///
+/// The pool tracks the `release` events of the buffers it creates: a buffer that
+/// was attached to a surface with `ShmBuffer::attach_to()` is considered busy until
+/// the server releases it, and its contents cannot be accessed in the meantime.
+/// When a `ShmBuffer` is dropped, its `wl_buffer` is destroyed as soon as the server
+/// no longer uses it, and its memory is made available for new buffers. The pool
+/// grows as needed to make room for new buffers.
+///
+/// ```no_run
+/// # use wayland_client::{Display, GlobalManager, ShmPool};
+/// use wayland_client::protocol::wl_shm;
+///
+/// # let display = Display::connect_to_env().unwrap();
+/// # let mut event_queue = display.create_event_queue();
+/// # let globals = GlobalManager::new(&display.attach(event_queue.token()));
+/// # event_queue.sync_roundtrip(&mut (), |_, _, _| unreachable!()).unwrap();
+/// let shm = globals.instantiate_exact::<wl_shm::WlShm>(1).unwrap();
+/// let mut pool = ShmPool::new(&shm, 4096).unwrap();
+///
+/// let buffer = pool.create_buffer(32, 32, 32 * 4, wl_shm::Format::Argb8888).unwrap();
+/// for pixel in pool.canvas(&buffer).unwrap().chunks_exact_mut(4) {
+///     pixel.copy_from_slice(&[0xFF, 0x00, 0x00, 0xFF]);
+/// }
+/// ```
+pub struct ShmPool {
+    pool: Main<wl_shm_pool::WlShmPool>,
+    inner: Rc<RefCell<PoolInner>>,
+}
+
+struct PoolInner {
+    file: File,
+    ptr: *mut u8,
+    len: usize,
+    // free regions of the pool, as (offset, len), sorted and never adjacent
+    free: Vec<(usize, usize)>,
+}
+
+impl ShmPool {
+    /// Create a new pool of given initial size
+    ///
+    /// The pool will be managed by the event queue the provided `wl_shm` is attached to.
+    pub fn new(shm: &Attached<wl_shm::WlShm>, size: usize) -> io::Result<ShmPool> {
+        let size = ::std::cmp::max(size, 1);
+        if size > std::i32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Requested pool is too large.",
+            ));
+        }
+        let file = create_shm_file()?;
+        file.set_len(size as u64)?;
+        let ptr = map(&file, size)?;
+        let inner = PoolInner { file, ptr, len: size, free: vec![(0, size)] };
+        let pool = shm.create_pool(inner.file.as_raw_fd(), size as i32);
+        Ok(ShmPool { pool, inner: Rc::new(RefCell::new(inner)) })
+    }
+
+    /// Create a new buffer from this pool
+    ///
+    /// The buffer will use `stride * height` bytes of the pool, which is
+    /// grown if no large enough free region is available.
+    ///
+    /// Fails with `InvalidInput` if the size is not positive, or if `stride` is
+    /// smaller than a row of `width` pixels of this format, in bytes.
+    pub fn create_buffer(
+        &mut self,
+        width: i32,
+        height: i32,
+        stride: i32,
+        format: wl_shm::Format,
+    ) -> io::Result<ShmBuffer> {
+        if width <= 0
+            || height <= 0
+            || i64::from(stride) < i64::from(width) * bytes_per_pixel(format) as i64
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid buffer dimensions."));
+        }
+        let len = stride as usize * height as usize;
+        let allocated = self.inner.borrow_mut().alloc(len);
+        let offset = match allocated {
+            Some(offset) => offset,
+            None => {
+                self.grow(len)?;
+                self.inner.borrow_mut().alloc(len).expect("The pool was grown for this buffer.")
+            }
+        };
+
+        let buffer = self.pool.create_buffer(offset as i32, width, height, stride, format);
+        let state = Rc::new(BufferState { busy: Cell::new(false), dropped: Cell::new(false) });
+        let state2 = state.clone();
+        let pool = Rc::downgrade(&self.inner);
+        buffer.quick_assign(move |buffer, wl_buffer::Event::Release, _| {
+            state2.busy.set(false);
+            if state2.dropped.get() {
+                free_buffer(&buffer, &pool, offset, len);
+            }
+        });
+
+        Ok(ShmBuffer {
+            buffer,
+            pool: Rc::downgrade(&self.inner),
+            state,
+            offset,
+            len,
+            width,
+            height,
+            stride,
+            format,
+        })
+    }
+
+    /// Access the contents of a buffer of this pool
+    ///
+    /// Returns `None` if the buffer is currently used by the server, or was not
+    /// allocated from this pool.
+    pub fn canvas(&mut self, buffer: &ShmBuffer) -> Option<&mut [u8]> {
+        if buffer.is_busy() || !Weak::ptr_eq(&buffer.pool, &Rc::downgrade(&self.inner)) {
+            return None;
+        }
+        let inner = self.inner.borrow();
+        // the buffer lies within the mapped memory, and we hold a unique borrow
+        // of the pool, which is the only way to access this memory
+        Some(unsafe { ::std::slice::from_raw_parts_mut(inner.ptr.add(buffer.offset), buffer.len) })
+    }
+
+    /// Resize the pool
+    ///
+    /// This only ever grows the pool, it does nothing if the requested size is
+    /// smaller or equal to its current size.
+    pub fn resize(&mut self, size: usize) -> io::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        if size <= inner.len {
+            return Ok(());
+        }
+        if size > std::i32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Requested pool is too large.",
+            ));
+        }
+        inner.file.set_len(size as u64)?;
+        let ptr = map(&inner.file, size)?;
+        unsafe {
+            let _ = mman::munmap(inner.ptr as *mut _, inner.len);
+        }
+        let old_len = inner.len;
+        inner.ptr = ptr;
+        inner.len = size;
+        inner.free(old_len, size - old_len);
+        self.pool.resize(size as i32);
+        Ok(())
+    }
+
+    /// Current size of the pool
+    pub fn len(&self) -> usize {
+        self.inner.borrow().len
+    }
+
+    /// Whether the pool is empty
+    ///
+    /// This is never the case, a pool always contains at least one byte.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Access the underlying `wl_shm_pool`
+    pub fn pool(&self) -> &Main<wl_shm_pool::WlShmPool> {
+        &self.pool
+    }
+
+    // grow the pool so that it has a free region of at least `len` bytes
+    fn grow(&mut self, len: usize) -> io::Result<()> {
+        let (current, tail) = {
+            let inner = self.inner.borrow();
+            // the last free region can be extended if it reaches the end of the pool
+            let tail = match inner.free.last() {
+                Some(&(offset, free_len)) if offset + free_len == inner.len => free_len,
+                _ => 0,
+            };
+            (inner.len, tail)
+        };
+        let needed = current + len - tail;
+        self.resize(::std::cmp::max(needed, current.saturating_mul(2)))
+    }
+}
+
+impl PoolInner {
+    fn alloc(&mut self, len: usize) -> Option<usize> {
+        let idx = self.free.iter().position(|&(_, free_len)| free_len >= len)?;
+        let (offset, free_len) = self.free[idx];
+        if free_len == len {
+            self.free.remove(idx);
+        } else {
+            self.free[idx] = (offset + len, free_len - len);
+        }
+        Some(offset)
+    }
+
+    fn free(&mut self, offset: usize, len: usize) {
+        let idx = self.free.iter().position(|&(o, _)| o > offset).unwrap_or(self.free.len());
+        self.free.insert(idx, (offset, len));
+        // merge with the next region
+        if idx + 1 < self.free.len() && offset + len == self.free[idx + 1].0 {
+            self.free[idx].1 += self.free[idx + 1].1;
+            self.free.remove(idx + 1);
+        }
+        // merge with the previous region
+        if idx > 0 && self.free[idx - 1].0 + self.free[idx - 1].1 == offset {
+            self.free[idx - 1].1 += self.free[idx].1;
+            self.free.remove(idx);
+        }
+    }
+}
+
+impl Drop for PoolInner {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mman::munmap(self.ptr as *mut _, self.len);
+        }
+    }
+}
+
+impl Drop for ShmPool {
+    fn drop(&mut self) {
+        // the server keeps the memory alive as long as buffers from this pool exist
+        self.pool.destroy();
+    }
+}
+
+struct BufferState {
+    busy: Cell<bool>,
+    dropped: Cell<bool>,
+}
+
+/// A buffer allocated from a `ShmPool`
+///
+/// When this handle is dropped, the `wl_buffer` is destroyed and its memory
+/// returned to the pool, once the server has released it.
+pub struct ShmBuffer {
+    buffer: Main<wl_buffer::WlBuffer>,
+    pool: Weak<RefCell<PoolInner>>,
+    state: Rc<BufferState>,
+    offset: usize,
+    len: usize,
+    width: i32,
+    height: i32,
+    stride: i32,
+    format: wl_shm::Format,
+}
+
+impl ShmBuffer {
+    /// Attach this buffer to a surface
+    ///
+    /// This sends a `wl_surface.attach` request and marks the buffer as busy
+    /// until the server releases it.
+    pub fn attach_to(&self, surface: &wl_surface::WlSurface, x: i32, y: i32) {
+        surface.attach(Some(&self.buffer), x, y);
+        self.state.busy.set(true);
+    }
+
+    /// Whether this buffer is currently used by the server
+    pub fn is_busy(&self) -> bool {
+        self.state.busy.get()
+    }
+
+    /// Mark this buffer as used by the server
+    ///
+    /// This is only needed if you attach its `wl_buffer` to a surface by other
+    /// means than `attach_to()`.
+    pub fn mark_busy(&self) {
+        self.state.busy.set(true);
+    }
+
+    /// Access the underlying `wl_buffer`
+    pub fn wl_buffer(&self) -> &Main<wl_buffer::WlBuffer> {
+        &self.buffer
+    }
+
+    /// Offset of this buffer in its pool
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Width of this buffer, in pixels
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    /// Height of this buffer, in pixels
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Stride of this buffer, in bytes
+    pub fn stride(&self) -> i32 {
+        self.stride
+    }
+
+    /// Pixel format of this buffer
+    pub fn format(&self) -> wl_shm::Format {
+        self.format
+    }
+}
+
+impl Drop for ShmBuffer {
+    fn drop(&mut self) {
+        self.state.dropped.set(true);
+        if !self.state.busy.get() {
+            free_buffer(&self.buffer, &self.pool, self.offset, self.len);
+        }
+    }
+}
+
+// The number of bytes used by a pixel of this format, or by the first plane
+// of a planar format
+fn bytes_per_pixel(format: wl_shm::Format) -> usize {
+    use wl_shm::Format::*;
+    match format {
+        C8 | Rgb332 | Bgr233 => 1,
+        Xrgb4444 | Xbgr4444 | Rgbx4444 | Bgrx4444 | Argb4444 | Abgr4444 | Rgba4444 | Bgra4444
+        | Xrgb1555 | Xbgr1555 | Rgbx5551 | Bgrx5551 | Argb1555 | Abgr1555 | Rgba5551 | Bgra5551
+        | Rgb565 | Bgr565 | Yuyv | Yvyu | Uyvy | Vyuy => 2,
+        Rgb888 | Bgr888 => 3,
+        Argb8888 | Xrgb8888 | Xbgr8888 | Rgbx8888 | Bgrx8888 | Abgr8888 | Rgba8888 | Bgra8888
+        | Xrgb2101010 | Xbgr2101010 | Rgbx1010102 | Bgrx1010102 | Argb2101010 | Abgr2101010
+        | Rgba1010102 | Bgra1010102 | Ayuv => 4,
+        // the planar formats have at least one byte per pixel in their first plane
+        _ => 1,
+    }
+}
+
+fn free_buffer(
+    buffer: &wl_buffer::WlBuffer,
+    pool: &Weak<RefCell<PoolInner>>,
+    offset: usize,
+    len: usize,
+) {
+    buffer.destroy();
+    if let Some(pool) = pool.upgrade() {
+        pool.borrow_mut().free(offset, len);
+    }
+}
+
+fn map(file: &File, len: usize) -> io::Result<*mut u8> {
+    let ptr = unsafe {
+        mman::mmap(
+            ptr::null_mut(),
+            len,
+            mman::ProtFlags::PROT_READ | mman::ProtFlags::PROT_WRITE,
+            mman::MapFlags::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    match ptr {
+        Ok(ptr) => Ok(ptr as *mut u8),
+        Err(nix::Error::Sys(errno)) => Err(errno.into()),
+        Err(_) => unreachable!(),
+    }
+}
+
+fn create_shm_file() -> io::Result<File> {
+    // Only try memfd on linux
+    #[cfg(target_os = "linux")]
+    loop {
+        use nix::sys::memfd;
+        use std::ffi::CStr;
+        match memfd::memfd_create(
+            CStr::from_bytes_with_nul(b"wayland-client-rs\0").unwrap(),
+            memfd::MemFdCreateFlag::MFD_CLOEXEC,
+        ) {
+            Ok(fd) => return Ok(unsafe { File::from_raw_fd(fd) }),
+            Err(nix::Error::Sys(Errno::EINTR)) => continue,
+            Err(nix::Error::Sys(Errno::ENOSYS)) => break,
+            Err(nix::Error::Sys(errno)) => return Err(errno.into()),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    // Fallback to an unlinked temporary file
+    let dir: PathBuf = env::var_os("XDG_RUNTIME_DIR").map(Into::into).unwrap_or_else(env::temp_dir);
+    loop {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        let path = dir.join(format!("wayland-client-rs-{}-{}", ::std::process::id(), nanos));
+        match OpenOptions::new().read(true).write(true).create_new(true).mode(0o600).open(&path) {
+            Ok(file) => {
+                ::std::fs::remove_file(&path)?;
+                return Ok(file);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -6,6 +6,18 @@ use std::cmp::Ordering;
 /// Limit separating server-created from client-created objects IDs in the namespace
 pub const SERVER_ID_LIMIT: u32 = 0xFF00_0000;
 
+/// Error returned when all the ids of the client namespace are in use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdsExhausted;
+
+impl std::error::Error for IdsExhausted {}
+
+impl std::fmt::Display for IdsExhausted {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        f.write_str("All the object ids are in use.")
+    }
+}
+
 /// A trait representing the metadata a wayland implementation
 /// may attach to an object.
 pub trait ObjectMetadata: Clone {
@@ -102,12 +114,25 @@ fn childs_from<M: MessageGroup, Meta: ObjectMetadata>(
 pub struct ObjectMap<Meta: ObjectMetadata> {
     client_objects: Vec<Option<Object<Meta>>>,
     server_objects: Vec<Option<Object<Meta>>>,
+    client_id_limit: u32,
 }
 
 impl<Meta: ObjectMetadata> ObjectMap<Meta> {
     /// Create a new empty object map
     pub fn new() -> ObjectMap<Meta> {
-        ObjectMap { client_objects: Vec::new(), server_objects: Vec::new() }
+        ObjectMap {
+            client_objects: Vec::new(),
+            server_objects: Vec::new(),
+            client_id_limit: SERVER_ID_LIMIT - 1,
+        }
+    }
+
+    /// Limit the ids allocated by `client_insert_new` to the range `1..=limit`
+    ///
+    /// The default is the whole client namespace, which a larger limit is clamped to.
+    /// Objects already using ids above the limit are kept.
+    pub fn set_client_id_limit(&mut self, limit: u32) {
+        self.client_id_limit = limit.min(SERVER_ID_LIMIT - 1);
     }
 
     /// Find an object in the store
@@ -151,8 +176,11 @@ impl<Meta: ObjectMetadata> ObjectMap<Meta> {
     }
 
     /// Allocate a new id for an object in the client namespace
-    pub fn client_insert_new(&mut self, object: Object<Meta>) -> u32 {
-        insert_in(&mut self.client_objects, object) + 1
+    ///
+    /// Fails if all the ids of the client namespace are in use, see `set_client_id_limit()`.
+    pub fn client_insert_new(&mut self, object: Object<Meta>) -> Result<u32, IdsExhausted> {
+        insert_in_bounded(&mut self.client_objects, object, self.client_id_limit as usize)
+            .map(|id| id + 1)
     }
 
     /// Allocate a new id for an object in the server namespace
@@ -212,6 +240,25 @@ fn insert_in<Meta: ObjectMetadata>(
     }
 }
 
+// insert a new object in a store at the first free place among the `limit` first ones
+fn insert_in_bounded<Meta: ObjectMetadata>(
+    store: &mut Vec<Option<Object<Meta>>>,
+    object: Object<Meta>,
+    limit: usize,
+) -> Result<u32, IdsExhausted> {
+    match store.iter().take(limit).position(Option::is_none) {
+        Some(id) => {
+            store[id] = Some(object);
+            Ok(id as u32)
+        }
+        None if store.len() < limit => {
+            store.push(Some(object));
+            Ok((store.len() - 1) as u32)
+        }
+        None => Err(IdsExhausted),
+    }
+}
+
 // insert an object at a given place in a store
 fn insert_in_at<Meta: ObjectMetadata>(
     store: &mut Vec<Option<Object<Meta>>>,
@@ -234,3 +281,37 @@ fn insert_in_at<Meta: ObjectMetadata>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_insert_reuses_ids() {
+        let mut store = Vec::new();
+        assert_eq!(insert_in_bounded(&mut store, Object::placeholder(()), 2), Ok(0));
+        assert_eq!(insert_in_bounded(&mut store, Object::placeholder(()), 2), Ok(1));
+        assert_eq!(insert_in_bounded(&mut store, Object::placeholder(()), 2), Err(IdsExhausted));
+        store[0] = None;
+        assert_eq!(insert_in_bounded(&mut store, Object::placeholder(()), 2), Ok(0));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn client_id_limit() {
+        let mut map = ObjectMap::<()>::new();
+        map.set_client_id_limit(3);
+        for id in 1..=3 {
+            assert_eq!(map.client_insert_new(Object::placeholder(())), Ok(id));
+        }
+        assert_eq!(map.client_insert_new(Object::placeholder(())), Err(IdsExhausted));
+
+        // ids freed above a lowered limit are not reused
+        map.set_client_id_limit(1);
+        map.remove(3);
+        assert_eq!(map.client_insert_new(Object::placeholder(())), Err(IdsExhausted));
+        map.remove(1);
+        assert_eq!(map.client_insert_new(Object::placeholder(())), Ok(1));
+        assert!(map.find(2).is_some());
+    }
+}
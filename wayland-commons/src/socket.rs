@@ -1,5 +1,6 @@
 //! Wayland socket manipulation
 
+use std::collections::HashMap;
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 
 use nix::{
@@ -13,6 +14,11 @@ use crate::wire::{ArgumentType, Message, MessageParseError, MessageWriteError};
 pub const MAX_FDS_OUT: usize = 28;
 /// Maximum number of bytes that can be sent in a single socket message
 pub const MAX_BYTES_OUT: usize = 4096;
+/// Largest size of a wayland message allowed by the wire format
+///
+/// The size of a message is encoded on 16 bits in its header, and is always
+/// a multiple of 4 bytes.
+pub const MAX_WIRE_MESSAGE_SIZE: usize = 0xFFFC;
 
 /*
  * Socket
@@ -28,18 +34,20 @@ impl Socket {
     ///
     /// A single socket message can contain several wayland messages
     ///
+    /// Return the number of bytes actually sent, which may be less than the length
+    /// of `bytes`. If any byte was sent, all the `fds` were sent along with it.
+    ///
     /// The `fds` slice should not be longer than `MAX_FDS_OUT`, and the `bytes`
     /// slice should not be longer than `MAX_BYTES_OUT` otherwise the receiving
     /// end may lose some data.
-    pub fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> NixResult<()> {
+    pub fn send_msg(&self, bytes: &[u8], fds: &[RawFd]) -> NixResult<usize> {
         let iov = [uio::IoVec::from_slice(bytes)];
         if !fds.is_empty() {
             let cmsgs = [socket::ControlMessage::ScmRights(fds)];
-            socket::sendmsg(self.fd, &iov, &cmsgs, socket::MsgFlags::MSG_DONTWAIT, None)?;
+            socket::sendmsg(self.fd, &iov, &cmsgs, socket::MsgFlags::MSG_DONTWAIT, None)
         } else {
-            socket::sendmsg(self.fd, &iov, &[], socket::MsgFlags::MSG_DONTWAIT, None)?;
-        };
-        Ok(())
+            socket::sendmsg(self.fd, &iov, &[], socket::MsgFlags::MSG_DONTWAIT, None)
+        }
     }
 
     /// Receive a single message from the socket
@@ -97,30 +105,111 @@ impl Drop for Socket {
     }
 }
 
+/*
+ * Statistics
+ */
+
+/// Statistics about the traffic of a `BufferedSocket`
+///
+/// All the counters start at zero when the socket is created.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SocketStats {
+    /// Number of messages written to the socket
+    pub messages_sent: u64,
+    /// Number of messages read from the socket
+    pub messages_received: u64,
+    /// Number of bytes sent through the socket
+    pub bytes_sent: u64,
+    /// Number of bytes received from the socket
+    pub bytes_received: u64,
+    /// Number of file descriptors sent through the socket
+    pub fds_sent: u64,
+    /// Number of file descriptors received from the socket
+    pub fds_received: u64,
+    /// Number of flushes of the outgoing buffer that had data to send
+    pub flushes: u64,
+    /// Number of times sending or receiving data failed because it would block (`EAGAIN`)
+    pub would_block: u64,
+}
+
+/// Statistics about a wayland connection
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Traffic statistics of the socket of the connection
+    pub socket: SocketStats,
+    /// Number of live objects of the connection, by interface name
+    pub live_objects: HashMap<&'static str, usize>,
+}
+
 /*
  * BufferedSocket
  */
 
 /// An adapter around a raw Socket that directly handles buffering and
 /// conversion from/to wayland messages
+///
+/// The buffers start with room for `MAX_BYTES_OUT` bytes, and grow as needed
+/// to hold messages up to the maximum message size of the socket. Outgoing data
+/// is sent in chunks of at most `MAX_BYTES_OUT` bytes.
 pub struct BufferedSocket {
     socket: Socket,
     in_data: Buffer<u32>,
     in_fds: Buffer<RawFd>,
     out_data: Buffer<u32>,
     out_fds: Buffer<RawFd>,
+    // number of bytes of the first word of out_data already sent
+    out_partial: usize,
+    max_message_size: usize,
+    stats: SocketStats,
 }
 
 impl BufferedSocket {
     /// Wrap a Socket into a Buffered Socket
+    ///
+    /// The maximum message size is initially `MAX_BYTES_OUT`.
     pub fn new(socket: Socket) -> BufferedSocket {
-        BufferedSocket {
+        BufferedSocket::with_max_message_size(socket, MAX_BYTES_OUT)
+    }
+
+    /// Wrap a Socket into a Buffered Socket accepting messages up to `size` bytes
+    ///
+    /// See `set_max_message_size()` for the allowed values.
+    pub fn with_max_message_size(socket: Socket, size: usize) -> BufferedSocket {
+        let mut socket = BufferedSocket {
             socket,
             in_data: Buffer::new(2 * MAX_BYTES_OUT / 4), // Incoming buffers are twice as big in order to be
             in_fds: Buffer::new(2 * MAX_FDS_OUT),        // able to store leftover data if needed
             out_data: Buffer::new(MAX_BYTES_OUT / 4),
             out_fds: Buffer::new(MAX_FDS_OUT),
-        }
+            out_partial: 0,
+            max_message_size: MAX_BYTES_OUT,
+            stats: SocketStats::default(),
+        };
+        socket.set_max_message_size(size);
+        socket
+    }
+
+    /// Set the maximum size in bytes of the messages sent and received through this socket
+    ///
+    /// Writing a bigger message fails with `E2BIG`, and receiving one is a
+    /// `MessageParseError::Malformed` error. The value is clamped between
+    /// `MAX_BYTES_OUT` and `MAX_WIRE_MESSAGE_SIZE`, and rounded down to a multiple of 4.
+    ///
+    /// Note that the system wayland library does not accept messages bigger than
+    /// `MAX_BYTES_OUT`, raising the limit only makes sense if the peer uses this crate.
+    pub fn set_max_message_size(&mut self, size: usize) {
+        let size = ::std::cmp::min(size, MAX_WIRE_MESSAGE_SIZE);
+        self.max_message_size = ::std::cmp::max(size, MAX_BYTES_OUT) & !3;
+    }
+
+    /// Get the maximum size in bytes of the messages sent and received through this socket
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Get the traffic statistics of this socket
+    pub fn stats(&self) -> SocketStats {
+        self.stats
     }
 
     /// Get direct access to the underlying socket
@@ -136,17 +225,51 @@ impl BufferedSocket {
     }
 
     /// Flush the contents of the outgoing buffer into the socket
+    ///
+    /// The data is sent in chunks of at most `MAX_BYTES_OUT` bytes, the file
+    /// descriptors being attached to the first one. If the socket would block, the
+    /// data that was not sent yet remains in the buffer for the next flush.
     pub fn flush(&mut self) -> NixResult<()> {
-        {
-            let words = self.out_data.get_contents();
-            let bytes = unsafe {
-                ::std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4)
+        if self.out_data.has_content() {
+            self.stats.flushes += 1;
+        }
+        // always send at least once, even an empty buffer, to detect a closed socket
+        loop {
+            let sent = {
+                let words = self.out_data.get_contents();
+                let bytes = unsafe {
+                    ::std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4)
+                };
+                let bytes = &bytes[self.out_partial..];
+                let bytes = &bytes[..bytes.len().min(MAX_BYTES_OUT)];
+                let fds = self.out_fds.get_contents();
+                match self.socket.send_msg(bytes, fds) {
+                    Ok(sent) => sent,
+                    Err(e) => {
+                        if let ::nix::Error::Sys(::nix::errno::Errno::EAGAIN) = e {
+                            self.stats.would_block += 1;
+                        }
+                        return Err(e);
+                    }
+                }
             };
-            let fds = self.out_fds.get_contents();
-            self.socket.send_msg(bytes, fds)?;
-            for &fd in fds {
-                // once the fds are sent, we can close them
-                let _ = ::nix::unistd::close(fd);
+            if sent > 0 {
+                let fds = self.out_fds.get_contents();
+                self.stats.fds_sent += fds.len() as u64;
+                for &fd in fds {
+                    // once the fds are sent, we can close them
+                    let _ = ::nix::unistd::close(fd);
+                }
+                let sent_fds = fds.len();
+                self.out_fds.offset(sent_fds);
+            }
+            self.stats.bytes_sent += sent as u64;
+            // a partial write may stop in the middle of a word
+            let sent = self.out_partial + sent;
+            self.out_data.offset(sent / 4);
+            self.out_partial = sent % 4;
+            if !self.out_data.has_content() {
+                break;
             }
         }
         self.out_data.clear();
@@ -180,19 +303,25 @@ impl BufferedSocket {
     ///
     /// This method may flush the internal buffer if necessary (if it is full).
     ///
-    /// If the message is too big to fit in the buffer, the error `Error::Sys(E2BIG)`
-    /// will be returned.
+    /// If the message is bigger than the maximum message size of the socket, the error
+    /// `Error::Sys(E2BIG)` will be returned.
     pub fn write_message(&mut self, msg: &Message) -> NixResult<()> {
         if !self.attempt_write_message(msg)? {
             // the attempt failed, there is not enough space in the buffer
             // we need to flush it
             self.flush()?;
             if !self.attempt_write_message(msg)? {
-                // If this fails again, this means the message is too big
-                // to be transmitted at all
-                return Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG));
+                // the message is bigger than the buffer, grow it up to the
+                // maximum message size
+                self.out_data.grow(self.max_message_size / 4);
+                if !self.attempt_write_message(msg)? {
+                    // If this fails again, this means the message is too big
+                    // to be transmitted at all
+                    return Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG));
+                }
             }
         }
+        self.stats.messages_sent += 1;
         Ok(())
     }
 
@@ -206,6 +335,20 @@ impl BufferedSocket {
         if !self.in_fds.has_content() {
             self.in_fds.clear();
         }
+        // make room for the rest of a partially received message
+        if self.in_data.get_writable_storage().is_empty() {
+            self.in_data.move_to_front();
+            if self.in_data.get_writable_storage().is_empty() {
+                self.in_data.grow(2 * self.max_message_size / 4);
+            }
+        }
+        if self.in_fds.get_writable_storage().is_empty() {
+            self.in_fds.move_to_front();
+            if self.in_fds.get_writable_storage().is_empty() {
+                let size = self.in_fds.storage.len() * 2;
+                self.in_fds.grow(size);
+            }
+        }
         // receive a message
         let (in_bytes, in_fds) = {
             let words = self.in_data.get_writable_storage();
@@ -213,12 +356,22 @@ impl BufferedSocket {
                 ::std::slice::from_raw_parts_mut(words.as_ptr() as *mut u8, words.len() * 4)
             };
             let fds = self.in_fds.get_writable_storage();
-            self.socket.rcv_msg(bytes, fds)?
+            match self.socket.rcv_msg(bytes, fds) {
+                Ok(ret) => ret,
+                Err(e) => {
+                    if let ::nix::Error::Sys(::nix::errno::Errno::EAGAIN) = e {
+                        self.stats.would_block += 1;
+                    }
+                    return Err(e);
+                }
+            }
         };
         if in_bytes == 0 {
             // the other end of the socket was closed
             return Err(::nix::Error::Sys(::nix::errno::Errno::EPIPE));
         }
+        self.stats.bytes_received += in_bytes as u64;
+        self.stats.fds_received += in_fds as u64;
         // advance the storage
         self.in_data.advance(in_bytes / 4 + if in_bytes % 4 > 0 { 1 } else { 0 });
         self.in_fds.advance(in_fds);
@@ -252,12 +405,19 @@ impl BufferedSocket {
             }
             let object_id = data[0];
             let opcode = (data[1] & 0x0000_FFFF) as u16;
+            let size = (data[1] >> 16) as usize;
+            if size > self.max_message_size {
+                return Err(MessageParseError::Malformed);
+            }
+            if size > data.len() * 4 {
+                // the rest of the message has not been received yet
+                return Err(MessageParseError::MissingData);
+            }
             if let Some(sig) = signature(object_id, opcode) {
                 match Message::from_raw(data, sig, fds) {
                     Ok((msg, rest_data, rest_fds)) => {
                         (msg, data.len() - rest_data.len(), fds.len() - rest_fds.len())
                     }
-                    // TODO: gracefully handle wayland messages split across unix messages ?
                     Err(e) => return Err(e),
                 }
             } else {
@@ -268,6 +428,7 @@ impl BufferedSocket {
 
         self.in_data.offset(read_data);
         self.in_fds.offset(read_fd);
+        self.stats.messages_received += 1;
 
         Ok(msg)
     }
@@ -388,6 +549,13 @@ impl<T: Copy + Default> Buffer<T> {
         self.occupied += bytes;
     }
 
+    /// Grow the storage of the buffer to `size` elements, if it is smaller
+    fn grow(&mut self, size: usize) {
+        if self.storage.len() < size {
+            self.storage.resize(size, T::default());
+        }
+    }
+
     /// Advance the read offset of current occupied space
     fn offset(&mut self, bytes: usize) {
         self.offset += bytes;
@@ -552,6 +720,139 @@ mod tests {
         assert_eq!(ret, 1);
     }
 
+    #[test]
+    fn socket_stats() {
+        let msg = Message {
+            sender_id: 42,
+            opcode: 7,
+            args: smallvec![Argument::Uint(3), Argument::Fd(1)],
+        };
+
+        let (client, server) = ::std::os::unix::net::UnixStream::pair().unwrap();
+        let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
+        let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
+
+        client.write_message(&msg).unwrap();
+        client.write_message(&msg).unwrap();
+        client.flush().unwrap();
+        // flushing an empty buffer is not counted
+        client.flush().unwrap();
+
+        static SIGNATURE: &[ArgumentType] = &[ArgumentType::Uint, ArgumentType::Fd];
+
+        let ret = server
+            .read_messages(
+                |_, _| Some(SIGNATURE),
+                |message| {
+                    if let Argument::Fd(fd) = message.args[1] {
+                        let _ = ::nix::unistd::close(fd);
+                    }
+                    true
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(ret, 2);
+
+        // there is nothing more to read
+        assert!(server.read_messages(|_, _| Some(SIGNATURE), |_| true).is_err());
+
+        assert_eq!(
+            client.stats(),
+            SocketStats {
+                messages_sent: 2,
+                bytes_sent: 24,
+                fds_sent: 2,
+                flushes: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            server.stats(),
+            SocketStats {
+                messages_received: 2,
+                bytes_received: 24,
+                fds_received: 2,
+                // both reads stopped on EAGAIN
+                would_block: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn write_read_cycle_large_message() {
+        let msg = Message {
+            sender_id: 42,
+            opcode: 0,
+            args: smallvec![
+                Argument::Fd(1), // stdout
+                Argument::Array(Box::new((0..40_000).map(|i| i as u8).collect())),
+            ],
+        };
+
+        static SIGNATURE: &[ArgumentType] = &[ArgumentType::Fd, ArgumentType::Array];
+
+        let (client, server) = ::std::os::unix::net::UnixStream::pair().unwrap();
+        let mut client = BufferedSocket::new(unsafe { Socket::from_raw_fd(client.into_raw_fd()) });
+        let mut server = BufferedSocket::with_max_message_size(
+            unsafe { Socket::from_raw_fd(server.into_raw_fd()) },
+            MAX_WIRE_MESSAGE_SIZE,
+        );
+
+        // the message does not fit in the default maximum size
+        assert_eq!(client.write_message(&msg), Err(::nix::Error::Sys(::nix::errno::Errno::E2BIG)));
+
+        client.set_max_message_size(MAX_WIRE_MESSAGE_SIZE);
+        assert_eq!(client.max_message_size(), MAX_WIRE_MESSAGE_SIZE);
+        client.write_message(&msg).unwrap();
+        client.flush().unwrap();
+        assert_eq!(client.stats().bytes_sent, 40_000 + 12);
+
+        let ret = server
+            .read_messages(
+                |_, _| Some(SIGNATURE),
+                |message| {
+                    assert_eq_msgs(&message, &msg);
+                    if let Argument::Fd(fd) = message.args[0] {
+                        let _ = ::nix::unistd::close(fd);
+                    }
+                    true
+                },
+            )
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(ret, 1);
+    }
+
+    #[test]
+    fn reject_oversized_message() {
+        let msg = Message {
+            sender_id: 42,
+            opcode: 0,
+            args: smallvec![Argument::Array(Box::new(vec![0; 10_000]))],
+        };
+
+        static SIGNATURE: &[ArgumentType] = &[ArgumentType::Array];
+
+        let (client, server) = ::std::os::unix::net::UnixStream::pair().unwrap();
+        let mut client = BufferedSocket::with_max_message_size(
+            unsafe { Socket::from_raw_fd(client.into_raw_fd()) },
+            MAX_WIRE_MESSAGE_SIZE,
+        );
+        let mut server = BufferedSocket::new(unsafe { Socket::from_raw_fd(server.into_raw_fd()) });
+
+        client.write_message(&msg).unwrap();
+        client.flush().unwrap();
+
+        let ret = server.read_messages(|_, _| Some(SIGNATURE), |_| panic!("Unexpected message."));
+        match ret {
+            Ok(Err(MessageParseError::Malformed)) => {}
+            _ => panic!("The message was not rejected."),
+        }
+    }
+
     #[test]
     fn write_read_cycle_multiple() {
         let messages = [
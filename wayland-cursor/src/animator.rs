@@ -0,0 +1,288 @@
+//! Driving the animation of a cursor surface
+
+use std::{
+    cell::RefCell,
+    io,
+    os::unix::io::{AsRawFd, RawFd},
+    rc::{Rc, Weak},
+    time::{Duration, Instant},
+};
+
+use wayland_client::{
+    protocol::{wl_callback, wl_surface::WlSurface},
+    Attached,
+};
+
+use crate::Cursor;
+
+/// Displays a cursor on a surface, animating it if needed
+///
+/// The animator owns the cursor surface, and attaches to it the frame of the cursor
+/// that should currently be shown. Once a cursor is set with `set_cursor`, give the
+/// surface to `wl_pointer.set_cursor` along with the hotspot returned by `hotspot`.
+///
+/// Animated cursors are advanced either:
+///
+/// - with `wl_surface.frame` callbacks, for an animator created with `new`. The animation
+///   then progresses as the events of the queue of the surface are dispatched, and stops
+///   while the compositor does not show the surface.
+/// - with a timer, for an animator created with `with_timer`. The animator is then a
+///   file descriptor, to be polled for readability in your event loop, calling
+///   `dispatch_timer` when it is readable.
+pub struct CursorAnimator {
+    surface: Attached<WlSurface>,
+    inner: Rc<RefCell<Inner>>,
+}
+
+struct Inner {
+    surface: Attached<WlSurface>,
+    cursor: Option<Cursor>,
+    start: Instant,
+    frame: usize,
+    hotspot: (i32, i32),
+    // incremented on each change of cursor, so that callbacks of the previous one are ignored
+    generation: u32,
+    timer: Option<TimerFd>,
+}
+
+impl CursorAnimator {
+    /// Create an animator advancing the animation with frame callbacks
+    pub fn new(surface: Attached<WlSurface>) -> CursorAnimator {
+        CursorAnimator::with_inner(surface, None)
+    }
+
+    /// Create an animator advancing the animation with a timer
+    ///
+    /// The returned animator should be polled for readability, using its `AsRawFd`
+    /// implementation, and `dispatch_timer` called when it is readable.
+    pub fn with_timer(surface: Attached<WlSurface>) -> io::Result<CursorAnimator> {
+        let timer = TimerFd::new()?;
+        Ok(CursorAnimator::with_inner(surface, Some(timer)))
+    }
+
+    fn with_inner(surface: Attached<WlSurface>, timer: Option<TimerFd>) -> CursorAnimator {
+        CursorAnimator {
+            surface: surface.clone(),
+            inner: Rc::new(RefCell::new(Inner {
+                surface,
+                cursor: None,
+                start: Instant::now(),
+                frame: 0,
+                hotspot: (0, 0),
+                generation: 0,
+                timer,
+            })),
+        }
+    }
+
+    /// Show a cursor on the surface, starting its animation from the first frame
+    ///
+    /// The first frame is attached and committed right away. The hotspot of the surface
+    /// is adjusted to the one of the new cursor, so changing the cursor while the surface
+    /// is already set as the pointer cursor does not require a new `wl_pointer.set_cursor`.
+    pub fn set_cursor(&mut self, cursor: &Cursor) {
+        let mut inner = self.inner.borrow_mut();
+        inner.generation = inner.generation.wrapping_add(1);
+        inner.cursor = Some(cursor.clone());
+        inner.start = Instant::now();
+        // the buffer scale was introduced in version 3 of wl_surface
+        if inner.surface.as_ref().version() >= 3 {
+            inner.surface.set_buffer_scale(cursor.scale() as i32);
+        }
+        inner.show_frame(0);
+        inner.schedule(&self.inner);
+        inner.surface.commit();
+    }
+
+    /// Hide the cursor, stopping its animation
+    pub fn unset_cursor(&mut self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.generation = inner.generation.wrapping_add(1);
+        inner.cursor = None;
+        if let Some(ref timer) = inner.timer {
+            let _ = timer.set(None);
+        }
+        inner.surface.attach(None, 0, 0);
+        inner.surface.commit();
+    }
+
+    /// The cursor currently shown, if any
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.inner.borrow().cursor.clone()
+    }
+
+    /// The hotspot of the current frame, in surface coordinates
+    ///
+    /// These are the coordinates to give to `wl_pointer.set_cursor`.
+    pub fn hotspot(&self) -> (i32, i32) {
+        self.inner.borrow().hotspot
+    }
+
+    /// The cursor surface
+    pub fn surface(&self) -> &Attached<WlSurface> {
+        &self.surface
+    }
+
+    /// Advance the animation after the timer expired
+    ///
+    /// This should be called when the animator is readable. It does nothing if the timer
+    /// has not expired, or for an animator created with `new`.
+    pub fn dispatch_timer(&mut self) -> io::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        let expired = match inner.timer {
+            Some(ref timer) => timer.read()?,
+            None => false,
+        };
+        if expired && inner.cursor.is_some() {
+            inner.advance(&self.inner);
+        }
+        Ok(())
+    }
+}
+
+impl AsRawFd for CursorAnimator {
+    /// The file descriptor of the timer
+    ///
+    /// **Panic:** this method panics if the animator was not created with `with_timer`.
+    fn as_raw_fd(&self) -> RawFd {
+        match self.inner.borrow().timer {
+            Some(ref timer) => timer.fd,
+            None => panic!("This CursorAnimator does not use a timer."),
+        }
+    }
+}
+
+impl Inner {
+    /// Attach the given frame of the cursor, keeping the hotspot in place
+    fn show_frame(&mut self, frame: usize) {
+        let cursor = match self.cursor {
+            Some(ref cursor) => cursor,
+            None => return,
+        };
+        let image = &cursor[frame];
+        let (x, y) = image.surface_hotspot();
+        let hotspot = (x as i32, y as i32);
+        self.surface.attach(Some(image), self.hotspot.0 - hotspot.0, self.hotspot.1 - hotspot.1);
+        self.surface.damage(0, 0, std::i32::MAX, std::i32::MAX);
+        self.frame = frame;
+        self.hotspot = hotspot;
+    }
+
+    /// Show the frame to display now, and schedule the next change
+    fn advance(&mut self, this: &Rc<RefCell<Inner>>) {
+        let frame = match self.cursor {
+            Some(ref cursor) => cursor.frame_and_duration(elapsed_millis(self.start)).frame_index,
+            None => return,
+        };
+        if frame != self.frame {
+            self.show_frame(frame);
+        }
+        self.schedule(this);
+        self.surface.commit();
+    }
+
+    /// Schedule the next change of frame, if the cursor is animated
+    ///
+    /// With frame callbacks, the request is sent with the next commit.
+    fn schedule(&mut self, this: &Rc<RefCell<Inner>>) {
+        let remaining = match self.cursor {
+            Some(ref cursor) => {
+                cursor.frame_and_duration(elapsed_millis(self.start)).frame_duration
+            }
+            None => return,
+        };
+        if remaining == 0 {
+            if let Some(ref timer) = self.timer {
+                let _ = timer.set(None);
+            }
+            return;
+        }
+
+        match self.timer {
+            Some(ref timer) => {
+                let _ = timer.set(Some(Duration::from_millis(remaining as u64)));
+            }
+            None => {
+                let generation = self.generation;
+                let weak: Weak<RefCell<Inner>> = Rc::downgrade(this);
+                let callback = self.surface.frame();
+                callback.quick_assign(move |_, event, _| {
+                    if let wl_callback::Event::Done { .. } = event {
+                        if let Some(inner) = weak.upgrade() {
+                            let mut guard = inner.borrow_mut();
+                            if guard.generation == generation {
+                                guard.advance(&inner);
+                            }
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn elapsed_millis(start: Instant) -> u32 {
+    start.elapsed().as_millis() as u32
+}
+
+/// A monotonic one-shot timer
+struct TimerFd {
+    fd: RawFd,
+}
+
+impl TimerFd {
+    fn new() -> io::Result<TimerFd> {
+        let fd = unsafe {
+            libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_CLOEXEC | libc::TFD_NONBLOCK)
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(TimerFd { fd })
+    }
+
+    /// Arm the timer to expire after the given duration, or disarm it
+    fn set(&self, duration: Option<Duration>) -> io::Result<()> {
+        let zero = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        let value = match duration {
+            // a zero value would disarm the timer
+            Some(duration) if duration == Duration::from_secs(0) => {
+                libc::timespec { tv_sec: 0, tv_nsec: 1 }
+            }
+            Some(duration) => libc::timespec {
+                tv_sec: duration.as_secs() as libc::time_t,
+                tv_nsec: duration.subsec_nanos() as libc::c_long,
+            },
+            None => zero,
+        };
+        let spec = libc::itimerspec { it_interval: zero, it_value: value };
+        let ret = unsafe { libc::timerfd_settime(self.fd, 0, &spec, std::ptr::null_mut()) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Consume the expiration of the timer, returning whether it had expired
+    fn read(&self) -> io::Result<bool> {
+        let mut expirations = 0u64;
+        let ret =
+            unsafe { libc::read(self.fd, &mut expirations as *mut u64 as *mut libc::c_void, 8) };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::WouldBlock {
+                return Ok(false);
+            }
+            return Err(err);
+        }
+        Ok(expirations > 0)
+    }
+}
+
+impl Drop for TimerFd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
@@ -0,0 +1,370 @@
+//! Built-in cursors, used when no theme provides a cursor
+//!
+//! The cursors are drawn on a 16x16 grid, where `X` is a black pixel, `.` a white one,
+//! and a space a transparent one. They are scaled by an integer factor to get as close as
+//! possible to the requested size.
+//!
+//! Each shape is known by the CSS name of its cursor, the other names of the cursor being
+//! resolved by `icon::candidate_names`.
+
+use xcursor::parser::Image;
+
+const BASE_SIZE: u32 = 16;
+
+struct Shape {
+    name: &'static str,
+    hotspot: (u32, u32),
+    pixels: [&'static str; BASE_SIZE as usize],
+    transform: Transform,
+}
+
+#[derive(Copy, Clone)]
+enum Transform {
+    Normal,
+    Transposed,
+    Mirrored,
+}
+
+const LEFT_PTR: [&str; 16] = [
+    "X               ",
+    "XX              ",
+    "X.X             ",
+    "X..X            ",
+    "X...X           ",
+    "X....X          ",
+    "X.....X         ",
+    "X......X        ",
+    "X.......X       ",
+    "X........X      ",
+    "X.....XXXXX     ",
+    "X..X..X         ",
+    "X.X X..X        ",
+    "XX  X..X        ",
+    "X    X..X       ",
+    "     XXXX       ",
+];
+
+const XTERM: [&str; 16] = [
+    "   XXXX XXXX    ",
+    "   X...X...X    ",
+    "   XXXX.XXXX    ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "      X.X       ",
+    "   XXXX.XXXX    ",
+    "   X...X...X    ",
+    "   XXXX XXXX    ",
+];
+
+const WATCH: [&str; 16] = [
+    "  XXXXXXXXXXX   ",
+    "  X.........X   ",
+    "  XX.......XX   ",
+    "   X.......X    ",
+    "    X.....X     ",
+    "     X...X      ",
+    "      X.X       ",
+    "      X.X       ",
+    "     X...X      ",
+    "    X.....X     ",
+    "   X.......X    ",
+    "  XX.......XX   ",
+    "  X.........X   ",
+    "  XXXXXXXXXXX   ",
+    "                ",
+    "                ",
+];
+
+const HAND: [&str; 16] = [
+    "     XX         ",
+    "    X..X        ",
+    "    X..X        ",
+    "    X..X        ",
+    "    X..XXX      ",
+    "    X..X..XXX   ",
+    "    X..X..X..XX ",
+    " XXXX..X..X..X.X",
+    "X..XX..........X",
+    "X...X..........X",
+    " X.............X",
+    "  X............X",
+    "   X..........X ",
+    "    X.........X ",
+    "     X.......X  ",
+    "     XXXXXXXXX  ",
+];
+
+const H_DOUBLE_ARROW: [&str; 16] = [
+    "                ",
+    "                ",
+    "                ",
+    "                ",
+    "    X      X    ",
+    "   X.X    X.X   ",
+    "  X..XXXXXX..X  ",
+    " X............X ",
+    "  X..XXXXXX..X  ",
+    "   X.X    X.X   ",
+    "    X      X    ",
+    "                ",
+    "                ",
+    "                ",
+    "                ",
+    "                ",
+];
+
+const BD_DOUBLE_ARROW: [&str; 16] = [
+    "                ",
+    " XXXXX          ",
+    " X..X           ",
+    " X...X          ",
+    " XX...X         ",
+    " X X...X        ",
+    "    X...X       ",
+    "     X...X      ",
+    "      X...X X   ",
+    "       X...XX   ",
+    "        X...X   ",
+    "         X..X   ",
+    "        XXXXX   ",
+    "                ",
+    "                ",
+    "                ",
+];
+
+const SHAPES: &[Shape] = &[
+    Shape { name: "default", hotspot: (0, 0), pixels: LEFT_PTR, transform: Transform::Normal },
+    Shape { name: "text", hotspot: (7, 8), pixels: XTERM, transform: Transform::Normal },
+    Shape { name: "wait", hotspot: (7, 7), pixels: WATCH, transform: Transform::Normal },
+    Shape { name: "pointer", hotspot: (5, 0), pixels: HAND, transform: Transform::Normal },
+    Shape {
+        name: "ew-resize",
+        hotspot: (7, 7),
+        pixels: H_DOUBLE_ARROW,
+        transform: Transform::Normal,
+    },
+    Shape {
+        name: "ns-resize",
+        hotspot: (7, 7),
+        pixels: H_DOUBLE_ARROW,
+        transform: Transform::Transposed,
+    },
+    Shape {
+        name: "nwse-resize",
+        hotspot: (6, 6),
+        pixels: BD_DOUBLE_ARROW,
+        transform: Transform::Normal,
+    },
+    Shape {
+        name: "nesw-resize",
+        hotspot: (9, 6),
+        pixels: BD_DOUBLE_ARROW,
+        transform: Transform::Mirrored,
+    },
+];
+
+/// Cursors without a shape of their own, and the cursor whose shape they use
+const SIMILAR: &[(&str, &str)] = &[
+    ("progress", "wait"),
+    ("e-resize", "ew-resize"),
+    ("w-resize", "ew-resize"),
+    ("col-resize", "ew-resize"),
+    ("n-resize", "ns-resize"),
+    ("s-resize", "ns-resize"),
+    ("row-resize", "ns-resize"),
+    ("nw-resize", "nwse-resize"),
+    ("se-resize", "nwse-resize"),
+    ("ne-resize", "nesw-resize"),
+    ("sw-resize", "nesw-resize"),
+];
+
+/// The shape drawing the cursor with the CSS name `name`
+fn shape(name: &str) -> Option<&'static Shape> {
+    let name =
+        SIMILAR.iter().find(|&&(cursor, _)| cursor == name).map_or(name, |&(_, shape)| shape);
+    SHAPES.iter().find(|shape| shape.name == name)
+}
+
+/// Get the image of a built-in cursor, as close as possible to `size`
+///
+/// `name` is the CSS name of the cursor, its other names are not recognized.
+pub(crate) fn load_images(name: &str, size: u32) -> Option<Vec<Image>> {
+    let shape = shape(name)?;
+    let factor = std::cmp::max(1, size / BASE_SIZE);
+    let side = BASE_SIZE * factor;
+
+    let mut pixels_rgba = Vec::with_capacity((side * side * 4) as usize);
+    let mut pixels_argb = Vec::with_capacity((side * side * 4) as usize);
+    for y in 0..side {
+        for x in 0..side {
+            let (col, row) = match shape.transform {
+                Transform::Normal => (x / factor, y / factor),
+                Transform::Transposed => (y / factor, x / factor),
+                Transform::Mirrored => (BASE_SIZE - 1 - x / factor, y / factor),
+            };
+            // pixels are premultiplied ARGB, stored as little-endian u32 like in xcursor files
+            let (alpha, value) = match shape.pixels[row as usize].as_bytes()[col as usize] {
+                b'X' => (0xff, 0x00),
+                b'.' => (0xff, 0xff),
+                _ => (0x00, 0x00),
+            };
+            pixels_rgba.extend_from_slice(&[value, value, value, alpha]);
+            pixels_argb.extend_from_slice(&[alpha, value, value, value]);
+        }
+    }
+
+    Some(vec![Image {
+        size: side,
+        width: side,
+        height: side,
+        xhot: shape.hotspot.0 * factor,
+        yhot: shape.hotspot.1 * factor,
+        delay: 0,
+        pixels_rgba,
+        pixels_argb,
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon;
+
+    fn image(name: &str, size: u32) -> Image {
+        let mut images = load_images(name, size).unwrap();
+        assert_eq!(images.len(), 1);
+        images.remove(0)
+    }
+
+    // the (alpha, value) of a pixel, as in the pixel grids
+    fn pixel(image: &Image, x: u32, y: u32) -> (u8, u8) {
+        let offset = ((y * image.width + x) * 4) as usize;
+        let rgba = &image.pixels_rgba[offset..offset + 4];
+        assert_eq!(rgba[0..3], [rgba[0]; 3]);
+        assert_eq!(image.pixels_argb[offset..offset + 4], [rgba[3], rgba[0], rgba[0], rgba[0]]);
+        (rgba[3], rgba[0])
+    }
+
+    #[test]
+    fn scaling_factor() {
+        // sizes and the sides of the images loaded for them
+        let sides = [(1, 16), (16, 16), (24, 16), (31, 16), (32, 32), (48, 48), (50, 48)];
+        for &(size, side) in &sides {
+            let image = image("default", size);
+            assert_eq!((image.size, image.width, image.height), (side, side, side));
+            assert_eq!(image.pixels_rgba.len(), (side * side * 4) as usize);
+        }
+
+        // each pixel of the grid becomes a square of `factor` pixels
+        let small = image("pointer", 16);
+        let large = image("pointer", 48);
+        for y in 0..48 {
+            for x in 0..48 {
+                assert_eq!(pixel(&large, x, y), pixel(&small, x / 3, y / 3));
+            }
+        }
+        assert_eq!(pixel(&small, 0, 0), (0x00, 0x00));
+        assert_eq!(pixel(&small, 5, 0), (0xff, 0x00));
+        assert_eq!(pixel(&small, 5, 1), (0xff, 0xff));
+    }
+
+    #[test]
+    fn hotspot_scaling() {
+        let hotspot = |name, size| {
+            let image = image(name, size);
+            (image.xhot, image.yhot)
+        };
+        assert_eq!(hotspot("text", 16), (7, 8));
+        assert_eq!(hotspot("text", 32), (14, 16));
+        assert_eq!(hotspot("text", 48), (21, 24));
+        assert_eq!(hotspot("pointer", 24), (5, 0));
+        assert_eq!(hotspot("pointer", 64), (20, 0));
+    }
+
+    #[test]
+    fn transforms() {
+        for &size in &[16, 32] {
+            let horizontal = image("ew-resize", size);
+            let vertical = image("ns-resize", size);
+            let backward = image("nwse-resize", size);
+            let forward = image("nesw-resize", size);
+            let side = horizontal.width;
+            for y in 0..side {
+                for x in 0..side {
+                    assert_eq!(pixel(&vertical, x, y), pixel(&horizontal, y, x));
+                    assert_eq!(pixel(&forward, x, y), pixel(&backward, side - 1 - x, y));
+                }
+            }
+
+            // the hotspots follow the pixels they point at
+            let factor = side / BASE_SIZE;
+            assert_eq!((vertical.xhot, vertical.yhot), (horizontal.yhot, horizontal.xhot));
+            assert_eq!(
+                (forward.xhot, forward.yhot),
+                (side - factor - backward.xhot, backward.yhot)
+            );
+        }
+
+        // the arrow of ns-resize points up and down
+        let vertical = image("ns-resize", 16);
+        assert_eq!(pixel(&vertical, 7, 1), (0xff, 0x00));
+        assert_eq!(pixel(&vertical, 7, 2), (0xff, 0xff));
+        assert_eq!(pixel(&vertical, 1, 7), (0x00, 0x00));
+        // the arrow of nesw-resize goes from the top right corner
+        let forward = image("nesw-resize", 16);
+        assert_eq!(pixel(&forward, 14, 1), (0xff, 0x00));
+        assert_eq!(pixel(&forward, 13, 2), (0xff, 0xff));
+        assert_eq!(pixel(&forward, 1, 1), (0x00, 0x00));
+    }
+
+    #[test]
+    fn names() {
+        // the shape found for the first of the candidate names having one, like on lookup
+        let resolve = |name| {
+            icon::candidate_names(name).into_iter().filter_map(shape).map(|shape| shape.name).next()
+        };
+        let names = [
+            ("left_ptr", "default"),
+            ("top_left_arrow", "default"),
+            ("xterm", "text"),
+            ("ibeam", "text"),
+            ("watch", "wait"),
+            ("progress", "wait"),
+            ("left_ptr_watch", "wait"),
+            ("hand2", "pointer"),
+            ("pointing_hand", "pointer"),
+            ("sb_h_double_arrow", "ew-resize"),
+            ("col-resize", "ew-resize"),
+            ("left_side", "ew-resize"),
+            ("right_side", "ew-resize"),
+            ("sb_v_double_arrow", "ns-resize"),
+            ("row-resize", "ns-resize"),
+            ("top_side", "ns-resize"),
+            ("bottom_side", "ns-resize"),
+            ("bd_double_arrow", "nwse-resize"),
+            ("top_left_corner", "nwse-resize"),
+            ("bottom_right_corner", "nwse-resize"),
+            ("fd_double_arrow", "nesw-resize"),
+            ("top_right_corner", "nesw-resize"),
+            ("bottom_left_corner", "nesw-resize"),
+        ];
+        for &(name, shape) in &names {
+            assert_eq!(resolve(name), Some(shape), "{}", name);
+        }
+        for shape in SHAPES {
+            assert_eq!(resolve(shape.name), Some(shape.name));
+        }
+        assert_eq!(resolve("crosshair"), None);
+
+        // only CSS names are known by the shapes themselves
+        assert!(load_images("left_ptr", 24).is_none());
+        assert!(load_images("crosshair", 24).is_none());
+    }
+}
@@ -0,0 +1,263 @@
+//! Cursor names, and the aliases they are known by in cursor themes
+
+use std::fmt;
+
+/// A cursor, named after the CSS `cursor` property values
+///
+/// This is the set of cursors an application usually needs. They are named after their
+/// CSS name, which is also the name used by the `cursor-shape-v1` protocol.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum CursorIcon {
+    Default,
+    ContextMenu,
+    Help,
+    Pointer,
+    Progress,
+    Wait,
+    Cell,
+    Crosshair,
+    Text,
+    VerticalText,
+    Alias,
+    Copy,
+    Move,
+    NoDrop,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    EResize,
+    NResize,
+    NeResize,
+    NwResize,
+    SResize,
+    SeResize,
+    SwResize,
+    WResize,
+    EwResize,
+    NsResize,
+    NeswResize,
+    NwseResize,
+    ColResize,
+    RowResize,
+    AllScroll,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl CursorIcon {
+    /// The CSS name of this cursor
+    pub fn name(self) -> &'static str {
+        match self {
+            CursorIcon::Default => "default",
+            CursorIcon::ContextMenu => "context-menu",
+            CursorIcon::Help => "help",
+            CursorIcon::Pointer => "pointer",
+            CursorIcon::Progress => "progress",
+            CursorIcon::Wait => "wait",
+            CursorIcon::Cell => "cell",
+            CursorIcon::Crosshair => "crosshair",
+            CursorIcon::Text => "text",
+            CursorIcon::VerticalText => "vertical-text",
+            CursorIcon::Alias => "alias",
+            CursorIcon::Copy => "copy",
+            CursorIcon::Move => "move",
+            CursorIcon::NoDrop => "no-drop",
+            CursorIcon::NotAllowed => "not-allowed",
+            CursorIcon::Grab => "grab",
+            CursorIcon::Grabbing => "grabbing",
+            CursorIcon::EResize => "e-resize",
+            CursorIcon::NResize => "n-resize",
+            CursorIcon::NeResize => "ne-resize",
+            CursorIcon::NwResize => "nw-resize",
+            CursorIcon::SResize => "s-resize",
+            CursorIcon::SeResize => "se-resize",
+            CursorIcon::SwResize => "sw-resize",
+            CursorIcon::WResize => "w-resize",
+            CursorIcon::EwResize => "ew-resize",
+            CursorIcon::NsResize => "ns-resize",
+            CursorIcon::NeswResize => "nesw-resize",
+            CursorIcon::NwseResize => "nwse-resize",
+            CursorIcon::ColResize => "col-resize",
+            CursorIcon::RowResize => "row-resize",
+            CursorIcon::AllScroll => "all-scroll",
+            CursorIcon::ZoomIn => "zoom-in",
+            CursorIcon::ZoomOut => "zoom-out",
+        }
+    }
+}
+
+impl AsRef<str> for CursorIcon {
+    fn as_ref(&self) -> &str {
+        self.name()
+    }
+}
+
+impl fmt::Display for CursorIcon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Names designating the same cursor, the CSS name first
+///
+/// Themes ship cursors under their CSS names, their legacy X11 names, or hashes of
+/// their bitmap in the X11 cursor font, and often only under some of them.
+const ALIASES: &[&[&str]] = &[
+    &["default", "left_ptr", "arrow", "top_left_arrow", "left_arrow"],
+    &["context-menu"],
+    &[
+        "help",
+        "question_arrow",
+        "whats_this",
+        "d9ce0ab605698f320427677b458ad60b",
+        "5c6cd98b3f3ebcb1f9c7f1c204630408",
+    ],
+    &[
+        "pointer",
+        "hand2",
+        "hand1",
+        "hand",
+        "pointing_hand",
+        "e29285e634086352946a0e7090d73106",
+        "9d800788f1b08800ae810202380a0822",
+    ],
+    &[
+        "progress",
+        "left_ptr_watch",
+        "half-busy",
+        "3ecb610c1bf2410f44200f48c40d3599",
+        "08e8e1c95fe2fc01f976f1e063a24ccd",
+    ],
+    &["wait", "watch"],
+    &["cell", "plus"],
+    &["crosshair", "cross", "tcross"],
+    &["text", "xterm", "ibeam"],
+    &["vertical-text"],
+    &[
+        "alias",
+        "dnd-link",
+        "link",
+        "640fb0e74195791501fd1ed57b41487f",
+        "3085a0e285430894940527032f8b26df",
+    ],
+    &["copy", "dnd-copy", "1081e37283d90000800003c07f3ef6bf", "6407b0e94181790501fd1e167b474872"],
+    &[
+        "move",
+        "dnd-move",
+        "fleur",
+        "4498f0e0c1937ffe01fd06f973665830",
+        "9081237383d90e509aa00f00170e968f",
+    ],
+    &["no-drop", "dnd-no-drop", "circle"],
+    &["not-allowed", "crossed_circle", "forbidden", "03b6e0fcb3499374a867c041f52298f0"],
+    &["grab", "openhand"],
+    &["grabbing", "closedhand", "fleur"],
+    &["e-resize", "right_side"],
+    &["n-resize", "top_side"],
+    &["ne-resize", "top_right_corner"],
+    &["nw-resize", "top_left_corner"],
+    &["s-resize", "bottom_side"],
+    &["se-resize", "bottom_right_corner"],
+    &["sw-resize", "bottom_left_corner"],
+    &["w-resize", "left_side"],
+    &[
+        "ew-resize",
+        "sb_h_double_arrow",
+        "h_double_arrow",
+        "size_hor",
+        "028006030e0e7ebffc7f7070c0600140",
+    ],
+    &[
+        "ns-resize",
+        "sb_v_double_arrow",
+        "v_double_arrow",
+        "size_ver",
+        "00008160000006810000408080010102",
+    ],
+    &["nesw-resize", "fd_double_arrow", "size_bdiag", "fcf1c3c7cd4491d801f1e1c78f100000"],
+    &["nwse-resize", "bd_double_arrow", "size_fdiag", "c7088f0f3e6c8088236ef8e1e3e70000"],
+    &["col-resize", "split_h", "sb_h_double_arrow", "14fef782d02440884392942c11205230"],
+    &["row-resize", "split_v", "sb_v_double_arrow", "2870a09082c103050810ffdffffe0204"],
+    &["all-scroll", "fleur", "size_all"],
+    &["zoom-in", "zoom_in"],
+    &["zoom-out", "zoom_out"],
+];
+
+/// The names to look for to find the cursor `name`, starting with `name` itself
+pub(crate) fn candidate_names(name: &str) -> Vec<&str> {
+    let mut names = vec![name];
+    for group in ALIASES.iter().filter(|group| group.contains(&name)) {
+        for alias in group.iter() {
+            if !names.contains(alias) {
+                names.push(alias);
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICONS: [CursorIcon; 34] = [
+        CursorIcon::Default,
+        CursorIcon::ContextMenu,
+        CursorIcon::Help,
+        CursorIcon::Pointer,
+        CursorIcon::Progress,
+        CursorIcon::Wait,
+        CursorIcon::Cell,
+        CursorIcon::Crosshair,
+        CursorIcon::Text,
+        CursorIcon::VerticalText,
+        CursorIcon::Alias,
+        CursorIcon::Copy,
+        CursorIcon::Move,
+        CursorIcon::NoDrop,
+        CursorIcon::NotAllowed,
+        CursorIcon::Grab,
+        CursorIcon::Grabbing,
+        CursorIcon::EResize,
+        CursorIcon::NResize,
+        CursorIcon::NeResize,
+        CursorIcon::NwResize,
+        CursorIcon::SResize,
+        CursorIcon::SeResize,
+        CursorIcon::SwResize,
+        CursorIcon::WResize,
+        CursorIcon::EwResize,
+        CursorIcon::NsResize,
+        CursorIcon::NeswResize,
+        CursorIcon::NwseResize,
+        CursorIcon::ColResize,
+        CursorIcon::RowResize,
+        CursorIcon::AllScroll,
+        CursorIcon::ZoomIn,
+        CursorIcon::ZoomOut,
+    ];
+
+    #[test]
+    fn every_icon_has_aliases() {
+        for icon in ICONS.iter() {
+            let group = ALIASES.iter().find(|group| group[0] == icon.name());
+            assert!(group.is_some(), "{} has no alias group", icon);
+            assert_eq!(candidate_names(icon.name())[0], icon.name());
+        }
+        // every alias group is the one of an icon, and icons have distinct names
+        assert_eq!(ALIASES.len(), ICONS.len());
+    }
+
+    #[test]
+    fn candidates() {
+        assert_eq!(candidate_names("pointer")[..3], ["pointer", "hand2", "hand1"]);
+        // a name in several groups gets the aliases of all of them, without duplicates
+        let names = candidate_names("sb_h_double_arrow");
+        assert_eq!(names[..3], ["sb_h_double_arrow", "ew-resize", "h_double_arrow"]);
+        assert!(names.contains(&"col-resize"));
+        assert_eq!(names.iter().filter(|&&name| name == "sb_h_double_arrow").count(), 1);
+        // unknown names are still looked for
+        assert_eq!(candidate_names("custom"), ["custom"]);
+    }
+}
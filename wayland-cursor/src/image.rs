@@ -0,0 +1,462 @@
+//! Loading of cursor images, independently of any wayland connection
+
+use std::{env, fs::File, io::Read, ops::Index, path::PathBuf};
+
+use xcursor::parser as xparser;
+
+use crate::search::{self, CursorLocation};
+use crate::{fallback, icon};
+
+/// A cursor theme, loading cursors as raw images
+///
+/// This provides the same cursor lookup as `CursorTheme`, but gives access to the pixels
+/// of the cursors rather than uploading them to `wl_buffer`s. This is meant for
+/// compositors drawing the pointer themselves.
+#[derive(Clone, Debug)]
+pub struct CursorImageTheme {
+    name: String,
+    size: u32,
+    search_paths: Vec<PathBuf>,
+}
+
+impl CursorImageTheme {
+    /// Load a cursor theme from system defaults.
+    ///
+    /// Same as calling `load_or("default", size)`
+    pub fn load(size: u32) -> Self {
+        CursorImageTheme::load_or("default", size)
+    }
+
+    /// Load a cursor theme, using `name` as fallback.
+    ///
+    /// The theme name and cursor size are read from the `XCURSOR_THEME` and
+    /// `XCURSOR_SIZE` environment variables, respectively, or from the provided variables
+    /// if those are invalid.
+    pub fn load_or(name: &str, size: u32) -> Self {
+        CursorThemeBuilder::new(size).name(name).build_images()
+    }
+
+    /// Create a new cursor theme, ignoring the system defaults.
+    pub fn load_from_name(name: &str, size: u32) -> Self {
+        CursorThemeBuilder::new(size).name(name).ignore_env().build_images()
+    }
+
+    /// Name of this theme
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Nominal size of the cursors of this theme, at scale 1
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Directories the themes are looked for in, in lookup order
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// The themes cursors are looked for in, in lookup order
+    ///
+    /// This is this theme, followed by the themes it inherits from, as listed by the
+    /// `Inherits` key of their `index.theme` files. The "default" theme is inherited
+    /// by themes which do not specify their parents.
+    pub fn inheritance(&self) -> Vec<String> {
+        search::inheritance_chain(&self.name, &self.search_paths)
+            .into_iter()
+            .map(|theme| theme.name)
+            .collect()
+    }
+
+    /// Find the file providing a cursor
+    ///
+    /// This returns the file, and the theme it belongs to, that would be loaded for this
+    /// cursor. It returns `None` if the cursor is not provided by any theme, in which case
+    /// a built-in fallback cursor would be used, if any.
+    pub fn locate<N: AsRef<str>>(&self, name: N) -> Option<CursorLocation> {
+        let names = icon::candidate_names(name.as_ref());
+        let chain = search::inheritance_chain(&self.name, &self.search_paths);
+        let location = search::locate(&chain, &names).next();
+        location
+    }
+
+    /// Load the images of a cursor, for a surface with the given buffer scale.
+    ///
+    /// The cursor can be designated by a `CursorIcon`, or by any of its names. If the theme
+    /// does not provide a cursor with this name, the other names of this cursor are tried.
+    ///
+    /// This method returns `None` if this cursor is not provided
+    /// either by the theme, by one of its parents, or by the built-in fallback cursors.
+    ///
+    /// **Panic:** this method panics if `scale` is 0.
+    pub fn load_cursor<N: AsRef<str>>(&self, name: N, scale: u32) -> Option<CursorImages> {
+        assert!(scale > 0, "A cursor scale must be at least 1.");
+        let name = name.as_ref();
+        let size = self.size * scale;
+
+        let names = icon::candidate_names(name);
+        let chain = search::inheritance_chain(&self.name, &self.search_paths);
+        let found = search::locate(&chain, &names)
+            .filter_map(|location| Some((load_images(&location)?, location)))
+            .next();
+        let (images, location) = match found {
+            Some((images, location)) => (images, Some(location)),
+            None => {
+                let images =
+                    names.iter().filter_map(|name| fallback::load_images(name, size)).next()?;
+                (images, None)
+            }
+        };
+
+        Some(CursorImages::new(name, &images, size, scale, location))
+    }
+}
+
+/// Read and parse the images of a cursor file.
+fn load_images(location: &CursorLocation) -> Option<Vec<xparser::Image>> {
+    let mut icon_file = File::open(&location.path).ok()?;
+
+    let mut buf = Vec::new();
+    icon_file.read_to_end(&mut buf).ok()?;
+    xparser::parse_xcursor(&buf).filter(|images| !images.is_empty())
+}
+
+/// A builder for cursor themes
+///
+/// By default, the builder loads the theme named "default" with the size given to `new`,
+/// unless other ones are set in the `XCURSOR_THEME` and `XCURSOR_SIZE` environment
+/// variables, and looks for themes in the standard directories, or those of `XCURSOR_PATH`.
+///
+/// ```ignore
+/// use wayland_cursor::CursorThemeBuilder;
+///
+/// let theme = CursorThemeBuilder::new(24)
+///     .name("Adwaita")
+///     .search_path("/app/share/icons")
+///     .build(&wl_shm);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CursorThemeBuilder {
+    name: String,
+    size: u32,
+    use_env: bool,
+    search_paths: Vec<PathBuf>,
+}
+
+impl CursorThemeBuilder {
+    /// Start building a cursor theme, with the given nominal size
+    pub fn new(size: u32) -> Self {
+        CursorThemeBuilder {
+            name: String::from("default"),
+            size,
+            use_env: true,
+            search_paths: Vec::new(),
+        }
+    }
+
+    /// Set the name of the theme to load
+    ///
+    /// This name is used if `XCURSOR_THEME` is not set, or if `ignore_env` is used.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = String::from(name);
+        self
+    }
+
+    /// Ignore the `XCURSOR_THEME` and `XCURSOR_SIZE` environment variables
+    ///
+    /// `XCURSOR_PATH` is still used to find the themes.
+    pub fn ignore_env(mut self) -> Self {
+        self.use_env = false;
+        self
+    }
+
+    /// Add a directory to look for themes in
+    ///
+    /// The directory is looked into after the standard directories, or those of
+    /// `XCURSOR_PATH`, so that themes installed by the user take precedence. This
+    /// can be used for themes bundled with an application.
+    pub fn search_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    /// Build a theme loading cursors as raw images
+    pub fn build_images(self) -> CursorImageTheme {
+        let mut name = self.name;
+        let mut size = self.size;
+        if self.use_env {
+            if let Ok(var) = env::var("XCURSOR_THEME") {
+                name = var;
+            }
+            if let Some(int) = env::var("XCURSOR_SIZE").ok().and_then(|var| var.parse().ok()) {
+                size = int;
+            }
+        }
+
+        let mut search_paths = search::default_search_paths();
+        search_paths.extend(self.search_paths);
+
+        CursorImageTheme { name, size, search_paths }
+    }
+
+    /// Build a theme loading cursors into `wl_buffer`s
+    #[cfg(feature = "client")]
+    pub fn build(
+        self,
+        shm: &wayland_client::Attached<wayland_client::protocol::wl_shm::WlShm>,
+    ) -> crate::CursorTheme {
+        crate::CursorTheme::new(self.build_images(), shm)
+    }
+}
+
+/// The images of a cursor. Can contain several images if animated.
+#[derive(Clone, Debug)]
+pub struct CursorImages {
+    name: String,
+    images: Vec<CursorImage>,
+    total_duration: u32,
+    scale: u32,
+    location: Option<CursorLocation>,
+}
+
+impl CursorImages {
+    /// Keep the images of the size closest to `size`.
+    fn new(
+        name: &str,
+        images: &[xparser::Image],
+        size: u32,
+        scale: u32,
+        location: Option<CursorLocation>,
+    ) -> Self {
+        let size = nearest_size(size, images);
+
+        let images: Vec<_> = images
+            .iter()
+            .filter(|el| el.width == size && el.height == size)
+            .map(CursorImage::new)
+            .collect();
+
+        let total_duration = images.iter().map(|el| el.delay).sum();
+
+        CursorImages { name: String::from(name), images, total_duration, scale, location }
+    }
+
+    /// Name this cursor was requested with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Given a time, calculate which frame to show, and how much time remains until the next frame.
+    ///
+    /// Time will wrap, so if for instance the cursor has an animation during 100ms,
+    /// then calling this function with 5ms and 105ms as input gives the same output.
+    pub fn frame_and_duration(&self, millis: u32) -> FrameAndDuration {
+        frame_and_duration(self.images.iter().map(|img| img.delay), self.total_duration, millis)
+    }
+
+    /// Total number of images forming this cursor animation
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
+    /// The images forming this cursor animation
+    pub fn images(&self) -> &[CursorImage] {
+        &self.images
+    }
+
+    /// The buffer scale this cursor was loaded for
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Whether this cursor is one of the built-in fallback cursors
+    ///
+    /// This is the case when the cursor was not found in the theme, nor in the
+    /// themes it inherits from, for example when no cursor theme is installed.
+    pub fn is_fallback(&self) -> bool {
+        self.location.is_none()
+    }
+
+    /// The file this cursor was loaded from, or `None` for a built-in fallback cursor
+    pub fn location(&self) -> Option<&CursorLocation> {
+        self.location.as_ref()
+    }
+}
+
+impl Index<usize> for CursorImages {
+    type Output = CursorImage;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.images[index]
+    }
+}
+
+/// An image of a cursor, as raw pixels.
+#[derive(Clone)]
+pub struct CursorImage {
+    pixels: Vec<u8>,
+    delay: u32,
+    xhot: u32,
+    yhot: u32,
+    width: u32,
+    height: u32,
+}
+
+impl CursorImage {
+    fn new(image: &xparser::Image) -> Self {
+        CursorImage {
+            pixels: image.pixels_rgba.clone(),
+            delay: image.delay,
+            xhot: image.xhot,
+            yhot: image.yhot,
+            width: image.width,
+            height: image.height,
+        }
+    }
+
+    /// Pixels of this image
+    ///
+    /// They are in the `Argb8888` format of `wl_shm`: premultiplied ARGB pixels
+    /// stored as little-endian 32-bit values, row by row, with a stride of `4 * width`.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Dimensions of this image, in buffer pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Location of the pointer hotspot in this image, in buffer pixels
+    pub fn hotspot(&self) -> (u32, u32) {
+        (self.xhot, self.yhot)
+    }
+
+    /// Time (in milliseconds) for which this image should be displayed
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+}
+
+impl std::fmt::Debug for CursorImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CursorImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("xhot", &self.xhot)
+            .field("yhot", &self.yhot)
+            .field("delay", &self.delay)
+            .finish()
+    }
+}
+
+/// Which frame to show, and for how long.
+///
+/// This struct is output by `Cursor::frame_and_duration`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FrameAndDuration {
+    /// The index of the frame which should be shown.
+    pub frame_index: usize,
+    /// The remaining duration that the frame should be shown for (in milliseconds).
+    ///
+    /// This is 0 if the cursor is not animated.
+    pub frame_duration: u32,
+}
+
+pub(crate) fn frame_and_duration<I: Iterator<Item = u32>>(
+    delays: I,
+    total_duration: u32,
+    mut millis: u32,
+) -> FrameAndDuration {
+    // a cursor with a single frame, or without delays, is never animated
+    if total_duration == 0 {
+        return FrameAndDuration { frame_index: 0, frame_duration: 0 };
+    }
+
+    millis %= total_duration;
+
+    for (i, delay) in delays.enumerate() {
+        if millis < delay {
+            return FrameAndDuration { frame_index: i, frame_duration: delay - millis };
+        }
+        millis -= delay;
+    }
+
+    unreachable!("The delays of a cursor add up to its total duration.")
+}
+
+fn nearest_size(size: u32, images: &[xparser::Image]) -> u32 {
+    let size = size as i32;
+    let mut all_sizes = Vec::new();
+
+    for img in images {
+        if !all_sizes.contains(&(img.width as i32)) {
+            all_sizes.push(img.width as i32);
+        }
+    }
+
+    let mut min = 0;
+    for (i, width) in all_sizes.iter().enumerate() {
+        if (width - size).abs() < (all_sizes[min] - size).abs() {
+            min = i;
+        }
+    }
+    all_sizes[min] as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_and_duration, CursorImageTheme, FrameAndDuration};
+
+    fn frame(delays: &[u32], millis: u32) -> (usize, u32) {
+        let total = delays.iter().sum();
+        let FrameAndDuration { frame_index, frame_duration } =
+            frame_and_duration(delays.iter().cloned(), total, millis);
+        (frame_index, frame_duration)
+    }
+
+    #[test]
+    fn animated_frames() {
+        let delays = [50, 100, 20];
+        assert_eq!(frame(&delays, 0), (0, 50));
+        assert_eq!(frame(&delays, 49), (0, 1));
+        assert_eq!(frame(&delays, 50), (1, 100));
+        assert_eq!(frame(&delays, 140), (1, 10));
+        assert_eq!(frame(&delays, 165), (2, 5));
+        // time wraps around the total duration
+        assert_eq!(frame(&delays, 170), (0, 50));
+        assert_eq!(frame(&delays, 170 * 3 + 60), (1, 90));
+    }
+
+    #[test]
+    fn static_frames() {
+        assert_eq!(frame(&[0], 0), (0, 0));
+        assert_eq!(frame(&[0], 1234), (0, 0));
+        assert_eq!(frame(&[0, 0, 0], 1234), (0, 0));
+    }
+
+    #[test]
+    fn zero_delay_frames_are_skipped() {
+        let delays = [0, 30, 0, 10];
+        assert_eq!(frame(&delays, 0), (1, 30));
+        assert_eq!(frame(&delays, 35), (3, 5));
+    }
+
+    #[test]
+    fn missing_theme_uses_fallback() {
+        // no search path, so neither the theme nor the default theme can be found
+        let theme =
+            CursorImageTheme { name: String::from("missing"), size: 24, search_paths: Vec::new() };
+        assert_eq!(theme.locate("pointer"), None);
+
+        let cursor = theme.load_cursor("pointer", 2).unwrap();
+        assert!(cursor.is_fallback());
+        assert_eq!(cursor.location(), None);
+        assert_eq!(cursor.scale(), 2);
+        assert_eq!(cursor.image_count(), 1);
+        assert_eq!(cursor[0].dimensions(), (48, 48));
+        assert_eq!(cursor[0].hotspot(), (15, 0));
+
+        assert!(theme.load_cursor("crosshair", 1).is_none());
+    }
+}
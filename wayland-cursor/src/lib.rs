@@ -11,41 +11,55 @@
 //! which can contain several images if the cursor is animated. It also provides you with the
 //! means of querying which frame of the animation should be displayed at
 //! what time, as well as handles to the buffers containing these frames, to
-//! attach them to a wayland surface.
+//! attach them to a wayland surface. A `CursorAnimator` can do this for you.
+//!
+//! Cursors can be requested by any of their names, such as their CSS name ("pointer"), or
+//! their legacy X11 names ("hand2"), whichever the theme provides. The `CursorIcon` enum
+//! lists the cursors by their CSS names.
+//!
+//! If a cursor can't be found in the theme, nor in the themes it inherits from, a small
+//! built-in set of cursors is used as fallback, so that a pointer can be shown even on
+//! systems with no cursor theme installed. `Cursor::is_fallback` tells whether this happened.
+//!
+//! Themes are looked for in the standard directories, to which more can be added using
+//! a `CursorThemeBuilder`. `Cursor::location` tells which file, and which of the themes
+//! in the inheritance chain, a cursor was loaded from.
+//!
+//! On HiDPI outputs, use `get_cursor_scaled` to load the same cursor at a given integer
+//! scale. Each scale is loaded lazily into the same shared memory pool, and the buffers of
+//! a scaled cursor report their scale, to be given to `wl_surface.set_buffer_scale`.
+//!
+//! Compositors wanting to draw the pointer themselves can use `CursorImageTheme` instead,
+//! which gives access to the raw pixels of the cursor images, without any wayland connection.
+//! Disabling the default `client` cargo feature removes the dependency on `wayland-client`,
+//! leaving only this part of the crate.
 //!
 //! # Example
 //!
 //! ```ignore
-//! use wayland_cursor::CursorTheme;
-//! # use std::thread::sleep;
-//! # use std::time::{Instant, Duration};
-//!
-//! let cursor_theme = CursorTheme::load(32, wl_shm);
-//! let cursor = cursor_theme.get_cursor("wait").expect("Cursor not provided by theme");
+//! use wayland_cursor::{CursorAnimator, CursorIcon, CursorTheme};
 //!
-//! let start_time = Instant::now();
-//! loop {
-//!     // Obtain which frame we should show, and for how long.
-//!     let millis = start_time.elapsed().as_millis();
-//!     let fr_info = cursor.frame_and_duration(millis as u32);
+//! let mut cursor_theme = CursorTheme::load(32, &wl_shm);
+//! let cursor = cursor_theme.get_cursor(CursorIcon::Wait).expect("Cursor not provided by theme");
 //!
-//!     // Here, we obtain the right cursor frame...
-//!     let buffer = cursor[fr_info.frame_index];
-//!     // and attach it to a wl_surface.
-//!     cursor_surface.attach(Some(&buffer), 0, 0);
-//!     cursor_surface.commit();
+//! // The animator attaches the right frame of the cursor to the surface, and
+//! // advances the animation with frame callbacks.
+//! let mut animator = CursorAnimator::new(cursor_surface);
+//! animator.set_cursor(cursor);
 //!
-//!     sleep(fr_info.frame_duration);
-//! }
+//! // On wl_pointer.enter:
+//! let (hx, hy) = animator.hotspot();
+//! wl_pointer.set_cursor(serial, Some(animator.surface()), hx, hy);
 //! ```
 
+#[cfg(feature = "client")]
 use std::{
-    env,
     fs::File,
-    io::{Read, Seek, SeekFrom, Write},
+    io::{Seek, SeekFrom, Write},
     ops::{Deref, Index},
     os::unix::io::{AsRawFd, FromRawFd},
 };
+#[cfg(feature = "client")]
 use wayland_client::{
     protocol::{
         wl_buffer::WlBuffer,
@@ -54,19 +68,33 @@ use wayland_client::{
     },
     Attached, Main,
 };
-use xcursor::parser as xparser;
-use xcursor::CursorTheme as XCursorTheme;
+
+#[cfg(feature = "client")]
+mod animator;
+mod fallback;
+mod icon;
+mod image;
+mod search;
+
+#[cfg(feature = "client")]
+pub use animator::CursorAnimator;
+pub use icon::CursorIcon;
+pub use image::{
+    CursorImage, CursorImageTheme, CursorImages, CursorThemeBuilder, FrameAndDuration,
+};
+pub use search::CursorLocation;
 
 /// Represents a cursor theme loaded from the system.
+#[cfg(feature = "client")]
 pub struct CursorTheme {
-    name: String,
+    theme: CursorImageTheme,
     cursors: Vec<Cursor>,
-    size: u32,
     pool: Main<WlShmPool>,
     pool_size: i32,
     file: File,
 }
 
+#[cfg(feature = "client")]
 impl CursorTheme {
     /// Load a cursor theme from system defaults.
     ///
@@ -80,21 +108,16 @@ impl CursorTheme {
     /// The theme name and cursor size are read from the `XCURSOR_THEME` and
     /// `XCURSOR_SIZE` environment variables, respectively, or from the provided variables
     /// if those are invalid.
-    pub fn load_or(name: &str, mut size: u32, shm: &Attached<WlShm>) -> Self {
-        let name_string = String::from(name);
-        let name = &env::var("XCURSOR_THEME").unwrap_or(name_string);
-
-        if let Ok(var) = env::var("XCURSOR_SIZE") {
-            if let Ok(int) = var.parse() {
-                size = int;
-            }
-        }
-
-        CursorTheme::load_from_name(name, size, shm)
+    pub fn load_or(name: &str, size: u32, shm: &Attached<WlShm>) -> Self {
+        CursorTheme::new(CursorImageTheme::load_or(name, size), shm)
     }
 
     /// Create a new cursor theme, ignoring the system defaults.
     pub fn load_from_name(name: &str, size: u32, shm: &Attached<WlShm>) -> Self {
+        CursorTheme::new(CursorImageTheme::load_from_name(name, size), shm)
+    }
+
+    pub(crate) fn new(theme: CursorImageTheme, shm: &Attached<WlShm>) -> Self {
         // Set some minimal cursor size to hold it. We're not using `size` argument for that,
         // because the actual size that we'll use depends on theme sizes available on a system.
         // The minimal size covers most common minimal theme size, which is 16.
@@ -111,44 +134,49 @@ impl CursorTheme {
 
         let pool = shm.create_pool(file.as_raw_fd(), INITIAL_POOL_SIZE);
 
-        let name = String::from(name);
-
-        CursorTheme { name, file, size, pool, pool_size: INITIAL_POOL_SIZE, cursors: Vec::new() }
+        CursorTheme { theme, file, pool, pool_size: INITIAL_POOL_SIZE, cursors: Vec::new() }
     }
 
     /// Retrieve a cursor from the theme.
     ///
+    /// The cursor can be designated by a `CursorIcon`, or by any of its names. If the theme
+    /// does not provide a cursor with this name, the other names of this cursor are tried.
+    ///
+    /// This method returns `None` if this cursor is not provided
+    /// either by the theme, by one of its parents, or by the built-in fallback cursors.
+    pub fn get_cursor<N: AsRef<str>>(&mut self, name: N) -> Option<&Cursor> {
+        self.get_cursor_scaled(name, 1)
+    }
+
+    /// Retrieve a cursor from the theme, for a surface with the given buffer scale.
+    ///
+    /// The cursor images are loaded for `scale` times the size of the theme, and are kept
+    /// alongside the images of the other scales, so a cursor surface moving to an output with
+    /// a different scale can switch images without reloading the theme. The buffer scale to
+    /// set on the cursor surface is given by `Cursor::scale`.
+    ///
     /// This method returns `None` if this cursor is not provided
-    /// either by the theme, or by one of its parents.
-    pub fn get_cursor(&mut self, name: &str) -> Option<&Cursor> {
-        let cur = self.cursors.iter().position(|i| i.name == name);
+    /// either by the theme, by one of its parents, or by the built-in fallback cursors.
+    ///
+    /// **Panic:** this method panics if `scale` is 0.
+    pub fn get_cursor_scaled<N: AsRef<str>>(&mut self, name: N, scale: u32) -> Option<&Cursor> {
+        let name = name.as_ref();
+        let cur = self.cursors.iter().position(|i| i.name == name && i.scale == scale);
 
         match cur {
             Some(i) => Some(&self.cursors[i]),
             None => {
-                let cur = self.load_cursor(name, self.size)?;
+                let images = self.theme.load_cursor(name, scale)?;
+                let cur = Cursor::new(self, &images);
                 self.cursors.push(cur);
                 self.cursors.iter().last()
             }
         }
     }
 
-    /// This function loads a cursor, parses it and
-    /// pushes the images onto the shm pool.
-    /// Keep in mind that if the cursor is already loaded,
-    /// the function will make a duplicate.
-    fn load_cursor(&mut self, name: &str, size: u32) -> Option<Cursor> {
-        let icon_path = XCursorTheme::load(&self.name).load_icon(name)?;
-        let mut icon_file = File::open(icon_path).ok()?;
-
-        let mut buf = Vec::new();
-        let images = {
-            icon_file.read_to_end(&mut buf).ok()?;
-            xparser::parse_xcursor(&buf)?
-        };
-
-        let cursor = Cursor::new(name, self, &images, size);
-        Some(cursor)
+    /// The theme used to load the cursor images
+    pub fn image_theme(&self) -> &CursorImageTheme {
+        &self.theme
     }
 
     /// Grow the wl_shm_pool this theme is stored on.
@@ -163,76 +191,78 @@ impl CursorTheme {
 }
 
 /// A cursor from a theme. Can contain several images if animated.
+#[cfg(feature = "client")]
 #[derive(Clone)]
 pub struct Cursor {
     name: String,
     images: Vec<CursorImageBuffer>,
     total_duration: u32,
+    scale: u32,
+    location: Option<CursorLocation>,
 }
 
+#[cfg(feature = "client")]
 impl Cursor {
     /// Construct a new Cursor.
     ///
     /// Each of the provided images will be written into `theme`.
     /// This will also grow `theme.pool` if necessary.
-    fn new(name: &str, theme: &mut CursorTheme, images: &[xparser::Image], size: u32) -> Self {
-        let mut buffers = Vec::with_capacity(images.len());
-        let size = Cursor::nearest_size(size, images);
-
-        images
-            .iter()
-            .filter(|el| el.width == size && el.height == size)
-            .for_each(|el| buffers.push(CursorImageBuffer::new(theme, el)));
+    fn new(theme: &mut CursorTheme, images: &CursorImages) -> Self {
+        let scale = images.scale();
+        let buffers: Vec<_> =
+            images.images().iter().map(|el| CursorImageBuffer::new(theme, el, scale)).collect();
 
         let total_duration = buffers.iter().map(|el| el.delay).sum();
 
-        Cursor { total_duration, name: String::from(name), images: buffers }
-    }
-
-    fn nearest_size(size: u32, images: &[xparser::Image]) -> u32 {
-        let size = size as i32;
-        let mut all_sizes = Vec::new();
-
-        for img in images {
-            if !all_sizes.contains(&(img.width as i32)) {
-                all_sizes.push(img.width as i32);
-            }
+        Cursor {
+            total_duration,
+            name: String::from(images.name()),
+            images: buffers,
+            scale,
+            location: images.location().cloned(),
         }
-
-        let mut min = 0;
-        for (i, width) in all_sizes.iter().enumerate() {
-            if (width - size).abs() < (all_sizes[min] - size).abs() {
-                min = i;
-            }
-        }
-        all_sizes[min] as u32
     }
 
     /// Given a time, calculate which frame to show, and how much time remains until the next frame.
     ///
     /// Time will wrap, so if for instance the cursor has an animation during 100ms,
     /// then calling this function with 5ms and 105ms as input gives the same output.
-    pub fn frame_and_duration(&self, mut millis: u32) -> FrameAndDuration {
-        millis %= self.total_duration;
-
-        let mut res = 0;
-        for (i, img) in self.images.iter().enumerate() {
-            if millis < img.delay {
-                res = i;
-                break;
-            }
-            millis -= img.delay;
-        }
-
-        FrameAndDuration { frame_index: res, frame_duration: millis }
+    pub fn frame_and_duration(&self, millis: u32) -> FrameAndDuration {
+        image::frame_and_duration(
+            self.images.iter().map(|img| img.delay),
+            self.total_duration,
+            millis,
+        )
     }
 
     /// Total number of images forming this cursor animation
     pub fn image_count(&self) -> usize {
         self.images.len()
     }
+
+    /// The buffer scale this cursor was loaded for
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Whether this cursor is one of the built-in fallback cursors
+    ///
+    /// This is the case when the cursor was not found in the theme, nor in the
+    /// themes it inherits from, for example when no cursor theme is installed.
+    pub fn is_fallback(&self) -> bool {
+        self.location.is_none()
+    }
+
+    /// The file this cursor was loaded from, or `None` for a built-in fallback cursor
+    ///
+    /// This tells which theme, among this theme and those it inherits from, provided
+    /// the cursor.
+    pub fn location(&self) -> Option<&CursorLocation> {
+        self.location.as_ref()
+    }
 }
 
+#[cfg(feature = "client")]
 impl Index<usize> for Cursor {
     type Output = CursorImageBuffer;
 
@@ -247,6 +277,7 @@ impl Index<usize> for Cursor {
 ///
 /// Note that this proxy will be considered as "unmanaged" by the crate, as such you should
 /// not try to act on it beyond assigning it to `wl_surface`s.
+#[cfg(feature = "client")]
 #[derive(Clone)]
 pub struct CursorImageBuffer {
     buffer: WlBuffer,
@@ -255,56 +286,75 @@ pub struct CursorImageBuffer {
     yhot: u32,
     width: u32,
     height: u32,
+    scale: u32,
 }
 
+#[cfg(feature = "client")]
 impl CursorImageBuffer {
     /// Construct a new CursorImageBuffer
     ///
     /// This function appends the pixels of the image to the provided file,
     /// and constructs a wl_buffer on that data.
-    fn new(theme: &mut CursorTheme, image: &xparser::Image) -> Self {
-        let buf = &image.pixels_rgba;
+    fn new(theme: &mut CursorTheme, image: &CursorImage, scale: u32) -> Self {
+        let buf = image.pixels();
         let offset = theme.file.seek(SeekFrom::End(0)).unwrap();
-        theme.file.write_all(&buf).unwrap();
+        theme.file.write_all(buf).unwrap();
 
         let new_size = theme.file.seek(SeekFrom::End(0)).unwrap();
         theme.grow(new_size as i32);
 
+        let (width, height) = image.dimensions();
+        let (xhot, yhot) = image.hotspot();
         let buffer = theme.pool.create_buffer(
             offset as i32,
-            image.width as i32,
-            image.height as i32,
-            (image.width * 4) as i32,
+            width as i32,
+            height as i32,
+            (width * 4) as i32,
             Format::Argb8888,
         );
         buffer.quick_assign(|_, _, _| {});
 
         CursorImageBuffer {
             buffer: buffer.detach(),
-            delay: image.delay,
-            xhot: image.xhot,
-            yhot: image.yhot,
-            width: image.width,
-            height: image.height,
+            delay: image.delay(),
+            xhot,
+            yhot,
+            width,
+            height,
+            scale,
         }
     }
 
-    /// Dimensions of this image
+    /// Dimensions of this image, in buffer pixels
     pub fn dimensions(&self) -> (u32, u32) {
         (self.width, self.height)
     }
 
-    /// Location of the pointer hotspot in this image
+    /// Location of the pointer hotspot in this image, in buffer pixels
     pub fn hotspot(&self) -> (u32, u32) {
         (self.xhot, self.yhot)
     }
 
+    /// Buffer scale to set on the surface this image is attached to
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Location of the pointer hotspot in surface coordinates
+    ///
+    /// This is the hotspot divided by the scale of this image, as expected
+    /// by `wl_pointer.set_cursor`.
+    pub fn surface_hotspot(&self) -> (u32, u32) {
+        (self.xhot / self.scale, self.yhot / self.scale)
+    }
+
     /// Time (in milliseconds) for which this image should be displayed
     pub fn delay(&self) -> u32 {
         self.delay
     }
 }
 
+#[cfg(feature = "client")]
 impl Deref for CursorImageBuffer {
     type Target = WlBuffer;
 
@@ -313,18 +363,8 @@ impl Deref for CursorImageBuffer {
     }
 }
 
-/// Which frame to show, and for how long.
-///
-/// This struct is output by `Cursor::frame_and_duration`
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct FrameAndDuration {
-    /// The index of the frame which should be shown.
-    pub frame_index: usize,
-    /// The duration that the frame should be shown for (in milliseconds).
-    pub frame_duration: u32,
-}
-
 /// Create a shared file descriptor in memory
+#[cfg(feature = "client")]
 use {
     nix::{
         errno::Errno,
@@ -339,6 +379,7 @@ use {
     },
 };
 
+#[cfg(feature = "client")]
 fn create_shm_fd() -> io::Result<RawFd> {
     // Only try memfd on linux
     #[cfg(target_os = "linux")]
@@ -0,0 +1,229 @@
+//! Lookup of cursor files in the installed themes
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Where the file of a cursor was found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CursorLocation {
+    /// Path of the cursor file
+    pub path: PathBuf,
+    /// Name of the theme providing the cursor: the requested theme, or one it inherits from
+    pub theme: String,
+    /// Name of the cursor file, which can be an alias of the requested name
+    pub name: String,
+}
+
+/// A theme, and the directories it is installed in
+#[derive(Clone, Debug)]
+pub(crate) struct ThemeDirs {
+    pub name: String,
+    pub dirs: Vec<PathBuf>,
+}
+
+/// The directories to look for themes in, according to the environment
+///
+/// This follows the same order as `libXcursor` and `libwayland-cursor`. If `XCURSOR_PATH`
+/// is set, it replaces the default paths.
+pub(crate) fn default_search_paths() -> Vec<PathBuf> {
+    let home = env::var("HOME").ok().filter(|home| !home.is_empty()).map(PathBuf::from);
+    let var = |name| env::var(name).ok().filter(|value: &String| !value.is_empty());
+
+    if let Some(xcursor_path) = var("XCURSOR_PATH") {
+        return split_paths(&xcursor_path, home.as_deref(), None);
+    }
+
+    let mut paths = Vec::new();
+    match var("XDG_DATA_HOME") {
+        Some(data_home) => paths.extend(expand_home(Path::new(&data_home), home.as_deref())),
+        None => paths.extend(home.as_ref().map(|home| home.join(".local/share/icons"))),
+    }
+    paths.extend(home.as_ref().map(|home| home.join(".icons")));
+    match var("XDG_DATA_DIRS") {
+        Some(data_dirs) => paths.extend(split_paths(&data_dirs, home.as_deref(), Some("icons"))),
+        None => {
+            paths.push(PathBuf::from("/usr/local/share/icons"));
+            paths.push(PathBuf::from("/usr/share/icons"));
+        }
+    }
+    paths.push(PathBuf::from("/usr/share/pixmaps"));
+    paths.extend(home.as_ref().map(|home| home.join(".cursors")));
+    paths.push(PathBuf::from("/usr/share/cursors/xorg-x11"));
+    paths
+}
+
+fn split_paths(list: &str, home: Option<&Path>, suffix: Option<&str>) -> Vec<PathBuf> {
+    list.split(':')
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| expand_home(Path::new(entry), home))
+        .map(|path| match suffix {
+            Some(suffix) => path.join(suffix),
+            None => path,
+        })
+        .collect()
+}
+
+/// Replace a leading `~` by the home directory, if there is one
+fn expand_home(path: &Path, home: Option<&Path>) -> Option<PathBuf> {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.map(|home| home.join(rest)),
+        Err(_) => Some(path.to_path_buf()),
+    }
+}
+
+/// The themes to look into for cursors of the theme `name`, in lookup order
+///
+/// This is the theme itself, followed by the themes it inherits from, depth first.
+/// A theme without any `Inherits` key inherits from the "default" theme.
+pub(crate) fn inheritance_chain(name: &str, search_paths: &[PathBuf]) -> Vec<ThemeDirs> {
+    let mut chain = Vec::new();
+    walk_theme(name, search_paths, &mut chain);
+    chain
+}
+
+fn walk_theme(name: &str, search_paths: &[PathBuf], chain: &mut Vec<ThemeDirs>) {
+    if chain.iter().any(|theme| theme.name == name) {
+        return;
+    }
+
+    let dirs: Vec<PathBuf> =
+        search_paths.iter().map(|path| path.join(name)).filter(|dir| dir.is_dir()).collect();
+    let mut inherits = Vec::new();
+    for dir in &dirs {
+        if let Ok(content) = fs::read_to_string(dir.join("index.theme")) {
+            for parent in parse_inherits(&content) {
+                if !inherits.contains(&parent) {
+                    inherits.push(parent);
+                }
+            }
+        }
+    }
+    if inherits.is_empty() && name != "default" {
+        inherits.push(String::from("default"));
+    }
+
+    chain.push(ThemeDirs { name: String::from(name), dirs });
+    for parent in inherits {
+        walk_theme(&parent, search_paths, chain);
+    }
+}
+
+/// The themes listed by the `Inherits` key of an `index.theme` file
+pub(crate) fn parse_inherits(content: &str) -> Vec<String> {
+    let is_separator = |c: char| c.is_whitespace() || c == ';' || c == ',';
+    for line in content.lines() {
+        if !line.starts_with("Inherits") {
+            continue;
+        }
+        let value = line["Inherits".len()..].trim_start();
+        if !value.starts_with('=') {
+            continue;
+        }
+        let value = &value[1..];
+        let themes: Vec<String> =
+            value.split(is_separator).filter(|t| !t.is_empty()).map(String::from).collect();
+        if !themes.is_empty() {
+            return themes;
+        }
+    }
+    Vec::new()
+}
+
+/// The cursor files matching any of `names`, in lookup order
+///
+/// Themes are looked into in the order of the chain, so that a cursor provided under
+/// an alias by a theme is preferred over the one of a theme it inherits from.
+pub(crate) fn locate<'a>(
+    chain: &'a [ThemeDirs],
+    names: &'a [&'a str],
+) -> impl Iterator<Item = CursorLocation> + 'a {
+    chain.iter().flat_map(move |theme| {
+        names.iter().flat_map(move |name| {
+            theme.dirs.iter().filter_map(move |dir| {
+                let path = dir.join("cursors").join(name);
+                if path.is_file() {
+                    Some(CursorLocation {
+                        path,
+                        theme: theme.name.clone(),
+                        name: String::from(*name),
+                    })
+                } else {
+                    None
+                }
+            })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inherits() {
+        assert_eq!(parse_inherits("Inherits=Adwaita"), vec!["Adwaita"]);
+        assert_eq!(
+            parse_inherits("[Icon Theme]\nName=Foo\nInherits = a, b;c\n"),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(parse_inherits(" Inherits=a"), Vec::<String>::new());
+        assert_eq!(parse_inherits("Inherits;=a"), Vec::<String>::new());
+        assert_eq!(parse_inherits("Inherits = ;;\nInherits=b"), vec!["b"]);
+    }
+
+    #[test]
+    fn expand() {
+        let home = Path::new("/home/user");
+        assert_eq!(expand_home(Path::new("~/.icons"), Some(home)), Some(home.join(".icons")));
+        assert_eq!(expand_home(Path::new("~/.icons"), None), None);
+        assert_eq!(expand_home(Path::new("/a/~"), None), Some(PathBuf::from("/a/~")));
+    }
+
+    #[test]
+    fn lookup_through_inheritance() {
+        let root = env::temp_dir().join(format!("wayland-cursor-search-{}", std::process::id()));
+        let system = root.join("system");
+        let bundled = root.join("bundled");
+        let theme = |base: &Path, name: &str, inherits: Option<&str>, cursors: &[&str]| {
+            let dir = base.join(name);
+            fs::create_dir_all(dir.join("cursors")).unwrap();
+            if let Some(inherits) = inherits {
+                fs::write(
+                    dir.join("index.theme"),
+                    format!("[Icon Theme]\nInherits={}\n", inherits),
+                )
+                .unwrap();
+            }
+            for cursor in cursors {
+                fs::write(dir.join("cursors").join(cursor), b"").unwrap();
+            }
+        };
+        theme(&system, "mine", Some("base,cyclic"), &["hand2"]);
+        theme(&system, "base", None, &["pointer", "xterm"]);
+        theme(&bundled, "cyclic", Some("mine"), &["watch"]);
+        theme(&bundled, "default", None, &["left_ptr"]);
+        let paths = vec![system.clone(), bundled.clone()];
+
+        let chain = inheritance_chain("mine", &paths);
+        let names: Vec<_> = chain.iter().map(|theme| theme.name.as_str()).collect();
+        assert_eq!(names, ["mine", "base", "default", "cyclic"]);
+
+        let find = |names: &[&str]| locate(&chain, names).next();
+        // an alias in the theme itself wins over the name in an inherited theme
+        let hand = find(&["pointer", "hand2"]).unwrap();
+        assert_eq!((hand.theme.as_str(), hand.name.as_str()), ("mine", "hand2"));
+        assert_eq!(hand.path, system.join("mine/cursors/hand2"));
+        assert_eq!(find(&["xterm"]).unwrap().theme, "base");
+        assert_eq!(find(&["left_ptr"]).unwrap().path, bundled.join("default/cursors/left_ptr"));
+        assert_eq!(find(&["watch"]).unwrap().theme, "cyclic");
+        assert_eq!(find(&["crosshair"]), None);
+
+        // a missing theme still inherits from the default theme
+        let chain = inheritance_chain("missing", &paths);
+        assert_eq!(locate(&chain, &["left_ptr"]).next().unwrap().theme, "default");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! This module contains bindings to the `libwayland-egl.so` library.
 //!
+//! If the `system_lib` cargo feature (enabled by default) is disabled, the
+//! `wl_egl_window` structure given to the EGL driver is instead allocated and managed
+//! by this crate, and `libwayland-egl.so` is not needed.
+//!
 //! This library is used to interface with the OpenGL stack, and creating
 //! EGL surfaces from a wayland surface.
 //!
@@ -10,19 +14,51 @@
 use std::os::raw::c_void;
 
 use wayland_client::protocol::wl_surface::WlSurface;
-use wayland_sys::{client::wl_proxy, egl::*, ffi_dispatch};
+use wayland_sys::client::wl_proxy;
+#[cfg(feature = "system_lib")]
+use wayland_sys::{egl::*, ffi_dispatch};
+
+#[cfg(not(feature = "system_lib"))]
+use window::wl_egl_window;
+use window::WL_EGL_WINDOW_VERSION;
+
+#[cfg_attr(feature = "system_lib", allow(dead_code))]
+mod window;
+
+// Call a function of libwayland-egl, or of its implementation in `window`
+#[cfg(feature = "system_lib")]
+macro_rules! egl_call {
+    ($name:ident, $($arg:expr),*) => {
+        ffi_dispatch!(WAYLAND_EGL_HANDLE, $name, $($arg),*)
+    };
+}
+
+#[cfg(not(feature = "system_lib"))]
+macro_rules! egl_call {
+    ($name:ident, $($arg:expr),*) => {
+        window::$name($($arg),*)
+    };
+}
 
 /// Checks if the wayland-egl lib is available and can be used
 ///
 /// Trying to create an `WlEglSurface` while this function returns
 /// `false` will result in a panic.
 pub fn is_available() -> bool {
-    is_lib_available()
+    #[cfg(feature = "system_lib")]
+    {
+        is_lib_available()
+    }
+    #[cfg(not(feature = "system_lib"))]
+    {
+        true
+    }
 }
-
 unsafe impl Send for WlEglSurface {}
 unsafe impl Sync for WlEglSurface {}
 
+type ResizeCallback = Box<dyn Fn(i32, i32, i32, i32) + Send + Sync>;
+
 /// EGL surface
 ///
 /// This object is a simple wrapper around a `WlSurface` to add the EGL
@@ -30,12 +66,20 @@ unsafe impl Sync for WlEglSurface {}
 /// to get the window pointer your OpenGL library is needing to initialize the
 /// EGL context (you'll most likely need the display ptr as well, that you can
 /// get via the `ptr` method of the `Proxy` trait on the `WlDisplay` object).
+///
+/// The size of the surface is tracked twice: the size requested with `resize`, and
+/// the size of the last buffer the EGL driver attached to the surface. The driver
+/// latches the requested size when it allocates its next buffer, usually on the next
+/// `eglSwapBuffers`, until then `is_resize_pending` returns `true`.
 pub struct WlEglSurface {
     ptr: *mut wl_egl_window,
+    resize_callback: Option<ResizeCallback>,
 }
 
 impl WlEglSurface {
     /// Create an EGL surface from a wayland surface
+    ///
+    /// **Panics**: if `width` or `height` is not positive, the window can't be created.
     pub fn new(surface: &WlSurface, width: i32, height: i32) -> WlEglSurface {
         unsafe { WlEglSurface::new_from_raw(surface.as_ref().c_ptr(), width, height) }
     }
@@ -45,18 +89,35 @@ impl WlEglSurface {
     /// # Safety
     ///
     /// The provided pointer must be a valid `wl_surface` pointer from `libwayland-client`.
+    ///
+    /// **Panics**: if `width` or `height` is not positive, the window can't be created.
     pub unsafe fn new_from_raw(surface: *mut wl_proxy, width: i32, height: i32) -> WlEglSurface {
-        let ptr = ffi_dispatch!(WAYLAND_EGL_HANDLE, wl_egl_window_create, surface, width, height);
-        WlEglSurface { ptr }
+        let ptr = egl_call!(wl_egl_window_create, surface, width, height);
+        assert!(
+            !ptr.is_null(),
+            "[wayland-egl] Failed to create a wl_egl_window of size {}x{}.",
+            width,
+            height
+        );
+        WlEglSurface { ptr, resize_callback: None }
     }
 
     /// Fetch current size of the EGL surface
+    ///
+    /// This is the size of the last buffer attached by the EGL driver, the same as
+    /// `get_attached_size`.
     pub fn get_size(&self) -> (i32, i32) {
+        self.get_attached_size()
+    }
+
+    /// Fetch the size of the last buffer attached to the surface by the EGL driver
+    ///
+    /// This is `(0, 0)` until the driver attached its first buffer.
+    pub fn get_attached_size(&self) -> (i32, i32) {
         let mut w = 0i32;
         let mut h = 0i32;
         unsafe {
-            ffi_dispatch!(
-                WAYLAND_EGL_HANDLE,
+            egl_call!(
                 wl_egl_window_get_attached_size,
                 self.ptr,
                 &mut w as *mut i32,
@@ -66,18 +127,54 @@ impl WlEglSurface {
         (w, h)
     }
 
+    /// Fetch the size requested by the last call to `resize`, or given at creation
+    pub fn get_requested_size(&self) -> (i32, i32) {
+        let window = self.window();
+        unsafe { ((*window).width, (*window).height) }
+    }
+
+    /// Whether the requested size was not yet latched by the EGL driver
+    ///
+    /// This is `true` from a call to `resize` changing the size until the driver
+    /// attaches a buffer of the new size.
+    pub fn is_resize_pending(&self) -> bool {
+        self.get_requested_size() != self.get_attached_size()
+    }
+
     /// Resize the EGL surface
     ///
     /// The two first arguments `(width, height)` are the new size of
     /// the surface, the two others `(dx, dy)` represent the displacement
     /// of the top-left corner of the surface. It allows you to control the
     /// direction of the resizing if necessary.
+    ///
+    /// Sizes that are not positive are ignored.
     pub fn resize(&self, width: i32, height: i32, dx: i32, dy: i32) {
-        unsafe {
-            ffi_dispatch!(WAYLAND_EGL_HANDLE, wl_egl_window_resize, self.ptr, width, height, dx, dy)
+        unsafe { egl_call!(wl_egl_window_resize, self.ptr, width, height, dx, dy) }
+        if width > 0 && height > 0 {
+            if let Some(ref callback) = self.resize_callback {
+                callback(width, height, dx, dy);
+            }
         }
     }
 
+    /// Register a callback for the resizes of the surface
+    ///
+    /// The callback is invoked by `resize` with its `(width, height, dx, dy)`
+    /// arguments, after the EGL driver was notified. Resizes with a size that is not
+    /// positive are ignored. Registering a callback replaces the previous one.
+    pub fn set_resize_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(i32, i32, i32, i32) + Send + Sync + 'static,
+    {
+        self.resize_callback = Some(Box::new(callback));
+    }
+
+    /// Remove the callback registered with `set_resize_callback`
+    pub fn unset_resize_callback(&mut self) {
+        self.resize_callback = None;
+    }
+
     /// Raw pointer to the EGL surface
     ///
     /// You'll need this pointer to initialize the EGL context in your
@@ -85,12 +182,119 @@ impl WlEglSurface {
     pub fn ptr(&self) -> *const c_void {
         self.ptr as *const c_void
     }
+
+    /// The fields of the window, which libwayland-egl lays out the same way
+    fn window(&self) -> *mut window::wl_egl_window {
+        let window: *mut window::wl_egl_window = self.ptr.cast();
+        // the ABI only grows by appending fields, later versions are compatible
+        assert!(
+            unsafe { (*window).version } >= WL_EGL_WINDOW_VERSION,
+            "[wayland-egl] Unsupported version of the wl_egl_window ABI."
+        );
+        window
+    }
 }
 
 impl Drop for WlEglSurface {
     fn drop(&mut self) {
         unsafe {
-            ffi_dispatch!(WAYLAND_EGL_HANDLE, wl_egl_window_destroy, self.ptr);
+            egl_call!(wl_egl_window_destroy, self.ptr);
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "system_lib")))]
+mod tests {
+    use super::*;
+    use std::ptr;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn resize_latching() {
+        let surface = unsafe { WlEglSurface::new_from_raw(ptr::null_mut(), 100, 50) };
+        assert_eq!(surface.get_requested_size(), (100, 50));
+        assert_eq!(surface.get_attached_size(), (0, 0));
+        assert!(surface.is_resize_pending());
+
+        // the driver attaches a buffer
+        let window = surface.window();
+        unsafe {
+            (*window).attached_width = 100;
+            (*window).attached_height = 50;
+        }
+        assert!(!surface.is_resize_pending());
+
+        surface.resize(200, 50, 0, 0);
+        assert_eq!(surface.get_requested_size(), (200, 50));
+        assert_eq!(surface.get_size(), (100, 50));
+        assert!(surface.is_resize_pending());
+        surface.resize(100, 50, 0, 0);
+        assert!(!surface.is_resize_pending());
+    }
+
+    #[test]
+    fn resize_callback() {
+        let mut surface = unsafe { WlEglSurface::new_from_raw(ptr::null_mut(), 100, 50) };
+        let resizes = Arc::new(Mutex::new(Vec::new()));
+        let sink = resizes.clone();
+        surface.set_resize_callback(move |w, h, dx, dy| sink.lock().unwrap().push((w, h, dx, dy)));
+        surface.resize(200, 100, -10, 0);
+        surface.resize(0, 100, 0, 0);
+        // registering again replaces the previous callback
+        surface.set_resize_callback(|_, _, _, _| {});
+        surface.resize(300, 100, 0, 0);
+        assert_eq!(*resizes.lock().unwrap(), vec![(200, 100, -10, 0)]);
+        surface.unset_resize_callback();
+        surface.resize(400, 100, 0, 0);
+
+        // the slot of the EGL driver is left alone
+        let window = surface.window();
+        unsafe {
+            assert!((*window).resize_callback.is_none());
+            assert!((*window).driver_private.is_null());
+        }
+    }
+
+    unsafe extern "C" fn driver_callback(window: *mut window::wl_egl_window, data: *mut c_void) {
+        *(data as *mut i32) = (*window).width;
+    }
+
+    #[test]
+    fn resize_callback_with_driver() {
+        let mut surface = unsafe { WlEglSurface::new_from_raw(ptr::null_mut(), 100, 50) };
+        // an EGL surface is created from the window, the driver takes the slot
+        let window = surface.window();
+        let mut driver_width = 0i32;
+        unsafe {
+            (*window).driver_private = &mut driver_width as *mut i32 as *mut c_void;
+            (*window).resize_callback = Some(driver_callback);
+        }
+        let resizes = Arc::new(Mutex::new(Vec::new()));
+        let sink = resizes.clone();
+        surface.set_resize_callback(move |w, _, _, _| sink.lock().unwrap().push(w));
+        surface.resize(200, 50, 0, 0);
+        // both the driver and the callback are notified
+        assert_eq!(driver_width, 200);
+        assert_eq!(*resizes.lock().unwrap(), vec![200]);
+        surface.unset_resize_callback();
+        unsafe {
+            assert_eq!((*window).driver_private, &mut driver_width as *mut i32 as *mut c_void);
+            (*window).resize_callback = None;
         }
     }
+
+    #[test]
+    fn newer_abi_version() {
+        let surface = unsafe { WlEglSurface::new_from_raw(ptr::null_mut(), 100, 50) };
+        // the fields of a later version would be appended
+        unsafe { (*surface.window()).version = WL_EGL_WINDOW_VERSION + 1 };
+        assert_eq!(surface.get_requested_size(), (100, 50));
+        assert!(surface.is_resize_pending());
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to create a wl_egl_window")]
+    fn invalid_size() {
+        unsafe { WlEglSurface::new_from_raw(ptr::null_mut(), 0, 50) };
+    }
 }
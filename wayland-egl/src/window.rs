@@ -0,0 +1,204 @@
+//! Implementation of `wl_egl_window` in Rust
+//!
+//! This replaces `libwayland-egl` when the `system_lib` feature is disabled. The structure
+//! is shared with the EGL driver, which reads the requested size from it and writes the
+//! size of the buffers it attached, so it follows the ABI of `wayland-egl-backend.h`, and
+//! the functions behave like those of `libwayland-egl`.
+
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+use wayland_sys::client::wl_proxy;
+
+/// Version of the `wl_egl_window` ABI implemented here
+pub const WL_EGL_WINDOW_VERSION: isize = 3;
+
+/// The window shared with the EGL driver, as defined by `wayland-egl-backend.h`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct wl_egl_window {
+    pub version: isize,
+    pub width: c_int,
+    pub height: c_int,
+    pub dx: c_int,
+    pub dy: c_int,
+    pub attached_width: c_int,
+    pub attached_height: c_int,
+    pub driver_private: *mut c_void,
+    pub resize_callback: Option<unsafe extern "C" fn(*mut wl_egl_window, *mut c_void)>,
+    pub destroy_window_callback: Option<unsafe extern "C" fn(*mut c_void)>,
+    pub surface: *mut wl_proxy,
+}
+
+/// Create a window for a surface, or return null if the size is not positive
+pub unsafe fn wl_egl_window_create(
+    surface: *mut wl_proxy,
+    width: c_int,
+    height: c_int,
+) -> *mut wl_egl_window {
+    if width <= 0 || height <= 0 {
+        return ptr::null_mut();
+    }
+
+    Box::into_raw(Box::new(wl_egl_window {
+        version: WL_EGL_WINDOW_VERSION,
+        width,
+        height,
+        dx: 0,
+        dy: 0,
+        attached_width: 0,
+        attached_height: 0,
+        driver_private: ptr::null_mut(),
+        resize_callback: None,
+        destroy_window_callback: None,
+        surface,
+    }))
+}
+
+/// Notify the driver, and free a window created by `wl_egl_window_create`
+pub unsafe fn wl_egl_window_destroy(egl_window: *mut wl_egl_window) {
+    if egl_window.is_null() {
+        return;
+    }
+    if let Some(callback) = (*egl_window).destroy_window_callback {
+        callback((*egl_window).driver_private);
+    }
+    drop(Box::from_raw(egl_window));
+}
+
+/// Request a new size, notifying the driver. Sizes that are not positive are ignored.
+pub unsafe fn wl_egl_window_resize(
+    egl_window: *mut wl_egl_window,
+    width: c_int,
+    height: c_int,
+    dx: c_int,
+    dy: c_int,
+) {
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    (*egl_window).width = width;
+    (*egl_window).height = height;
+    (*egl_window).dx = dx;
+    (*egl_window).dy = dy;
+    if let Some(callback) = (*egl_window).resize_callback {
+        callback(egl_window, (*egl_window).driver_private);
+    }
+}
+
+/// Read the size of the last buffer attached by the driver
+pub unsafe fn wl_egl_window_get_attached_size(
+    egl_window: *mut wl_egl_window,
+    width: *mut c_int,
+    height: *mut c_int,
+) {
+    if !width.is_null() {
+        *width = (*egl_window).attached_width;
+    }
+    if !height.is_null() {
+        *height = (*egl_window).attached_height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::size_of;
+
+    #[test]
+    fn layout() {
+        // offsets of the fields as laid out by a C compiler
+        let p = size_of::<*mut c_void>();
+        let window = unsafe { wl_egl_window_create(ptr::null_mut(), 1, 1) };
+        let offset = |field: *const u8| field as usize - window as usize;
+        unsafe {
+            assert_eq!(offset(&(*window).version as *const _ as *const u8), 0);
+            assert_eq!(offset(&(*window).width as *const _ as *const u8), p);
+            assert_eq!(offset(&(*window).height as *const _ as *const u8), p + 4);
+            assert_eq!(offset(&(*window).dx as *const _ as *const u8), p + 8);
+            assert_eq!(offset(&(*window).dy as *const _ as *const u8), p + 12);
+            assert_eq!(offset(&(*window).attached_width as *const _ as *const u8), p + 16);
+            assert_eq!(offset(&(*window).attached_height as *const _ as *const u8), p + 20);
+            assert_eq!(offset(&(*window).driver_private as *const _ as *const u8), p + 24);
+            assert_eq!(offset(&(*window).resize_callback as *const _ as *const u8), 2 * p + 24);
+            assert_eq!(
+                offset(&(*window).destroy_window_callback as *const _ as *const u8),
+                3 * p + 24
+            );
+            assert_eq!(offset(&(*window).surface as *const _ as *const u8), 4 * p + 24);
+            wl_egl_window_destroy(window);
+        }
+        assert_eq!(size_of::<wl_egl_window>(), 5 * p + 24);
+        // the callbacks are nullable function pointers
+        assert_eq!(size_of::<Option<unsafe extern "C" fn(*mut c_void)>>(), p);
+    }
+
+    #[test]
+    fn create() {
+        unsafe {
+            assert!(wl_egl_window_create(ptr::null_mut(), 0, 10).is_null());
+            assert!(wl_egl_window_create(ptr::null_mut(), 10, -1).is_null());
+
+            let surface = 0x1234 as *mut wl_proxy;
+            let window = wl_egl_window_create(surface, 640, 480);
+            assert_eq!((*window).version, WL_EGL_WINDOW_VERSION);
+            assert_eq!(((*window).width, (*window).height), (640, 480));
+            assert_eq!(((*window).dx, (*window).dy), (0, 0));
+            assert_eq!(((*window).attached_width, (*window).attached_height), (0, 0));
+            assert_eq!((*window).surface, surface);
+            wl_egl_window_destroy(window);
+        }
+    }
+
+    // a fake driver, counting the calls to its callbacks in its private data
+    #[derive(Default)]
+    struct Driver {
+        resizes: Vec<(c_int, c_int, c_int, c_int)>,
+        destroyed: bool,
+    }
+
+    unsafe extern "C" fn resize_callback(window: *mut wl_egl_window, private: *mut c_void) {
+        let window = &*window;
+        (*(private as *mut Driver)).resizes.push((
+            window.width,
+            window.height,
+            window.dx,
+            window.dy,
+        ));
+    }
+
+    unsafe extern "C" fn destroy_callback(private: *mut c_void) {
+        (*(private as *mut Driver)).destroyed = true;
+    }
+
+    #[test]
+    fn resize_and_destroy() {
+        let mut driver = Driver::default();
+        unsafe {
+            let window = wl_egl_window_create(ptr::null_mut(), 100, 100);
+            (*window).driver_private = &mut driver as *mut Driver as *mut c_void;
+            (*window).resize_callback = Some(resize_callback);
+            (*window).destroy_window_callback = Some(destroy_callback);
+
+            wl_egl_window_resize(window, 200, 150, 5, -5);
+            // invalid sizes are ignored, without notifying the driver
+            wl_egl_window_resize(window, 0, 150, 1, 1);
+            wl_egl_window_resize(window, 200, -1, 1, 1);
+            assert_eq!(((*window).width, (*window).height), (200, 150));
+
+            // the attached size is the one written by the driver
+            let (mut w, mut h) = (-1, -1);
+            wl_egl_window_get_attached_size(window, &mut w, &mut h);
+            assert_eq!((w, h), (0, 0));
+            (*window).attached_width = 200;
+            (*window).attached_height = 150;
+            wl_egl_window_get_attached_size(window, &mut w, ptr::null_mut());
+            wl_egl_window_get_attached_size(window, ptr::null_mut(), &mut h);
+            assert_eq!((w, h), (200, 150));
+
+            wl_egl_window_destroy(window);
+        }
+        assert_eq!(driver.resizes, vec![(200, 150, 5, -5)]);
+        assert!(driver.destroyed);
+    }
+}
@@ -86,7 +86,7 @@
 #![warn(missing_docs)]
 
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::path::Path;
 use std::process::Command;
 
@@ -97,13 +97,16 @@ mod parse;
 mod protocol;
 mod side;
 mod util;
+#[cfg(test)]
+mod xml_writer;
 
+pub use parse::ParseError;
 pub use side::Side;
 
 fn load_xml<P: AsRef<Path>>(prot: P) -> protocol::Protocol {
     let pfile = File::open(prot.as_ref())
         .unwrap_or_else(|_| panic!("Unable to open protocol file `{}`.", prot.as_ref().display()));
-    parse::parse_stream(pfile)
+    parse::parse_stream(pfile).unwrap_or_else(|e| panic!("{}", e))
 }
 
 /// Generate the code for a protocol
@@ -182,7 +185,24 @@ pub fn generate_code_streams_with_destructor_events<P1: Read, P2: Write>(
     side: Side,
     events: &[(&str, &str)],
 ) {
-    let mut protocol = parse::parse_stream(protocol);
+    if let Err(e) = try_generate_code_streams(protocol, target, side, events) {
+        panic!("{}", e);
+    }
+}
+
+/// Generate the code for a protocol from/to IO streams, without panicking
+///
+/// Same as `generate_code_streams_with_destructor_events`, but errors are returned
+/// rather than panicking. If the protocol file is ill-formed, the returned error is
+/// of kind `InvalidData` and wraps a `ParseError` describing the problem.
+pub fn try_generate_code_streams<P1: Read, P2: Write>(
+    protocol: P1,
+    target: &mut P2,
+    side: Side,
+    events: &[(&str, &str)],
+) -> io::Result<()> {
+    let mut protocol =
+        parse::parse_stream(protocol).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
     for interface in &mut protocol.interfaces {
         for event in &mut interface.events {
@@ -197,5 +217,5 @@ pub fn generate_code_streams_with_destructor_events<P1: Read, P2: Write>(
         Side::Server => c_code_gen::generate_protocol_server(protocol),
     };
 
-    write!(target, "{}", output).unwrap();
+    write!(target, "{}", output)
 }
@@ -1,15 +1,51 @@
 use crate::protocol::*;
+use crate::util::snake_to_camel;
+use std::fmt;
 use std::io::Read;
 use xml::attribute::OwnedAttribute;
+use xml::common::Position;
 use xml::reader::ParserConfig;
 use xml::reader::XmlEvent;
 use xml::EventReader;
 
+/// An error encountered while parsing a protocol file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line of the protocol file where the error was found, starting at 1
+    pub line: u64,
+    /// Column of the protocol file where the error was found, starting at 1
+    pub column: u64,
+    /// Description of the error
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ill-formed protocol file at {}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+fn error<R: Read, T>(reader: &EventReader<R>, message: String) -> Result<T> {
+    let position = reader.position();
+    Err(ParseError { line: position.row + 1, column: position.column + 1, message })
+}
+
+fn next<R: Read>(reader: &mut EventReader<R>) -> Result<XmlEvent> {
+    reader.next().map_err(|e| {
+        let position = e.position();
+        ParseError { line: position.row + 1, column: position.column + 1, message: e.msg().into() }
+    })
+}
+
 macro_rules! extract_from(
     ($it: expr => $pattern: pat => $result: expr) => (
-        match $it.next() {
-            Ok($pattern) => { $result },
-            e => panic!("Ill-formed protocol file: {:?}", e)
+        match next($it)? {
+            $pattern => { $result },
+            e => return error($it, format!("unexpected {:?}", e)),
         }
     )
 );
@@ -17,102 +53,162 @@ macro_rules! extract_from(
 macro_rules! extract_end_tag(
     ($it: expr => $tag: expr) => (
         extract_from!($it => XmlEvent::EndElement { name } => {
-            assert!(name.local_name == $tag, "Ill-formed protocol file");
+            if name.local_name != $tag {
+                return error($it, format!("unexpected closing tag `{}`", name.local_name));
+            }
         });
     )
 );
 
-pub fn parse_stream<S: Read>(stream: S) -> Protocol {
+// check that a name can be turned into the identifiers of the generated code
+fn check_name<R: Read>(reader: &EventReader<R>, name: &str, what: &str) -> Result<()> {
+    let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && !snake_to_camel(name).starts_with(|c: char| c.is_ascii_digit())
+        && !snake_to_camel(name).is_empty();
+    if valid {
+        Ok(())
+    } else {
+        error(reader, format!("invalid {} name `{}`", what, name))
+    }
+}
+
+// check the arguments of a message are supported by the generated code
+fn check_args<R: Read>(reader: &EventReader<R>, message: &Message, what: &str) -> Result<()> {
+    let mut new_ids = message.args.iter().filter(|arg| arg.typ == Type::NewId);
+    if let Some(new_id) = new_ids.next() {
+        if new_ids.next().is_some() {
+            return error(
+                reader,
+                format!("{} `{}` cannot have more than one new_id argument", what, message.name),
+            );
+        }
+        if what == "event" && new_id.interface.is_none() {
+            return error(
+                reader,
+                format!("event `{}` cannot have a new_id argument without interface", message.name),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn parse_number<R: Read, T: std::str::FromStr>(
+    reader: &EventReader<R>,
+    value: &str,
+    what: &str,
+) -> Result<T> {
+    match value.parse() {
+        Ok(v) => Ok(v),
+        Err(_) => error(reader, format!("invalid {} `{}`", what, value)),
+    }
+}
+
+pub fn parse_stream<S: Read>(stream: S) -> Result<Protocol> {
     let mut reader =
         EventReader::new_with_config(stream, ParserConfig::new().trim_whitespace(true));
-    reader.next().expect("Could not read from event reader");
+    extract_from!(&mut reader => XmlEvent::StartDocument { .. } => {});
     parse_protocol(reader)
 }
 
-fn parse_protocol<R: Read>(mut reader: EventReader<R>) -> Protocol {
+fn parse_protocol<R: Read>(mut reader: EventReader<R>) -> Result<Protocol> {
+    let reader = &mut reader;
     let mut protocol = extract_from!(
         reader => XmlEvent::StartElement { name, attributes, .. } => {
-            assert!(name.local_name == "protocol", "Missing protocol toplevel tag");
-            assert!(attributes[0].name.local_name == "name", "Protocol must have a name");
-            Protocol::new(attributes[0].value.clone())
+            if name.local_name != "protocol" {
+                return error(reader, "missing protocol toplevel tag".into());
+            }
+            match attributes.into_iter().find(|attr| attr.name.local_name == "name") {
+                Some(attr) => Protocol::new(attr.value),
+                None => return error(reader, "protocol must have a name".into()),
+            }
         }
     );
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+        match next(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => {
                 match &name.local_name[..] {
                     "copyright" => {
                         // parse the copyright
-                        let copyright = match reader.next() {
-                            Ok(XmlEvent::Characters(copyright))
-                            | Ok(XmlEvent::CData(copyright)) => copyright,
-                            e => panic!("Ill-formed protocol file: {:?}", e),
+                        let copyright = match next(reader)? {
+                            XmlEvent::Characters(copyright) | XmlEvent::CData(copyright) => {
+                                copyright
+                            }
+                            e => return error(reader, format!("unexpected {:?}", e)),
                         };
 
                         extract_end_tag!(reader => "copyright");
                         protocol.copyright = Some(copyright);
                     }
                     "interface" => {
-                        protocol.interfaces.push(parse_interface(&mut reader, attributes));
+                        protocol.interfaces.push(parse_interface(reader, attributes)?);
                     }
                     "description" => {
-                        protocol.description = Some(parse_description(&mut reader, attributes));
+                        protocol.description = Some(parse_description(reader, attributes)?);
+                    }
+                    _ => {
+                        return error(
+                            reader,
+                            format!(
+                                "unexpected token `{}` in protocol {}",
+                                name.local_name, protocol.name
+                            ),
+                        )
                     }
-                    _ => panic!(
-                        "Ill-formed protocol file: unexpected token `{}` in protocol {}",
-                        name.local_name, protocol.name
-                    ),
                 }
             }
-            Ok(XmlEvent::EndElement { name }) => {
-                assert!(
-                    name.local_name == "protocol",
-                    "Unexpected closing token `{}`",
-                    name.local_name
-                );
+            XmlEvent::EndElement { name } => {
+                if name.local_name != "protocol" {
+                    return error(reader, format!("unexpected closing tag `{}`", name.local_name));
+                }
                 break;
             }
-            e => panic!("Ill-formed protocol file: {:?}", e),
+            e => return error(reader, format!("unexpected {:?}", e)),
         }
     }
 
-    protocol
+    Ok(protocol)
 }
 
-fn parse_interface<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Interface {
+fn parse_interface<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Interface> {
     let mut interface = Interface::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => interface.name = attr.value,
-            "version" => interface.version = attr.value.parse().unwrap(),
+            "version" => interface.version = parse_number(reader, &attr.value, "version")?,
             _ => {}
         }
     }
+    check_name(reader, &interface.name, "interface")?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
+        match next(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
                 "description" => {
-                    interface.description = Some(parse_description(reader, attributes))
+                    interface.description = Some(parse_description(reader, attributes)?)
                 }
-                "request" => interface.requests.push(parse_request(reader, attributes)),
-                "event" => interface.events.push(parse_event(reader, attributes)),
-                "enum" => interface.enums.push(parse_enum(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+                "request" => interface.requests.push(parse_request(reader, attributes)?),
+                "event" => interface.events.push(parse_event(reader, attributes)?),
+                "enum" => interface.enums.push(parse_enum(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "interface" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "interface" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of file".into()),
             _ => {}
         }
     }
 
-    interface
+    Ok(interface)
 }
 
 fn parse_description<R: Read>(
     reader: &mut EventReader<R>,
     attrs: Vec<OwnedAttribute>,
-) -> (String, String) {
+) -> Result<(String, String)> {
     let mut summary = String::new();
     for attr in attrs {
         if &attr.name.local_name[..] == "summary" {
@@ -120,50 +216,56 @@ fn parse_description<R: Read>(
         }
     }
 
-    let description = match reader.next() {
-        Ok(XmlEvent::Characters(txt)) => {
+    let description = match next(reader)? {
+        XmlEvent::Characters(txt) => {
             extract_end_tag!(reader => "description");
             txt
         }
-        Ok(XmlEvent::EndElement { ref name }) if name.local_name == "description" => String::new(),
-        e => panic!("Ill-formed protocol file: {:?}", e),
+        XmlEvent::EndElement { ref name } if name.local_name == "description" => String::new(),
+        e => return error(reader, format!("unexpected {:?}", e)),
     };
 
-    (summary, description)
+    Ok((summary, description))
 }
 
-fn parse_request<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Message {
+fn parse_request<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Message> {
     let mut request = Message::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => request.name = attr.value,
-            "type" => request.typ = Some(parse_type(&attr.value)),
-            "since" => request.since = attr.value.parse().unwrap(),
+            "type" => request.typ = Some(parse_type(reader, &attr.value)?),
+            "since" => request.since = parse_number(reader, &attr.value, "version")?,
             _ => {}
         }
     }
+    check_name(reader, &request.name, "request")?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => request.description = Some(parse_description(reader, attributes)),
-                "arg" => request.args.push(parse_arg(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => request.description = Some(parse_description(reader, attributes)?),
+                "arg" => request.args.push(parse_arg(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "request" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "request" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of file".into()),
             _ => {}
         }
     }
+    check_args(reader, &request, "request")?;
 
-    request
+    Ok(request)
 }
 
-fn parse_enum<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Enum {
+fn parse_enum<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Result<Enum> {
     let mut enu = Enum::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => enu.name = attr.value,
-            "since" => enu.since = attr.value.parse().unwrap(),
+            "since" => enu.since = parse_number(reader, &attr.value, "version")?,
             "bitfield" => {
                 if &attr.value[..] == "true" {
                     enu.bitfield = true
@@ -172,53 +274,61 @@ fn parse_enum<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>)
             _ => {}
         }
     }
+    check_name(reader, &enu.name, "enum")?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => enu.description = Some(parse_description(reader, attributes)),
-                "entry" => enu.entries.push(parse_entry(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => enu.description = Some(parse_description(reader, attributes)?),
+                "entry" => enu.entries.push(parse_entry(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "enum" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "enum" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of file".into()),
             _ => {}
         }
     }
 
-    enu
+    Ok(enu)
 }
 
-fn parse_event<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Message {
+fn parse_event<R: Read>(
+    reader: &mut EventReader<R>,
+    attrs: Vec<OwnedAttribute>,
+) -> Result<Message> {
     let mut event = Message::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => event.name = attr.value,
-            "since" => event.since = attr.value.parse().unwrap(),
+            "since" => event.since = parse_number(reader, &attr.value, "version")?,
             _ => {}
         }
     }
+    check_name(reader, &event.name, "event")?;
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => event.description = Some(parse_description(reader, attributes)),
-                "arg" => event.args.push(parse_arg(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => event.description = Some(parse_description(reader, attributes)?),
+                "arg" => event.args.push(parse_arg(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "event" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "event" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of file".into()),
             _ => {}
         }
     }
+    check_args(reader, &event, "event")?;
 
-    event
+    Ok(event)
 }
 
-fn parse_arg<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Arg {
+fn parse_arg<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Result<Arg> {
     let mut arg = Arg::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => arg.name = attr.value,
-            "type" => arg.typ = parse_type(&attr.value),
+            "type" => arg.typ = parse_type(reader, &attr.value)?,
             "summary" => {
                 arg.summary = Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
             }
@@ -232,23 +342,42 @@ fn parse_arg<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -
             _ => {}
         }
     }
+    check_name(reader, &arg.name, "argument")?;
+    if arg.typ == Type::Destructor {
+        return error(reader, "an argument cannot have type `destructor`".into());
+    }
+    if let Some(ref interface) = arg.interface {
+        check_name(reader, interface, "interface")?;
+    }
+    if let Some(ref enu) = arg.enum_ {
+        let mut parts = enu.split('.');
+        if let (Some(first), second, None) = (parts.next(), parts.next(), parts.next()) {
+            check_name(reader, first, "enum")?;
+            if let Some(second) = second {
+                check_name(reader, second, "enum")?;
+            }
+        } else {
+            return error(reader, format!("invalid enum name `{}`", enu));
+        }
+    }
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => arg.description = Some(parse_description(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => arg.description = Some(parse_description(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "arg" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "arg" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of file".into()),
             _ => {}
         }
     }
 
-    arg
+    Ok(arg)
 }
 
-fn parse_type(txt: &str) -> Type {
-    match txt {
+fn parse_type<R: Read>(reader: &EventReader<R>, txt: &str) -> Result<Type> {
+    Ok(match txt {
         "int" => Type::Int,
         "uint" => Type::Uint,
         "fixed" => Type::Fixed,
@@ -258,40 +387,141 @@ fn parse_type(txt: &str) -> Type {
         "array" => Type::Array,
         "fd" => Type::Fd,
         "destructor" => Type::Destructor,
-        e => panic!("Unexpected type: {}", e),
-    }
+        e => return error(reader, format!("unexpected type `{}`", e)),
+    })
 }
 
-fn parse_entry<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Entry {
+fn parse_entry<R: Read>(reader: &mut EventReader<R>, attrs: Vec<OwnedAttribute>) -> Result<Entry> {
     let mut entry = Entry::new();
     for attr in attrs {
         match &attr.name.local_name[..] {
             "name" => entry.name = attr.value,
             "value" => {
                 entry.value = if attr.value.starts_with("0x") {
-                    u32::from_str_radix(&attr.value[2..], 16).unwrap()
+                    match u32::from_str_radix(&attr.value[2..], 16) {
+                        Ok(value) => value,
+                        Err(_) => return error(reader, format!("invalid value `{}`", attr.value)),
+                    }
                 } else {
-                    attr.value.parse().unwrap()
+                    parse_number(reader, &attr.value, "value")?
                 };
             }
-            "since" => entry.since = attr.value.parse().unwrap(),
+            "since" => entry.since = parse_number(reader, &attr.value, "version")?,
             "summary" => {
                 entry.summary = Some(attr.value.split_whitespace().collect::<Vec<_>>().join(" "))
             }
             _ => {}
         }
     }
+    // entries starting with a digit are prefixed by the generated code
+    if !entry.name.starts_with(|c: char| c.is_ascii_digit()) {
+        check_name(reader, &entry.name, "entry")?;
+    } else if !entry.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return error(reader, format!("invalid entry name `{}`", entry.name));
+    }
 
     loop {
-        match reader.next() {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => match &name.local_name[..] {
-                "description" => entry.description = Some(parse_description(reader, attributes)),
-                _ => panic!("Unexpected tocken: `{}`", name.local_name),
+        match next(reader)? {
+            XmlEvent::StartElement { name, attributes, .. } => match &name.local_name[..] {
+                "description" => entry.description = Some(parse_description(reader, attributes)?),
+                _ => return error(reader, format!("unexpected token `{}`", name.local_name)),
             },
-            Ok(XmlEvent::EndElement { ref name }) if name.local_name == "entry" => break,
+            XmlEvent::EndElement { ref name } if name.local_name == "entry" => break,
+            XmlEvent::EndDocument => return error(reader, "unexpected end of file".into()),
             _ => {}
         }
     }
 
-    entry
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_stream;
+    use crate::{try_generate_code_streams, Side};
+
+    fn parse_error(xml: &str) -> String {
+        parse_stream(xml.as_bytes()).unwrap_err().message
+    }
+
+    #[test]
+    fn ill_formed_protocols() {
+        assert_eq!(parse_error(r#"<protocol/>"#), "protocol must have a name");
+        assert_eq!(parse_error(r#"<interface name="a"/>"#), "missing protocol toplevel tag");
+        assert_eq!(
+            parse_error(r#"<protocol name="p"><interface name="a" version="x"/></protocol>"#),
+            "invalid version `x`"
+        );
+        assert_eq!(
+            parse_error(r#"<protocol name="p"><interface name="1a"/></protocol>"#),
+            "invalid interface name `1a`"
+        );
+        assert_eq!(
+            parse_error(
+                r#"<protocol name="p"><interface name="a"><event name="e">
+                <arg name="x" type="destructor"/></event></interface></protocol>"#
+            ),
+            "an argument cannot have type `destructor`"
+        );
+        assert_eq!(
+            parse_error(
+                r#"<protocol name="p"><interface name="a"><request name="r">
+                <arg name="x" type="uint" enum="a.b.c"/></request></interface></protocol>"#
+            ),
+            "invalid enum name `a.b.c`"
+        );
+        assert!(parse_stream(r#"<protocol name="p"><interface name="a">"#.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn unsupported_new_ids() {
+        assert_eq!(
+            parse_error(
+                r#"<protocol name="p"><interface name="a"><request name="r">
+                <arg name="x" type="new_id" interface="b"/>
+                <arg name="y" type="new_id" interface="c"/></request></interface></protocol>"#
+            ),
+            "request `r` cannot have more than one new_id argument"
+        );
+        assert_eq!(
+            parse_error(
+                r#"<protocol name="p"><interface name="a"><event name="e">
+                <arg name="x" type="new_id" interface="b"/>
+                <arg name="y" type="new_id" interface="c"/></event></interface></protocol>"#
+            ),
+            "event `e` cannot have more than one new_id argument"
+        );
+        assert_eq!(
+            parse_error(
+                r#"<protocol name="p"><interface name="a"><event name="e">
+                <arg name="x" type="new_id"/></event></interface></protocol>"#
+            ),
+            "event `e` cannot have a new_id argument without interface"
+        );
+        // code generation used to panic on those
+        let xml = r#"<protocol name="p"><interface name="a"><event name="e">
+            <arg name="x" type="new_id"/></event></interface></protocol>"#;
+        for &side in &[Side::Client, Side::Server] {
+            let error =
+                try_generate_code_streams(xml.as_bytes(), &mut Vec::new(), side, &[]).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+        // like wl_registry.bind, a request can create an object of any interface
+        assert!(parse_stream(
+            r#"<protocol name="p"><interface name="a"><request name="r">
+            <arg name="x" type="new_id"/></request></interface></protocol>"#
+                .as_bytes()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn error_position() {
+        let error =
+            parse_stream("<protocol name=\"p\">\n  <foo/>\n</protocol>".as_bytes()).unwrap_err();
+        assert_eq!(
+            (error.line, error.message.as_str()),
+            (2, "unexpected token `foo` in protocol p")
+        );
+    }
 }
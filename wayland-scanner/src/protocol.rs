@@ -1,7 +1,7 @@
 use proc_macro2::TokenStream;
 use quote::quote;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Protocol {
     pub name: String,
     pub copyright: Option<String>,
@@ -15,7 +15,7 @@ impl Protocol {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Interface {
     pub name: String,
     pub version: u32,
@@ -38,7 +38,7 @@ impl Interface {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Message {
     pub name: String,
     pub typ: Option<Type>,
@@ -67,7 +67,7 @@ impl Message {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Arg {
     pub name: String,
     pub typ: Type,
@@ -92,7 +92,7 @@ impl Arg {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Enum {
     pub name: String,
     pub since: u16,
@@ -113,7 +113,7 @@ impl Enum {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Entry {
     pub name: String,
     pub value: u32,
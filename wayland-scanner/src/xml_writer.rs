@@ -0,0 +1,400 @@
+//! Serialization of a protocol back to XML, the inverse of `parse::parse_stream`
+
+use crate::protocol::*;
+use std::io::Write;
+use xml::writer::{EmitterConfig, EventWriter, Result, XmlEvent};
+
+pub fn write_protocol<W: Write>(protocol: &Protocol, sink: W) -> Result<()> {
+    let mut writer = EmitterConfig::new().perform_indent(true).create_writer(sink);
+    writer.write(XmlEvent::start_element("protocol").attr("name", &protocol.name))?;
+    if let Some(ref copyright) = protocol.copyright {
+        writer.write(XmlEvent::start_element("copyright"))?;
+        // text is trimmed by the parser, CDATA is not
+        if !copyright.is_empty() && copyright.trim() == copyright {
+            writer.write(XmlEvent::characters(copyright))?;
+        } else {
+            writer.write(XmlEvent::cdata(copyright))?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    write_description(&mut writer, &protocol.description)?;
+    for interface in &protocol.interfaces {
+        write_interface(&mut writer, interface)?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+fn write_interface<W: Write>(writer: &mut EventWriter<W>, interface: &Interface) -> Result<()> {
+    let version = interface.version.to_string();
+    writer.write(
+        XmlEvent::start_element("interface")
+            .attr("name", &interface.name)
+            .attr("version", &version),
+    )?;
+    write_description(writer, &interface.description)?;
+    for request in &interface.requests {
+        write_message(writer, "request", request)?;
+    }
+    for event in &interface.events {
+        write_message(writer, "event", event)?;
+    }
+    for enu in &interface.enums {
+        write_enum(writer, enu)?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+fn write_description<W: Write>(
+    writer: &mut EventWriter<W>,
+    description: &Option<(String, String)>,
+) -> Result<()> {
+    if let Some((ref summary, ref text)) = *description {
+        writer.write(XmlEvent::start_element("description").attr("summary", summary))?;
+        if !text.is_empty() {
+            writer.write(XmlEvent::characters(text))?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    Ok(())
+}
+
+fn write_message<W: Write>(writer: &mut EventWriter<W>, tag: &str, msg: &Message) -> Result<()> {
+    let since = msg.since.to_string();
+    let mut start = XmlEvent::start_element(tag).attr("name", &msg.name).attr("since", &since);
+    if let Some(typ) = msg.typ {
+        start = start.attr("type", type_name(typ));
+    }
+    writer.write(start)?;
+    write_description(writer, &msg.description)?;
+    for arg in &msg.args {
+        write_arg(writer, arg)?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+fn write_arg<W: Write>(writer: &mut EventWriter<W>, arg: &Arg) -> Result<()> {
+    let mut start =
+        XmlEvent::start_element("arg").attr("name", &arg.name).attr("type", type_name(arg.typ));
+    if let Some(ref summary) = arg.summary {
+        start = start.attr("summary", summary);
+    }
+    if let Some(ref interface) = arg.interface {
+        start = start.attr("interface", interface);
+    }
+    if arg.allow_null {
+        start = start.attr("allow-null", "true");
+    }
+    if let Some(ref enu) = arg.enum_ {
+        start = start.attr("enum", enu);
+    }
+    writer.write(start)?;
+    write_description(writer, &arg.description)?;
+    writer.write(XmlEvent::end_element())
+}
+
+fn write_enum<W: Write>(writer: &mut EventWriter<W>, enu: &Enum) -> Result<()> {
+    let since = enu.since.to_string();
+    let mut start = XmlEvent::start_element("enum").attr("name", &enu.name).attr("since", &since);
+    if enu.bitfield {
+        start = start.attr("bitfield", "true");
+    }
+    writer.write(start)?;
+    write_description(writer, &enu.description)?;
+    for entry in &enu.entries {
+        let value = entry.value.to_string();
+        let since = entry.since.to_string();
+        let mut start = XmlEvent::start_element("entry")
+            .attr("name", &entry.name)
+            .attr("value", &value)
+            .attr("since", &since);
+        if let Some(ref summary) = entry.summary {
+            start = start.attr("summary", summary);
+        }
+        writer.write(start)?;
+        write_description(writer, &entry.description)?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())
+}
+
+fn type_name(typ: Type) -> &'static str {
+    match typ {
+        Type::Int => "int",
+        Type::Uint => "uint",
+        Type::Fixed => "fixed",
+        Type::String => "string",
+        Type::Object => "object",
+        Type::NewId => "new_id",
+        Type::Array => "array",
+        Type::Fd => "fd",
+        Type::Destructor => "destructor",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_protocol;
+    use crate::parse::parse_stream;
+    use crate::protocol::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn assert_round_trip(xml: &str) {
+        let protocol = parse_stream(xml.as_bytes()).unwrap();
+        let mut written = Vec::new();
+        write_protocol(&protocol, &mut written).unwrap();
+        let reparsed = parse_stream(&written[..]).unwrap_or_else(|e| {
+            panic!("{}\n{}", e, String::from_utf8_lossy(&written));
+        });
+        assert_eq!(protocol, reparsed);
+    }
+
+    fn visit_protocols(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                visit_protocols(&path, files);
+            } else if path.extension().map(|ext| ext == "xml").unwrap_or(false) {
+                files.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_protocol_files() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let mut files = vec![root.join("wayland-client/wayland.xml")];
+        let protocols = root.join("wayland-protocols");
+        // the protocol files are git submodules, which may not be checked out
+        for dir in &["protocols", "wlr-protocols", "misc"] {
+            if protocols.join(dir).is_dir() {
+                visit_protocols(&protocols.join(dir), &mut files);
+            }
+        }
+        for file in files {
+            assert_round_trip(&fs::read_to_string(&file).unwrap());
+        }
+    }
+
+    #[test]
+    fn round_trip_edge_cases() {
+        assert_round_trip(r#"<protocol name="empty"></protocol>"#);
+        assert_round_trip(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <protocol name="edge_cases">
+              <copyright><![CDATA[ <&> copyright ]]></copyright>
+              <description summary="  spaced
+                 out   summary "/>
+              <interface name="iface" version="42">
+                <request name="destroy" type="destructor" since="3"/>
+                <request name="make">
+                  <description summary="&quot;quoted&quot; &amp; &lt;escaped&gt;">
+                    Text with &lt;markup&gt;, "quotes" &amp; unicode: ∀ é
+                  </description>
+                  <arg name="id" type="new_id" interface="iface" summary="the new object"/>
+                  <arg name="maybe" type="object" interface="iface" allow-null="true"/>
+                  <arg name="fd" type="fd"/>
+                  <arg name="bits" type="uint" enum="other.flags"/>
+                  <arg name="kind" type="int" enum="kind"><description summary=""/></arg>
+                </request>
+                <request name="bind">
+                  <arg name="any" type="new_id"/>
+                </request>
+                <event name="done" since="2">
+                  <arg name="data" type="array"/>
+                  <arg name="text" type="string" allow-null="false"/>
+                  <arg name="value" type="fixed"/>
+                </event>
+                <enum name="kind" since="2">
+                  <entry name="0" value="0x0"/>
+                  <entry name="a_b" value="4294967295" summary="max" since="5">
+                    <description summary="entry">described</description>
+                  </entry>
+                </enum>
+                <enum name="flags" bitfield="true"/>
+              </interface>
+              <interface name="other" version="1"/>
+            </protocol>"#,
+        );
+    }
+
+    // A small deterministic generator of random protocols, limited to what the parser
+    // accepts and in the form it normalizes to
+    struct Gen(u64);
+
+    impl Gen {
+        fn next(&mut self) -> u64 {
+            // xorshift64*
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn chance(&mut self) -> bool {
+            self.below(2) == 0
+        }
+
+        fn pick(&mut self, chars: &str) -> char {
+            let chars: Vec<char> = chars.chars().collect();
+            chars[self.below(chars.len())]
+        }
+
+        fn vec<T>(&mut self, max: usize, mut f: impl FnMut(&mut Gen) -> T) -> Vec<T> {
+            (0..self.below(max + 1)).map(|_| f(self)).collect()
+        }
+
+        fn maybe<T>(&mut self, f: impl FnOnce(&mut Gen) -> T) -> Option<T> {
+            if self.chance() {
+                Some(f(self))
+            } else {
+                None
+            }
+        }
+
+        fn name(&mut self) -> String {
+            let mut name = self.pick("abcdefghijklmnopqrstuvwxyz").to_string();
+            for _ in 0..self.below(10) {
+                name.push(self.pick("abcdefghijklmnopqrstuvwxyz0123456789_"));
+            }
+            name
+        }
+
+        fn word(&mut self) -> String {
+            (0..1 + self.below(8)).map(|_| self.pick("azAZ09.,;:!?-_()[/\\'\"<>&é∀")).collect()
+        }
+
+        // whitespace-normalized text, as found in attributes
+        fn summary(&mut self) -> String {
+            self.vec(5, Gen::word).join(" ")
+        }
+
+        // trimmed text, which may span several lines
+        fn text(&mut self) -> String {
+            let mut text = self.word();
+            for _ in 0..self.below(10) {
+                text.push_str(if self.below(4) == 0 { "\n  " } else { " " });
+                text.push_str(&self.word());
+            }
+            text
+        }
+
+        fn description(&mut self) -> Option<(String, String)> {
+            self.maybe(|g| {
+                let text = if g.chance() { g.text() } else { String::new() };
+                (g.summary(), text)
+            })
+        }
+
+        fn arg(&mut self, new_id: bool, event: bool) -> Arg {
+            let types = [
+                Type::Int,
+                Type::Uint,
+                Type::Fixed,
+                Type::String,
+                Type::Object,
+                Type::Array,
+                Type::Fd,
+            ];
+            let typ = if new_id { Type::NewId } else { types[self.below(types.len())] };
+            let interface = if new_id && (event || self.chance()) {
+                Some(self.name())
+            } else if typ == Type::Object {
+                self.maybe(Gen::name)
+            } else {
+                None
+            };
+            Arg {
+                name: self.name(),
+                typ,
+                interface,
+                summary: self.maybe(Gen::summary),
+                description: self.description(),
+                allow_null: self.chance(),
+                enum_: self.maybe(|g| {
+                    if g.chance() {
+                        g.name()
+                    } else {
+                        format!("{}.{}", g.name(), g.name())
+                    }
+                }),
+            }
+        }
+
+        fn message(&mut self, event: bool) -> Message {
+            let mut args = self.vec(4, |g| g.arg(false, event));
+            // at most one new_id argument
+            if self.chance() {
+                let position = self.below(args.len() + 1);
+                args.insert(position, self.arg(true, event));
+            }
+            Message {
+                name: self.name(),
+                // destructor events are only known by the code generation
+                typ: if !event && self.below(4) == 0 { Some(Type::Destructor) } else { None },
+                since: self.next() as u32,
+                description: self.description(),
+                args,
+                type_index: 0,
+            }
+        }
+
+        fn entry(&mut self) -> Entry {
+            Entry {
+                // entries may start with a digit
+                name: if self.chance() { self.name() } else { self.below(1000).to_string() },
+                value: self.next() as u32,
+                since: self.next() as u16,
+                description: self.description(),
+                summary: self.maybe(Gen::summary),
+            }
+        }
+
+        fn protocol(&mut self) -> Protocol {
+            Protocol {
+                name: self.name(),
+                copyright: self.maybe(|g| {
+                    // surrounding whitespace must be preserved as well
+                    match g.below(3) {
+                        0 => g.text(),
+                        1 => format!("\n  {}\n", g.text()),
+                        _ => format!(" {}", g.text()),
+                    }
+                }),
+                description: self.description(),
+                interfaces: self.vec(4, |g| Interface {
+                    name: g.name(),
+                    version: g.next() as u32,
+                    description: g.description(),
+                    requests: g.vec(4, |g| g.message(false)),
+                    events: g.vec(4, |g| g.message(true)),
+                    enums: g.vec(3, |g| Enum {
+                        name: g.name(),
+                        since: g.next() as u16,
+                        description: g.description(),
+                        entries: g.vec(4, Gen::entry),
+                        bitfield: g.chance(),
+                    }),
+                }),
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_random_protocols() {
+        let mut gen = Gen(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..500 {
+            let protocol = gen.protocol();
+            let mut written = Vec::new();
+            write_protocol(&protocol, &mut written).unwrap();
+            let reparsed = parse_stream(&written[..]).unwrap_or_else(|e| {
+                panic!("{}\n{}", e, String::from_utf8_lossy(&written));
+            });
+            assert_eq!(protocol, reparsed, "\n{}", String::from_utf8_lossy(&written));
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! A headless reference compositor
+//!
+//! This compositor does not display anything: it implements `wl_compositor`, `wl_shm`,
+//! `xdg_wm_base`, `wl_seat` and `wl_output`, reads the contents of the shm buffers the
+//! clients commit, and immediately releases them and fires their frame callbacks.
+//!
+//! It is meant to be used to run clients in environments without a graphical session,
+//! for example for end-to-end tests, and as an example of how to implement these globals.
+//!
+//! Run it with an optional socket name as argument, and point your clients to it with
+//! the `WAYLAND_DISPLAY` environment variable.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use wayland_protocols::xdg_shell::server::{
+    xdg_popup, xdg_positioner, xdg_surface, xdg_toplevel, xdg_wm_base,
+};
+use wayland_server::protocol::{
+    wl_buffer, wl_callback, wl_compositor, wl_keyboard, wl_output, wl_pointer, wl_region, wl_seat,
+    wl_surface, wl_touch,
+};
+use wayland_server::{Display, Filter, Main, SerialCounter};
+
+// State shared by all the globals
+struct State {
+    start: Instant,
+    serials: SerialCounter,
+}
+
+impl State {
+    fn time(&self) -> u32 {
+        self.start.elapsed().as_millis() as u32
+    }
+}
+
+// The double-buffered state of a wl_surface
+#[derive(Default)]
+struct SurfaceData {
+    // Some(None) means that a null buffer was attached
+    pending_buffer: Option<Option<wl_buffer::WlBuffer>>,
+    frame_callbacks: Vec<Main<wl_callback::WlCallback>>,
+}
+
+fn main() {
+    let mut display = Display::new();
+    let socket_name = match std::env::args_os().nth(1) {
+        Some(name) => {
+            display.add_socket(Some(&name)).expect("Failed to create the server socket.");
+            name
+        }
+        None => display.add_socket_auto().expect("Failed to create the server socket."),
+    };
+    println!("Listening on {:?}", socket_name);
+
+    let state = Rc::new(State { start: Instant::now(), serials: SerialCounter::new() });
+
+    wayland_server::init_shm_global(&mut display, Vec::new());
+    init_compositor(&mut display, state.clone());
+    init_xdg_shell(&mut display, state.clone());
+    init_seat(&mut display);
+    init_output(&mut display);
+
+    loop {
+        display.dispatch(Duration::from_millis(16), &mut ()).unwrap();
+        display.flush_clients(&mut ());
+    }
+}
+
+/*
+ * wl_compositor
+ */
+
+fn init_compositor(display: &mut Display, state: Rc<State>) {
+    display.create_global::<wl_compositor::WlCompositor, _>(
+        4,
+        Filter::new(move |(compositor, _): (Main<wl_compositor::WlCompositor>, u32), _, _| {
+            let state = state.clone();
+            compositor.quick_assign(move |_, request, _| match request {
+                wl_compositor::Request::CreateSurface { id } => {
+                    id.as_ref().user_data().set(|| RefCell::new(SurfaceData::default()));
+                    let state = state.clone();
+                    id.quick_assign(move |surface, request, _| {
+                        surface_request(&surface, request, &state)
+                    });
+                }
+                wl_compositor::Request::CreateRegion { id } => {
+                    // regions only matter for input and rendering, which we don't do
+                    id.quick_assign(|_, _: wl_region::Request, _| {});
+                }
+                _ => {}
+            });
+        }),
+    );
+}
+
+fn surface_request(surface: &wl_surface::WlSurface, request: wl_surface::Request, state: &State) {
+    let data = surface.as_ref().user_data().get::<RefCell<SurfaceData>>().unwrap();
+    match request {
+        wl_surface::Request::Attach { buffer, .. } => {
+            data.borrow_mut().pending_buffer = Some(buffer);
+        }
+        wl_surface::Request::Frame { callback } => {
+            callback.quick_assign(|_, _, _| {});
+            data.borrow_mut().frame_callbacks.push(callback);
+        }
+        wl_surface::Request::Commit => {
+            let (buffer, callbacks) = {
+                let mut data = data.borrow_mut();
+                (data.pending_buffer.take(), std::mem::take(&mut data.frame_callbacks))
+            };
+            if let Some(Some(buffer)) = buffer {
+                // this is where a real compositor would upload the buffer contents
+                let ret = wayland_server::with_buffer_contents(&buffer, |contents, data| {
+                    let checksum = contents.iter().fold(0u32, |acc, &b| acc.wrapping_add(b as u32));
+                    println!(
+                        "{:?}: committed a {}x{} {:?} buffer (checksum {:08x})",
+                        surface, data.width, data.height, data.format, checksum
+                    );
+                });
+                if let Err(err) = ret {
+                    println!("{:?}: could not access its buffer: {}", surface, err);
+                }
+                buffer.release();
+            }
+            // we don't render anything, so the client can draw its next frame right away
+            let time = state.time();
+            for callback in callbacks {
+                callback.done(time);
+            }
+        }
+        _ => {}
+    }
+}
+
+/*
+ * xdg_wm_base
+ */
+
+fn init_xdg_shell(display: &mut Display, state: Rc<State>) {
+    display.create_global::<xdg_wm_base::XdgWmBase, _>(
+        2,
+        Filter::new(move |(wm_base, _): (Main<xdg_wm_base::XdgWmBase>, u32), _, _| {
+            let state = state.clone();
+            wm_base.quick_assign(move |_, request, _| match request {
+                xdg_wm_base::Request::CreatePositioner { id } => {
+                    // we don't place popups, so the positioner is ignored
+                    id.quick_assign(|_, _: xdg_positioner::Request, _| {});
+                }
+                xdg_wm_base::Request::GetXdgSurface { id, .. } => {
+                    let state = state.clone();
+                    id.quick_assign(move |xdg_surface, request, _| {
+                        xdg_surface_request(&xdg_surface, request, &state)
+                    });
+                }
+                _ => {}
+            });
+        }),
+    );
+}
+
+fn xdg_surface_request(
+    xdg_surface: &xdg_surface::XdgSurface,
+    request: xdg_surface::Request,
+    state: &State,
+) {
+    match request {
+        xdg_surface::Request::GetToplevel { id } => {
+            id.quick_assign(|_, _: xdg_toplevel::Request, _| {});
+            // let the client choose its size
+            id.configure(0, 0, Vec::new());
+            xdg_surface.configure(state.serials.next_serial());
+        }
+        xdg_surface::Request::GetPopup { id, .. } => {
+            id.quick_assign(|_, _: xdg_popup::Request, _| {});
+            id.configure(0, 0, 1, 1);
+            xdg_surface.configure(state.serials.next_serial());
+        }
+        _ => {}
+    }
+}
+
+/*
+ * wl_seat
+ */
+
+fn init_seat(display: &mut Display) {
+    display.create_global::<wl_seat::WlSeat, _>(
+        5,
+        Filter::new(move |(seat, version): (Main<wl_seat::WlSeat>, u32), _, _| {
+            seat.capabilities(wl_seat::Capability::Pointer | wl_seat::Capability::Keyboard);
+            if version >= 2 {
+                seat.name("seat0".into());
+            }
+            seat.quick_assign(|_, request, _| match request {
+                wl_seat::Request::GetPointer { id } => {
+                    id.quick_assign(|_, _: wl_pointer::Request, _| {});
+                }
+                wl_seat::Request::GetKeyboard { id } => {
+                    id.quick_assign(|_, _: wl_keyboard::Request, _| {});
+                    // there is no keyboard to describe
+                    if let Ok(null) = File::open("/dev/null") {
+                        id.keymap(wl_keyboard::KeymapFormat::NoKeymap, null.as_raw_fd(), 0);
+                    }
+                    if id.as_ref().version() >= 4 {
+                        id.repeat_info(25, 600);
+                    }
+                }
+                wl_seat::Request::GetTouch { id } => {
+                    id.quick_assign(|_, _: wl_touch::Request, _| {});
+                }
+                _ => {}
+            });
+        }),
+    );
+}
+
+/*
+ * wl_output
+ */
+
+fn init_output(display: &mut Display) {
+    display.create_global::<wl_output::WlOutput, _>(
+        3,
+        Filter::new(move |(output, version): (Main<wl_output::WlOutput>, u32), _, _| {
+            output.quick_assign(|_, _, _| {});
+            output.geometry(
+                0,
+                0,
+                520,
+                290,
+                wl_output::Subpixel::Unknown,
+                "wayland-rs".into(),
+                "headless".into(),
+                wl_output::Transform::Normal,
+            );
+            output.mode(wl_output::Mode::Current | wl_output::Mode::Preferred, 1920, 1080, 60_000);
+            if version >= 2 {
+                output.scale(1);
+                output.done();
+            }
+        }),
+    );
+}
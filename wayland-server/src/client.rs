@@ -58,6 +58,63 @@ impl Client {
         self.inner.kill()
     }
 
+    /// Adds a listener, notified of the resources created for this client
+    ///
+    /// The resources are given as anonymous handles, as they are not yet initialized
+    /// when the listener is notified. Their user data is not accessible through them.
+    ///
+    /// The listener is given the dispatch data of `Display::dispatch` for the resources
+    /// created by the requests of the client. For those created with `create_resource`,
+    /// usually from a callback already using the dispatch data, it is given an empty
+    /// one (`()`) instead.
+    ///
+    /// With the `use_system_lib` backend, the listener is never notified if the system
+    /// `libwayland-server` is too old to provide `wl_client_add_resource_created_listener`.
+    pub fn add_resource_created_listener(
+        &self,
+        listener: crate::Filter<Resource<crate::AnonymousObject>>,
+    ) {
+        self.inner
+            .add_resource_created_listener(move |resource, data| listener.send(resource, data))
+    }
+
+    /// Calls `f` for each resource of this client
+    ///
+    /// The resources are given as anonymous handles, their user data is not accessible
+    /// through them. The callback can create and destroy resources, those destroyed are
+    /// not given to it afterwards.
+    ///
+    /// With the `use_system_lib` backend, this does nothing if the system
+    /// `libwayland-server` is too old to provide `wl_client_for_each_resource`.
+    pub fn for_each_resource<F>(&self, f: F)
+    where
+        F: FnMut(Resource<crate::AnonymousObject>),
+    {
+        self.inner.for_each_resource(f)
+    }
+
+    /// Retrieves statistics about the traffic and the objects of this client
+    ///
+    /// Returns `None` if the client is no longer connected, and with the
+    /// `use_system_lib` backend, as the system library does not expose them.
+    pub fn stats(&self) -> Option<crate::ConnectionStats> {
+        self.inner.stats()
+    }
+
+    #[cfg(not(feature = "use_system_lib"))]
+    /// Set the maximum size in bytes of the messages exchanged with this client
+    ///
+    /// Sending a bigger event fails, and receiving a bigger request is treated as a
+    /// protocol error. The default is 4096 bytes, the limit of the system wayland library,
+    /// and it can be raised up to 65532 bytes, the limit of the wire format. Only raise it
+    /// if the client is known to accept bigger messages.
+    ///
+    /// This is not available with the `use_system_lib` backend, as the system library
+    /// has a fixed limit.
+    pub fn set_max_message_size(&self, size: usize) {
+        self.inner.set_max_message_size(size)
+    }
+
     /// Returns a reference to the `UserDataMap` associated with this client
     ///
     /// See `UserDataMap` documentation for details about its use.
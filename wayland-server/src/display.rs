@@ -231,6 +231,63 @@ impl Display {
         let data = crate::DispatchData::wrap(data);
         Client::make(self.inner.create_client(fd, data))
     }
+
+    /// Set a filter restricting the globals advertised to the clients
+    ///
+    /// The filter is given the client and the interface name of the global, and applies
+    /// to all globals of the display, including those created by C code with the
+    /// `use_system_lib` backend. It is checked
+    /// before the filter of a global created with `create_global_with_filter`. Setting a
+    /// new filter replaces the previous one.
+    ///
+    /// With the `use_system_lib` backend, if the system `libwayland-server` is too old to
+    /// provide `wl_global_get_interface`, the interface name given for the globals created
+    /// by C code is empty.
+    pub fn set_global_filter<F>(&mut self, mut filter: F)
+    where
+        F: FnMut(Client, &str) -> bool + 'static,
+    {
+        self.inner.set_global_filter(Some(move |client, interface: &str| {
+            filter(Client::make(client), interface)
+        }))
+    }
+
+    /// Remove the filter set with `set_global_filter`
+    pub fn unset_global_filter(&mut self) {
+        self.inner.set_global_filter(None::<fn(_, &str) -> bool>)
+    }
+
+    /// Add a logger, given all the requests received and events sent by the display
+    pub fn add_protocol_logger<F>(&mut self, logger: F) -> crate::ProtocolLogger
+    where
+        F: FnMut(&crate::ProtocolMessage<'_>) + 'static,
+    {
+        crate::ProtocolLogger::make(self.inner.add_protocol_logger(logger))
+    }
+
+    /// Add a timer to the event loop of the display
+    ///
+    /// The timer is created disarmed. Its callback is invoked from `dispatch` once it
+    /// expires, with the dispatch data given to `dispatch`.
+    pub fn add_timer<F>(&mut self, callback: F) -> crate::TimerSource
+    where
+        F: FnMut(crate::DispatchData<'_>) + 'static,
+    {
+        crate::TimerSource::make(self.inner.add_timer(callback))
+    }
+
+    /// Handle a signal in the event loop of the display
+    ///
+    /// The callback is invoked from `dispatch` when the signal is received, with the
+    /// signal number and the dispatch data given to `dispatch`. The signal is received
+    /// with a `signalfd`, so it must be blocked in all the threads of the program for
+    /// its default disposition not to apply.
+    pub fn add_signal<F>(&mut self, signal: i32, callback: F) -> crate::SignalSource
+    where
+        F: FnMut(i32, crate::DispatchData<'_>) + 'static,
+    {
+        crate::SignalSource::make(self.inner.add_signal(signal, callback))
+    }
 }
 
 #[cfg(feature = "use_system_lib")]
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::imp::EventSourceInner;
+
+/// A timer of the event loop of a `Display`
+///
+/// Its callback is invoked from `Display::dispatch` once the timer expires. The timer
+/// is created disarmed, use `set_delay` to arm it.
+pub struct TimerSource {
+    inner: EventSourceInner,
+}
+
+impl TimerSource {
+    pub(crate) fn make(inner: EventSourceInner) -> TimerSource {
+        TimerSource { inner }
+    }
+
+    /// Arm the timer to expire after the given delay, or disarm it with `None`
+    ///
+    /// The delay has a millisecond precision, and replaces any previous delay. The
+    /// timer expires only once, it can be armed again from its callback.
+    ///
+    /// Returns `false` if the timer could not be set, or was removed.
+    pub fn set_delay(&self, delay: Option<Duration>) -> bool {
+        self.inner.timer_update(delay)
+    }
+
+    /// Checks whether the timer is still part of the event loop
+    ///
+    /// This is `false` once it is removed, or its display is destroyed.
+    pub fn is_active(&self) -> bool {
+        self.inner.is_active()
+    }
+
+    /// Remove the timer from the event loop
+    pub fn remove(self) {
+        self.inner.remove()
+    }
+}
+
+/// A source of the event loop of a `Display` handling a signal
+///
+/// Its callback is invoked from `Display::dispatch` when the signal is received.
+pub struct SignalSource {
+    inner: EventSourceInner,
+}
+
+impl SignalSource {
+    pub(crate) fn make(inner: EventSourceInner) -> SignalSource {
+        SignalSource { inner }
+    }
+
+    /// Checks whether the source is still part of the event loop
+    ///
+    /// This is `false` if it could not be created, once it is removed, or once its
+    /// display is destroyed.
+    pub fn is_active(&self) -> bool {
+        self.inner.is_active()
+    }
+
+    /// Remove the source from the event loop
+    pub fn remove(self) {
+        self.inner.remove()
+    }
+}
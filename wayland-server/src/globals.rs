@@ -19,7 +19,26 @@ impl<I: Interface + AsRef<Resource<I>> + From<Resource<I>>> Global<I> {
         Global { inner }
     }
 
+    /// Stops advertising the global to the clients, without destroying it yet
+    ///
+    /// The clients are notified of the removal of the global, but it can still be bound
+    /// by those which did not receive the notification yet. Call `destroy` after a while,
+    /// once they had the time to process it.
+    ///
+    /// With the `use_system_lib` backend and a `libwayland-server` older than 1.17, which
+    /// cannot remove a global, it is instead hidden by the global filter: the clients are
+    /// only notified of its removal once it is destroyed, and binding it is a protocol
+    /// error.
+    pub fn disable(&self) {
+        self.inner.disable()
+    }
+
     /// Destroys the associated global object.
+    ///
+    /// If it was not disabled, the clients are notified of its removal. Those binding it
+    /// before receiving the notification get an inert object, with the rust backend, or
+    /// a protocol error with the `use_system_lib` backend, so `disable` should be called
+    /// first to avoid this race.
     pub fn destroy(self) {
         self.inner.destroy()
     }
@@ -76,18 +76,27 @@ extern crate wayland_sys;
 
 mod client;
 mod display;
+mod event_source;
 mod globals;
+mod protocol_logger;
 mod resource;
+mod serial;
+mod shm;
 
 pub use client::Client;
 pub use display::Display;
+pub use event_source::{SignalSource, TimerSource};
 pub use globals::Global;
-pub use resource::{Main, Resource};
+pub use protocol_logger::{MessageDirection, ProtocolLogger, ProtocolMessage};
+pub use resource::{Main, Resource, WeakResource};
+pub use serial::{SerialCounter, SerialRecord, SerialTracker};
+pub use shm::{init_shm_global, with_buffer_contents, BufferAccessError, BufferData};
 
 pub use anonymous_object::AnonymousObject;
 pub use wayland_commons::user_data::UserDataMap;
 pub use wayland_commons::{
     filter::{DispatchData, Filter},
+    socket::{ConnectionStats, SocketStats},
     Interface, MessageGroup, NoMessage,
 };
 
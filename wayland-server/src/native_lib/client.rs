@@ -8,7 +8,7 @@ use wayland_commons::ThreadGuard;
 use wayland_sys::server::*;
 
 use super::resource::ResourceInner;
-use crate::{DispatchData, Interface, Resource, UserDataMap};
+use crate::{AnonymousObject, DispatchData, Interface, Resource, UserDataMap};
 
 type BoxedDest = Box<dyn FnMut(Arc<UserDataMap>, DispatchData<'_>) + 'static>;
 
@@ -75,6 +75,10 @@ impl ClientInner {
         self.internal.alive.load(Ordering::Acquire)
     }
 
+    pub(crate) fn stats(&self) -> Option<crate::ConnectionStats> {
+        None
+    }
+
     pub(crate) fn equals(&self, other: &ClientInner) -> bool {
         Arc::ptr_eq(&self.internal, &other.internal)
     }
@@ -121,6 +125,79 @@ impl ClientInner {
         }))
     }
 
+    pub(crate) fn add_resource_created_listener<F>(&self, listener: F)
+    where
+        F: FnMut(Resource<AnonymousObject>, DispatchData<'_>) + 'static,
+    {
+        if self.internal.safe_thread != std::thread::current().id() {
+            panic!("Can only add a listener from the thread hosting the Display.");
+        }
+        let add_listener = match WAYLAND_SERVER_OPTIONAL.wl_client_add_resource_created_listener {
+            Some(f) => f,
+            // the library is too old to notify resource creations
+            None => return,
+        };
+        if !self.alive() {
+            return;
+        }
+        let _c_safety_guard = super::C_SAFETY.lock();
+        let data: Box<ResourceCreatedListener> = Box::new(Box::new(listener));
+        unsafe {
+            let listener = signal::rust_listener_create(resource_created);
+            signal::rust_listener_set_user_data(listener, Box::into_raw(data) as *mut c_void);
+            add_listener(self.ptr, listener);
+            // the listener is freed along with the client
+            let listener = listener as usize;
+            self.add_destructor(move |_, _| {
+                let listener = listener as *mut wl_listener;
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_list_remove, &mut (*listener).link);
+                drop(Box::from_raw(
+                    signal::rust_listener_get_user_data(listener) as *mut ResourceCreatedListener
+                ));
+                signal::rust_listener_destroy(listener);
+            });
+        }
+    }
+
+    pub(crate) fn for_each_resource<F>(&self, mut f: F)
+    where
+        F: FnMut(Resource<AnonymousObject>),
+    {
+        if self.internal.safe_thread != std::thread::current().id() {
+            panic!("Can only iterate over resources from the thread hosting the Display.");
+        }
+        let for_each = match WAYLAND_SERVER_OPTIONAL.wl_client_for_each_resource {
+            Some(f) => f,
+            // the library is too old to list the resources of a client
+            None => return,
+        };
+        if !self.alive() {
+            return;
+        }
+        let _c_safety_guard = super::C_SAFETY.lock();
+        // Collect the resources first: creating or destroying resources while the
+        // C library iterates over them is not allowed, the callback could do both.
+        let mut resources: Vec<(*mut wl_resource, u32)> = Vec::new();
+        unsafe {
+            for_each(
+                self.ptr,
+                collect_resource,
+                &mut resources as *mut Vec<(*mut wl_resource, u32)> as *mut c_void,
+            );
+        }
+        for (ptr, id) in resources {
+            if !self.alive() {
+                return;
+            }
+            // skip the resources destroyed by the previous calls
+            let current =
+                unsafe { ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_get_object, self.ptr, id) };
+            if current == ptr {
+                f(Resource::wrap(unsafe { ResourceInner::from_c_ptr_anonymous(ptr) }));
+            }
+        }
+    }
+
     pub(crate) fn create_resource<I: Interface + From<Resource<I>> + AsRef<Resource<I>>>(
         &self,
         version: u32,
@@ -146,6 +223,32 @@ impl ClientInner {
     }
 }
 
+type ResourceCreatedListener = Box<dyn FnMut(Resource<AnonymousObject>, DispatchData<'_>)>;
+
+unsafe extern "C" fn resource_created(listener: *mut wl_listener, data: *mut c_void) {
+    // safety of this function is the same as dispatch_func
+    let ret = ::std::panic::catch_unwind(move || {
+        let callback =
+            &mut *(signal::rust_listener_get_user_data(listener) as *mut ResourceCreatedListener);
+        let resource = Resource::wrap(ResourceInner::from_c_ptr_anonymous(data as *mut _));
+        // resources can be created from request handlers, which borrow the dispatch data
+        super::with_current_dispatch_data(|data| callback(resource, data));
+    });
+    if ret.is_err() {
+        eprintln!("[wayland-server error] A resource creation listener panicked, aborting.");
+        ::libc::abort();
+    }
+}
+
+unsafe extern "C" fn collect_resource(
+    resource: *mut wl_resource,
+    data: *mut c_void,
+) -> wl_iterator_result {
+    let resources = &mut *(data as *mut Vec<(*mut wl_resource, u32)>);
+    resources.push((resource, ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_id, resource)));
+    wl_iterator_result::WL_ITERATOR_CONTINUE
+}
+
 unsafe extern "C" fn client_destroy(listener: *mut wl_listener, _data: *mut c_void) {
     let internal =
         Box::from_raw(signal::rust_listener_get_user_data(listener) as *mut Arc<ClientInternal>);
@@ -1,4 +1,3 @@
-use std::cell::RefCell;
 use std::ffi::{CStr, CString, OsStr, OsString};
 use std::io::{Error as IoError, ErrorKind, Result as IoResult};
 use std::os::raw::c_void;
@@ -9,19 +8,22 @@ use std::rc::Rc;
 
 use wayland_sys::server::*;
 
-use super::globals::GlobalData;
+use super::event_loop::{AttachedObjects, EventSourceInner, ProtocolLoggerInner};
+use super::globals::{GlobalData, GlobalFilterData};
 use super::{ClientInner, GlobalInner};
 
 use crate::display::get_runtime_dir;
-use crate::{Interface, Main, Resource};
+use crate::{DispatchData, Interface, Main, ProtocolMessage, Resource};
 
 pub(crate) struct DisplayInner {
     pub(crate) ptr: *mut wl_display,
-    rust_globals: Rc<RefCell<Vec<*mut wl_global>>>,
+    global_filter: Box<GlobalFilterData>,
+    attached: Rc<AttachedObjects>,
 }
 
 impl Drop for DisplayInner {
     fn drop(&mut self) {
+        self.attached.clear();
         {
             let _c_safety_guard = super::C_SAFETY.lock();
             unsafe {
@@ -45,17 +47,17 @@ impl DisplayInner {
                 ptr,
                 listener
             );
-            let rust_globals = Rc::new(RefCell::new(Vec::new()));
+            let global_filter = Box::new(GlobalFilterData::default());
             // setup the global filter
             ffi_dispatch!(
                 WAYLAND_SERVER_HANDLE,
                 wl_display_set_global_filter,
                 ptr,
                 super::globals::global_filter,
-                &*rust_globals as *const RefCell<Vec<*mut wl_global>> as *mut _
+                &*global_filter as *const GlobalFilterData as *mut _
             );
 
-            DisplayInner { ptr, rust_globals }
+            DisplayInner { ptr, global_filter, attached: Rc::new(AttachedObjects::default()) }
         }
     }
 
@@ -87,12 +89,45 @@ impl DisplayInner {
                 super::globals::global_bind::<I>
             );
 
-            self.rust_globals.borrow_mut().push(ptr);
+            self.global_filter.rust_globals.borrow_mut().push(ptr);
 
-            GlobalInner::create(ptr, data, self.rust_globals.clone())
+            GlobalInner::create(ptr, data, self.global_filter.rust_globals.clone())
         }
     }
 
+    pub(crate) fn set_global_filter<F>(&mut self, filter: Option<F>)
+    where
+        F: FnMut(ClientInner, &str) -> bool + 'static,
+    {
+        *self.global_filter.filter.borrow_mut() = filter.map(|f| Box::new(f) as Box<_>);
+    }
+
+    pub(crate) fn add_protocol_logger<F>(&mut self, logger: F) -> ProtocolLoggerInner
+    where
+        F: FnMut(&ProtocolMessage<'_>) + 'static,
+    {
+        ProtocolLoggerInner::add(self.ptr, &self.attached, logger)
+    }
+
+    pub(crate) fn add_timer<F>(&mut self, callback: F) -> EventSourceInner
+    where
+        F: FnMut(DispatchData<'_>) + 'static,
+    {
+        EventSourceInner::add_timer(self.event_loop(), &self.attached, callback)
+    }
+
+    pub(crate) fn add_signal<F>(&mut self, signal: i32, callback: F) -> EventSourceInner
+    where
+        F: FnMut(i32, DispatchData<'_>) + 'static,
+    {
+        EventSourceInner::add_signal(self.event_loop(), &self.attached, signal, callback)
+    }
+
+    fn event_loop(&self) -> *mut wl_event_loop {
+        let _c_safety_guard = super::C_SAFETY.lock();
+        unsafe { ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, self.ptr) }
+    }
+
     pub(crate) fn flush_clients(&mut self, data: crate::DispatchData) {
         super::with_dispatch_data(data, || {
             let _c_safety_guard = super::C_SAFETY.lock();
@@ -113,6 +148,7 @@ impl DisplayInner {
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_loop_dispatch, evl_ptr, timeout)
             }
         });
+        self.attached.collect();
 
         if ret < 0 {
             Err(IoError::last_os_error())
@@ -0,0 +1,280 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use wayland_sys::server::*;
+
+use crate::{DispatchData, MessageDirection, ProtocolMessage};
+
+/// An object owned by the C library, along with the rust data it points to
+struct Attached {
+    ptr: *mut c_void,
+    destroy: unsafe fn(*mut c_void),
+    data: Box<dyn Any>,
+}
+
+/// The event sources and protocol loggers of a display
+///
+/// They are tracked so that they can be removed before the display is destroyed,
+/// and their rust data freed.
+#[derive(Default)]
+pub(crate) struct AttachedObjects {
+    objects: RefCell<Vec<Attached>>,
+    // data of the removed objects, which may be removed from their own callback,
+    // freed once the event loop is no longer dispatching
+    removed: RefCell<Vec<Box<dyn Any>>>,
+}
+
+impl AttachedObjects {
+    fn insert(&self, ptr: *mut c_void, destroy: unsafe fn(*mut c_void), data: Box<dyn Any>) {
+        self.objects.borrow_mut().push(Attached { ptr, destroy, data });
+    }
+
+    fn contains(&self, ptr: *mut c_void) -> bool {
+        self.objects.borrow().iter().any(|object| object.ptr == ptr)
+    }
+
+    fn remove(&self, ptr: *mut c_void) {
+        let removed = {
+            let mut objects = self.objects.borrow_mut();
+            objects.iter().position(|object| object.ptr == ptr).map(|i| objects.remove(i))
+        };
+        if let Some(object) = removed {
+            let _c_safety_guard = super::C_SAFETY.lock();
+            unsafe { (object.destroy)(object.ptr) };
+            self.removed.borrow_mut().push(object.data);
+        }
+    }
+
+    /// Free the data of the removed objects, must not be called from their callbacks
+    pub(crate) fn collect(&self) {
+        let removed = std::mem::take(&mut *self.removed.borrow_mut());
+        drop(removed);
+    }
+
+    /// Remove all objects, must be called before destroying the display
+    pub(crate) fn clear(&self) {
+        let objects = std::mem::take(&mut *self.objects.borrow_mut());
+        {
+            let _c_safety_guard = super::C_SAFETY.lock();
+            for object in &objects {
+                unsafe { (object.destroy)(object.ptr) };
+            }
+        }
+        drop(objects);
+        self.collect();
+    }
+}
+
+unsafe fn remove_source(ptr: *mut c_void) {
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, ptr as *mut wl_event_source);
+}
+
+unsafe fn destroy_logger(ptr: *mut c_void) {
+    ffi_dispatch!(
+        WAYLAND_SERVER_HANDLE,
+        wl_protocol_logger_destroy,
+        ptr as *mut wl_protocol_logger
+    );
+}
+
+type TimerCallback = Box<dyn FnMut(DispatchData<'_>)>;
+type SignalCallback = Box<dyn FnMut(i32, DispatchData<'_>)>;
+type LoggerCallback = Box<dyn FnMut(&ProtocolMessage<'_>)>;
+
+pub(crate) struct EventSourceInner {
+    ptr: *mut wl_event_source,
+    objects: Weak<AttachedObjects>,
+}
+
+impl EventSourceInner {
+    pub(crate) fn add_timer<F>(
+        event_loop: *mut wl_event_loop,
+        objects: &Rc<AttachedObjects>,
+        callback: F,
+    ) -> EventSourceInner
+    where
+        F: FnMut(DispatchData<'_>) + 'static,
+    {
+        let data: Box<TimerCallback> = Box::new(Box::new(callback));
+        let _c_safety_guard = super::C_SAFETY.lock();
+        let ptr = unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_event_loop_add_timer,
+                event_loop,
+                timer_callback,
+                &*data as *const TimerCallback as *mut c_void
+            )
+        };
+        EventSourceInner::track(ptr, objects, data)
+    }
+
+    pub(crate) fn add_signal<F>(
+        event_loop: *mut wl_event_loop,
+        objects: &Rc<AttachedObjects>,
+        signal: i32,
+        callback: F,
+    ) -> EventSourceInner
+    where
+        F: FnMut(i32, DispatchData<'_>) + 'static,
+    {
+        let data: Box<SignalCallback> = Box::new(Box::new(callback));
+        let _c_safety_guard = super::C_SAFETY.lock();
+        let ptr = unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_event_loop_add_signal,
+                event_loop,
+                signal,
+                signal_callback,
+                &*data as *const SignalCallback as *mut c_void
+            )
+        };
+        EventSourceInner::track(ptr, objects, data)
+    }
+
+    fn track(
+        ptr: *mut wl_event_source,
+        objects: &Rc<AttachedObjects>,
+        data: Box<dyn Any>,
+    ) -> EventSourceInner {
+        if !ptr.is_null() {
+            objects.insert(ptr as *mut c_void, remove_source, data);
+        }
+        EventSourceInner { ptr, objects: Rc::downgrade(objects) }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        match self.objects.upgrade() {
+            Some(objects) => objects.contains(self.ptr as *mut c_void),
+            None => false,
+        }
+    }
+
+    pub(crate) fn timer_update(&self, delay: Option<Duration>) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        let ms = match delay {
+            // a delay of 0 disarms the timer
+            Some(delay) => delay.as_millis().max(1).min(std::i32::MAX as u128) as c_int,
+            None => 0,
+        };
+        let _c_safety_guard = super::C_SAFETY.lock();
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_timer_update, self.ptr, ms) == 0
+        }
+    }
+
+    pub(crate) fn remove(self) {
+        if let Some(objects) = self.objects.upgrade() {
+            objects.remove(self.ptr as *mut c_void);
+        }
+    }
+}
+
+pub(crate) struct ProtocolLoggerInner {
+    ptr: *mut wl_protocol_logger,
+    objects: Weak<AttachedObjects>,
+}
+
+impl ProtocolLoggerInner {
+    pub(crate) fn add<F>(
+        display: *mut wl_display,
+        objects: &Rc<AttachedObjects>,
+        callback: F,
+    ) -> ProtocolLoggerInner
+    where
+        F: FnMut(&ProtocolMessage<'_>) + 'static,
+    {
+        let data: Box<LoggerCallback> = Box::new(Box::new(callback));
+        let _c_safety_guard = super::C_SAFETY.lock();
+        let ptr = unsafe {
+            ffi_dispatch!(
+                WAYLAND_SERVER_HANDLE,
+                wl_display_add_protocol_logger,
+                display,
+                logger_callback,
+                &*data as *const LoggerCallback as *mut c_void
+            )
+        };
+        if !ptr.is_null() {
+            objects.insert(ptr as *mut c_void, destroy_logger, data);
+        }
+        ProtocolLoggerInner { ptr, objects: Rc::downgrade(objects) }
+    }
+
+    pub(crate) fn destroy(self) {
+        if let Some(objects) = self.objects.upgrade() {
+            objects.remove(self.ptr as *mut c_void);
+        }
+    }
+}
+
+unsafe extern "C" fn timer_callback(data: *mut c_void) -> c_int {
+    // safety of this function is the same as dispatch_func
+    let ret = ::std::panic::catch_unwind(move || {
+        let callback = &mut *(data as *mut TimerCallback);
+        super::with_current_dispatch_data(callback);
+    });
+    if ret.is_err() {
+        eprintln!("[wayland-server error] A timer callback panicked, aborting.");
+        ::libc::abort();
+    }
+    0
+}
+
+unsafe extern "C" fn signal_callback(signal: c_int, data: *mut c_void) -> c_int {
+    // safety of this function is the same as dispatch_func
+    let ret = ::std::panic::catch_unwind(move || {
+        let callback = &mut *(data as *mut SignalCallback);
+        super::with_current_dispatch_data(|ddata| callback(signal, ddata));
+    });
+    if ret.is_err() {
+        eprintln!("[wayland-server error] A signal callback panicked, aborting.");
+        ::libc::abort();
+    }
+    0
+}
+
+unsafe extern "C" fn logger_callback(
+    data: *mut c_void,
+    direction: wl_protocol_logger_type,
+    message: *const wl_protocol_logger_message,
+) {
+    // safety of this function is the same as dispatch_func
+    let ret = ::std::panic::catch_unwind(move || {
+        let callback = &mut *(data as *mut LoggerCallback);
+        let message = &*message;
+        let resource = message.resource;
+        let interface = match WAYLAND_SERVER_OPTIONAL.wl_resource_get_class {
+            Some(wl_resource_get_class) => wl_resource_get_class(resource),
+            None => ::std::ptr::null(),
+        };
+        callback(&ProtocolMessage {
+            direction: match direction {
+                wl_protocol_logger_type::WL_PROTOCOL_LOGGER_REQUEST => MessageDirection::Request,
+                wl_protocol_logger_type::WL_PROTOCOL_LOGGER_EVENT => MessageDirection::Event,
+            },
+            interface: c_str(interface),
+            object_id: ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_id, resource),
+            opcode: message.message_opcode as u16,
+            name: c_str((*message.message).name),
+        });
+    });
+    if ret.is_err() {
+        eprintln!("[wayland-server error] A protocol logger panicked, aborting.");
+        ::libc::abort();
+    }
+}
+
+unsafe fn c_str<'a>(ptr: *const c_char) -> &'a str {
+    if ptr.is_null() {
+        return "";
+    }
+    CStr::from_ptr(ptr).to_str().unwrap_or("<invalid>")
+}
@@ -1,5 +1,7 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::ffi::CStr;
 use std::os::raw::c_void;
+use std::ptr;
 use std::rc::Rc;
 
 use wayland_commons::Interface;
@@ -12,6 +14,9 @@ use crate::{DispatchData, Main, Resource};
 pub(crate) struct GlobalData<I: Interface + AsRef<Resource<I>> + From<Resource<I>>> {
     pub(crate) bind: Box<dyn FnMut(Main<I>, u32, DispatchData<'_>)>,
     pub(crate) filter: Option<Box<dyn FnMut(ClientInner) -> bool>>,
+    pub(crate) interface: &'static str,
+    // set when the global is disabled but wl_global_remove is not available
+    pub(crate) hidden: Cell<bool>,
 }
 
 impl<I: Interface + AsRef<Resource<I>> + From<Resource<I>>> GlobalData<I> {
@@ -21,14 +26,29 @@ impl<I: Interface + AsRef<Resource<I>> + From<Resource<I>>> GlobalData<I> {
         F1: FnMut(Main<I>, u32, DispatchData<'_>) + 'static,
         F2: FnMut(ClientInner) -> bool + 'static,
     {
-        GlobalData { bind: Box::new(bind) as Box<_>, filter: filter.map(|f| Box::new(f) as Box<_>) }
+        GlobalData {
+            bind: Box::new(bind) as Box<_>,
+            filter: filter.map(|f| Box::new(f) as Box<_>),
+            interface: I::NAME,
+            hidden: Cell::new(false),
+        }
     }
 }
 
+type DisplayFilter = Box<dyn FnMut(ClientInner, &str) -> bool>;
+
+/// The data given to the global filter of the display
+#[derive(Default)]
+pub(crate) struct GlobalFilterData {
+    pub(crate) rust_globals: Rc<RefCell<Vec<*mut wl_global>>>,
+    pub(crate) filter: RefCell<Option<DisplayFilter>>,
+}
+
 pub(crate) struct GlobalInner<I: Interface + AsRef<Resource<I>> + From<Resource<I>>> {
     ptr: *mut wl_global,
     data: *mut GlobalData<I>,
     rust_globals: Rc<RefCell<Vec<*mut wl_global>>>,
+    disabled: Cell<bool>,
 }
 
 impl<I> GlobalInner<I>
@@ -40,7 +60,20 @@ where
         data: Box<GlobalData<I>>,
         rust_globals: Rc<RefCell<Vec<*mut wl_global>>>,
     ) -> GlobalInner<I> {
-        GlobalInner { ptr, data: Box::into_raw(data), rust_globals }
+        GlobalInner { ptr, data: Box::into_raw(data), rust_globals, disabled: Cell::new(false) }
+    }
+
+    pub fn disable(&self) {
+        if self.disabled.replace(true) {
+            return;
+        }
+        let _c_safety_guard = super::C_SAFETY.lock();
+        if let Some(wl_global_remove) = WAYLAND_SERVER_OPTIONAL.wl_global_remove {
+            unsafe { wl_global_remove(self.ptr) };
+        } else {
+            // the library is too old, hide the global from the clients using the global filter
+            unsafe { (*self.data).hidden.set(true) };
+        }
     }
 
     pub fn destroy(self) {
@@ -49,7 +82,7 @@ where
             // destroy the global
             ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_global_destroy, self.ptr);
             // remove from the list
-            self.rust_globals.borrow_mut().retain(|&g| g != self.ptr);
+            self.rust_globals.borrow_mut().retain(|&g| !ptr::eq(g, self.ptr));
             // free the user data
             let data = Box::from_raw(self.data);
             drop(data);
@@ -102,16 +135,41 @@ pub(crate) unsafe extern "C" fn global_filter(
 ) -> bool {
     // safety of this function is the same as dispatch_func
     let ret = ::std::panic::catch_unwind(move || {
+        let filter_data = &*(data as *const GlobalFilterData);
+        let client = ClientInner::from_ptr(client as *mut _);
+        let rust_managed = filter_data.rust_globals.borrow().iter().any(|&g| ptr::eq(g, global));
+        let global_data = if rust_managed {
+            Some(
+                &mut *(ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_global_get_user_data, global)
+                    as *mut GlobalData<crate::AnonymousObject>),
+            )
+        } else {
+            None
+        };
+        // the filter of the display applies to all globals
+        if let Some(ref mut filter) = *filter_data.filter.borrow_mut() {
+            let name = match (&global_data, WAYLAND_SERVER_OPTIONAL.wl_global_get_interface) {
+                (Some(global_data), _) => global_data.interface,
+                (None, Some(wl_global_get_interface)) => {
+                    let interface = wl_global_get_interface(global);
+                    CStr::from_ptr((*interface).name).to_str().unwrap_or("")
+                }
+                // the library is too old to tell the interface of globals created from C
+                (None, None) => "",
+            };
+            if !filter(client.clone(), name) {
+                return false;
+            }
+        }
         // early exit with true if the global is not rust-managed
-        let rust_globals = &*(data as *const RefCell<Vec<*mut wl_global>>);
-        if rust_globals.borrow().iter().all(|&g| g as *const wl_global != global) {
-            return true;
+        let global_data = match global_data {
+            Some(global_data) => global_data,
+            None => return true,
+        };
+        if global_data.hidden.get() {
+            return false;
         }
-        // the global is rust-managed, continue
-        let global_data = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_global_get_user_data, global)
-            as *mut GlobalData<crate::AnonymousObject>;
-        let client = ClientInner::from_ptr(client as *mut _);
-        let filter = &mut (*global_data).filter;
+        let filter = &mut global_data.filter;
         if let Some(ref mut filter) = *filter {
             filter(client)
         } else {
@@ -6,13 +6,15 @@ use crate::{Interface, Main, Resource};
 
 mod client;
 mod display;
+mod event_loop;
 mod globals;
 mod resource;
 
 pub(crate) use self::client::ClientInner;
 pub(crate) use self::display::DisplayInner;
+pub(crate) use self::event_loop::{EventSourceInner, ProtocolLoggerInner};
 pub(crate) use self::globals::GlobalInner;
-pub(crate) use self::resource::ResourceInner;
+pub(crate) use self::resource::{ResourceInner, WeakResourceInner};
 
 lazy_static::lazy_static! {
     // This lock *must* be held whenever an ffi call is made to
@@ -53,3 +55,19 @@ where
     let data = unsafe { std::mem::transmute(data) };
     DISPATCH_DATA.set(&RefCell::new(data), || f())
 }
+
+/// Calls `f` with the dispatch data of the current dispatch
+///
+/// The callbacks of the C library that can be invoked while a request handler or a
+/// global bind callback already borrows the dispatch data, such as listeners of the
+/// created resources, get an empty one instead.
+pub(crate) fn with_current_dispatch_data<F: FnOnce(crate::DispatchData<'_>)>(f: F) {
+    if DISPATCH_DATA.is_set() {
+        DISPATCH_DATA.with(|disp_data| match disp_data.try_borrow_mut() {
+            Ok(mut disp_data) => f(disp_data.reborrow()),
+            Err(_) => f(crate::DispatchData::wrap(&mut ())),
+        });
+    } else {
+        f(crate::DispatchData::wrap(&mut ()));
+    }
+}
@@ -1,7 +1,7 @@
 use std::cell::RefCell;
 use std::os::raw::{c_int, c_void};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 use wayland_sys::common::*;
 use wayland_sys::server::*;
@@ -90,6 +90,10 @@ impl ResourceInner {
         }
     }
 
+    pub(crate) fn downgrade(&self) -> WeakResourceInner {
+        WeakResourceInner { internal: self.internal.as_ref().map(Arc::downgrade), ptr: self.ptr }
+    }
+
     pub(crate) fn same_client_as(&self, other: &ResourceInner) -> bool {
         if !(self.is_alive() && other.is_alive()) {
             return false;
@@ -209,6 +213,11 @@ impl ResourceInner {
         ResourceInner { internal, ptr }
     }
 
+    /// A handle to a resource whose interface is unknown, it is treated as external
+    pub(crate) unsafe fn from_c_ptr_anonymous(ptr: *mut wl_resource) -> Self {
+        ResourceInner { internal: None, ptr }
+    }
+
     pub unsafe fn make_child_for<J: Interface + From<Resource<J>> + AsRef<Resource<J>>>(
         &self,
         id: u32,
@@ -285,6 +294,31 @@ impl ResourceInner {
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct WeakResourceInner {
+    internal: Option<Weak<ResourceInternal>>,
+    ptr: *mut wl_resource,
+}
+
+unsafe impl Send for WeakResourceInner {}
+unsafe impl Sync for WeakResourceInner {}
+
+impl WeakResourceInner {
+    pub(crate) fn upgrade(&self) -> Option<ResourceInner> {
+        let internal = match self.internal {
+            Some(ref weak) => Some(weak.upgrade()?),
+            // external resources can't be tracked, same as `is_alive()`
+            None => None,
+        };
+        let inner = ResourceInner { internal, ptr: self.ptr };
+        if inner.is_alive() {
+            Some(inner)
+        } else {
+            None
+        }
+    }
+}
+
 type BoxedHandler<I> = Box<dyn Fn(<I as Interface>::Request, Main<I>, DispatchData<'_>)>;
 type BoxedDest<I> = Box<dyn Fn(Resource<I>, DispatchData<'_>)>;
 
@@ -0,0 +1,43 @@
+use crate::imp::ProtocolLoggerInner;
+
+/// Whether a logged message is a request or an event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageDirection {
+    /// A request received from a client
+    Request,
+    /// An event sent to a client
+    Event,
+}
+
+/// A message given to a protocol logger
+#[derive(Copy, Clone, Debug)]
+pub struct ProtocolMessage<'a> {
+    /// Whether the message is a request or an event
+    pub direction: MessageDirection,
+    /// Name of the interface of the object
+    pub interface: &'a str,
+    /// Id of the object, in the namespace of its client
+    pub object_id: u32,
+    /// Opcode of the message
+    pub opcode: u16,
+    /// Name of the message
+    pub name: &'a str,
+}
+
+/// A handle to a protocol logger of a `Display`
+///
+/// The logger stays active until it is destroyed, or its display is destroyed.
+pub struct ProtocolLogger {
+    inner: ProtocolLoggerInner,
+}
+
+impl ProtocolLogger {
+    pub(crate) fn make(inner: ProtocolLoggerInner) -> ProtocolLogger {
+        ProtocolLogger { inner }
+    }
+
+    /// Stop logging messages
+    pub fn destroy(self) {
+        self.inner.destroy()
+    }
+}
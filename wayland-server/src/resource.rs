@@ -5,8 +5,8 @@ use wayland_commons::{Interface, MessageGroup};
 
 use wayland_sys::server::*;
 
-use crate::imp::ResourceInner;
-use crate::{Client, Filter};
+use crate::imp::{ResourceInner, WeakResourceInner};
+use crate::{AnonymousObject, Client, Filter};
 
 /// An handle to a wayland resource
 ///
@@ -99,6 +99,17 @@ where
         self.inner.equals(&other.inner)
     }
 
+    /// Create a weak handle to this object
+    ///
+    /// The weak handle does not keep the object or its `UserData` alive, and
+    /// can be stored in the `UserData` of other objects without risking to
+    /// create reference cycles.
+    ///
+    /// See [`WeakResource`](struct.WeakResource.html) for details.
+    pub fn downgrade(&self) -> WeakResource<I> {
+        WeakResource { _i: ::std::marker::PhantomData, inner: self.inner.downgrade() }
+    }
+
     /// Check if this resource and the other belong to the same client
     ///
     /// Always return false if either of them is dead
@@ -257,6 +268,53 @@ impl<I: Interface> Debug for Resource<I> {
     }
 }
 
+/// A weak handle to a wayland resource
+///
+/// This handle does not keep the Rust-side resources associated with the
+/// object (notably its `UserData`) alive. It can be upgraded back to a
+/// `Resource<I>` as long as the protocol object it refers to is still alive,
+/// upgrading it once the object has been destroyed returns `None`.
+///
+/// It is obtained from the `Resource::downgrade()` method.
+pub struct WeakResource<I: Interface> {
+    _i: ::std::marker::PhantomData<&'static I>,
+    inner: WeakResourceInner,
+}
+
+impl<I> WeakResource<I>
+where
+    I: Interface + From<Resource<I>> + AsRef<Resource<I>>,
+{
+    /// Try to recover a strong handle to the object
+    ///
+    /// Returns `None` if the object has been destroyed.
+    pub fn upgrade(&self) -> Option<Resource<I>> {
+        self.inner.upgrade().map(Resource::wrap)
+    }
+
+    pub(crate) fn anonymize(self) -> WeakResource<AnonymousObject> {
+        WeakResource { _i: ::std::marker::PhantomData, inner: self.inner }
+    }
+}
+
+impl<I: Interface> Clone for WeakResource<I> {
+    fn clone(&self) -> WeakResource<I> {
+        WeakResource { _i: ::std::marker::PhantomData, inner: self.inner.clone() }
+    }
+}
+
+impl<I> Debug for WeakResource<I>
+where
+    I: Interface + From<Resource<I>> + AsRef<Resource<I>>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.upgrade() {
+            Some(resource) => write!(f, "{:?}[WEAK]", resource),
+            None => write!(f, "{}@0[WEAK]", I::NAME),
+        }
+    }
+}
+
 /// A main handle to a proxy
 #[derive(Clone, PartialEq)]
 pub struct Main<I: Interface + AsRef<Resource<I>> + From<Resource<I>>> {
@@ -1,21 +1,24 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
 use std::rc::Rc;
 use std::sync::atomic::Ordering;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread::{self, ThreadId};
 
 use nix::Result as NixResult;
 
 use wayland_commons::debug;
 use wayland_commons::map::{Object, ObjectMap, ObjectMetadata, SERVER_ID_LIMIT};
-use wayland_commons::socket::{BufferedSocket, Socket};
+use wayland_commons::socket::{BufferedSocket, ConnectionStats, Socket};
 use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc, MessageParseError};
 use wayland_commons::{smallvec, ThreadGuard};
 
-use crate::{DispatchData, Interface, UserDataMap};
+use crate::{AnonymousObject, DispatchData, Interface, MessageDirection, ProtocolMessage};
+use crate::{Resource, UserDataMap};
 
+use super::event_loop::{log_message, Loggers};
 use super::event_loop_glue::{FdManager, Token};
 use super::globals::GlobalManager;
 use super::resources::{ObjectMeta, ResourceDestructor, ResourceInner};
@@ -29,6 +32,7 @@ pub(crate) enum Error {
 }
 
 type BoxedClientDestructor = Box<dyn FnMut(Arc<UserDataMap>, DispatchData<'_>)>;
+type ResourceCreatedListener = Rc<RefCell<dyn FnMut(Resource<AnonymousObject>, DispatchData<'_>)>>;
 
 pub(crate) struct ClientConnection {
     socket: BufferedSocket,
@@ -38,6 +42,8 @@ pub(crate) struct ClientConnection {
     last_error: Option<Error>,
     pending_destructors: Vec<ResourceInner>,
     zombie_clients: Arc<Mutex<Vec<ClientConnection>>>,
+    loggers: Loggers,
+    created_listeners: ThreadGuard<Vec<ResourceCreatedListener>>,
 }
 
 impl ClientConnection {
@@ -45,6 +51,7 @@ impl ClientConnection {
         fd: RawFd,
         display_object: Object<ObjectMeta>,
         zombies: Arc<Mutex<Vec<ClientConnection>>>,
+        loggers: Loggers,
     ) -> ClientConnection {
         let socket = BufferedSocket::new(Socket::from_raw_fd(fd));
 
@@ -60,6 +67,8 @@ impl ClientConnection {
             last_error: None,
             pending_destructors: Vec::new(),
             zombie_clients: zombies,
+            loggers,
+            created_listeners: ThreadGuard::new(Vec::new()),
         }
     }
 
@@ -76,6 +85,18 @@ impl ClientConnection {
     }
 
     pub(crate) fn write_message(&mut self, msg: &Message) -> NixResult<()> {
+        if let Some(object) = self.map.lock().unwrap().find(msg.sender_id) {
+            log_message(
+                &self.loggers,
+                &ProtocolMessage {
+                    direction: MessageDirection::Event,
+                    interface: object.interface,
+                    object_id: msg.sender_id,
+                    opcode: msg.opcode,
+                    name: object.events.get(msg.opcode as usize).map(|e| e.name).unwrap_or(""),
+                },
+            );
+        }
         self.socket.write_message(msg)
     }
 
@@ -97,7 +118,8 @@ impl ClientConnection {
         }
     }
 
-    pub(crate) fn read_request(&mut self) -> Result<Option<Message>, Error> {
+    /// Read the next request, along with the id of the object it created if any
+    pub(crate) fn read_request(&mut self) -> Result<Option<(Message, Option<u32>)>, Error> {
         if let Some(ref err) = self.last_error {
             return Err(err.clone());
         }
@@ -158,6 +180,7 @@ impl ClientConnection {
         };
 
         // create a new object if applicable
+        let mut created_id = None;
         if let Some(child) = object.request_child(msg.opcode) {
             let new_id = msg
                 .args
@@ -170,7 +193,7 @@ impl ClientConnection {
 
             if let Err(()) = map.insert_at(new_id, child) {
                 eprintln!(
-                    "[wayland-client] Protocol error: server tried to create an object \"{}\" with invalid id \"{}\".",
+                    "[wayland-server] Protocol error: client tried to create an object \"{}\" with invalid id \"{}\".",
                     child_interface,
                     new_id
                 );
@@ -178,6 +201,7 @@ impl ClientConnection {
                 self.last_error = Some(Error::Protocol);
                 return Err(Error::Protocol);
             }
+            created_id = Some(new_id);
         } else {
             // debug assert: if this opcode does not define a child, then there should be no
             // NewId argument, unless we are the registry
@@ -187,10 +211,12 @@ impl ClientConnection {
             );
         }
 
-        Ok(Some(msg))
+        Ok(Some((msg, created_id)))
     }
 
     fn cleanup(mut self, mut data: crate::DispatchData) {
+        // objects destroyed by the last requests of the client are no longer in the map
+        self.call_destructors(data.reborrow());
         let dummy_client = ClientInner {
             data: Arc::new(Mutex::new(None)),
             user_data_map: self.user_data_map.clone(),
@@ -226,12 +252,39 @@ impl ClientInner {
         Arc::ptr_eq(&self.data, &other.data)
     }
 
+    pub(crate) fn downgrade(&self) -> WeakClientInner {
+        WeakClientInner {
+            data: Arc::downgrade(&self.data),
+            user_data_map: Arc::downgrade(&self.user_data_map),
+            loop_thread: self.loop_thread,
+        }
+    }
+
     pub(crate) fn flush(&self) {
         if let Some(ref mut cx) = *self.data.lock().unwrap() {
             let _ = cx.socket.flush();
         }
     }
 
+    pub(crate) fn stats(&self) -> Option<ConnectionStats> {
+        let data = self.data.lock().unwrap();
+        let cx = data.as_ref()?;
+        let mut live_objects = HashMap::new();
+        cx.map.lock().unwrap().with_all(|_, obj| {
+            // skip the placeholders of objects being created
+            if !obj.interface.is_empty() {
+                *live_objects.entry(obj.interface).or_insert(0) += 1;
+            }
+        });
+        Some(ConnectionStats { socket: cx.socket.stats(), live_objects })
+    }
+
+    pub(crate) fn set_max_message_size(&self, size: usize) {
+        if let Some(ref mut cx) = *self.data.lock().unwrap() {
+            cx.socket.set_max_message_size(size);
+        }
+    }
+
     pub(crate) fn kill(&self) {
         if let Some(mut clientconn) = self.data.lock().unwrap().take() {
             let _ = clientconn.socket.flush();
@@ -279,6 +332,67 @@ impl ClientInner {
         self.kill();
     }
 
+    pub(crate) fn add_resource_created_listener<F>(&self, listener: F)
+    where
+        F: FnMut(Resource<AnonymousObject>, DispatchData<'_>) + 'static,
+    {
+        if self.loop_thread != thread::current().id() {
+            panic!("Can only add a listener from the thread hosting the Display.");
+        }
+        if let Some(ref mut cx) = *self.data.lock().unwrap() {
+            cx.created_listeners.get_mut().push(Rc::new(RefCell::new(listener)));
+        }
+    }
+
+    /// Notify the resource created listeners of a new object, must not be called with
+    /// the client locked
+    pub(crate) fn notify_resource_created(&self, id: u32, mut data: DispatchData) {
+        let (listeners, map) = match *self.data.lock().unwrap() {
+            Some(ref cx) => match cx.created_listeners.try_get() {
+                Some(listeners) if !listeners.is_empty() => (listeners.clone(), cx.map.clone()),
+                _ => return,
+            },
+            None => return,
+        };
+        let resource = match ResourceInner::from_id(id, map, self.clone()) {
+            Some(resource) => resource,
+            None => return,
+        };
+        for listener in listeners {
+            // a listener creating resources is not notified of them
+            if let Ok(mut listener) = listener.try_borrow_mut() {
+                (*listener)(Resource::wrap(resource.clone()), data.reborrow());
+            }
+        }
+    }
+
+    pub(crate) fn for_each_resource<F>(&self, mut f: F)
+    where
+        F: FnMut(Resource<AnonymousObject>),
+    {
+        if self.loop_thread != thread::current().id() {
+            panic!("Can only iterate over resources from the thread hosting the Display.");
+        }
+        // Collect the resources first: the callback can create and destroy resources,
+        // which requires the map not to be locked.
+        let mut resources = Vec::new();
+        match *self.data.lock().unwrap() {
+            Some(ref cx) => cx.map.lock().unwrap().with_all(|id, obj| {
+                // skip the placeholders of objects being created
+                if !obj.interface.is_empty() {
+                    resources.push(ResourceInner { id, object: obj.clone(), client: self.clone() });
+                }
+            }),
+            None => return,
+        }
+        for resource in resources {
+            // skip the resources destroyed by the previous calls
+            if resource.is_alive() && resource.downgrade().upgrade().is_some() {
+                f(Resource::wrap(resource));
+            }
+        }
+    }
+
     pub(crate) fn create_resource<I: Interface>(&self, version: u32) -> Option<ResourceInner> {
         if self.loop_thread != thread::current().id() {
             panic!("Can only create ressources from the thread hosting the Display.");
@@ -297,6 +411,8 @@ impl ClientInner {
             }
         };
 
+        // resources are created by the server outside of the dispatching of the display
+        self.notify_resource_created(id, DispatchData::wrap(&mut ()));
         Some(ResourceInner::from_id(id, map, self.clone()).unwrap())
     }
 
@@ -327,23 +443,43 @@ impl ClientInner {
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct WeakClientInner {
+    data: Weak<Mutex<Option<ClientConnection>>>,
+    user_data_map: Weak<UserDataMap>,
+    loop_thread: ThreadId,
+}
+
+impl WeakClientInner {
+    pub(crate) fn upgrade(&self) -> Option<ClientInner> {
+        Some(ClientInner {
+            data: self.data.upgrade()?,
+            user_data_map: self.user_data_map.upgrade()?,
+            loop_thread: self.loop_thread,
+        })
+    }
+}
+
 pub(crate) struct ClientManager {
     epoll_mgr: Rc<FdManager>,
     clients: Vec<(RefCell<Option<Token>>, ClientInner)>,
     zombie_clients: Arc<Mutex<Vec<ClientConnection>>>,
     global_mgr: Rc<RefCell<GlobalManager>>,
+    loggers: Loggers,
 }
 
 impl ClientManager {
     pub(crate) fn new(
         epoll_mgr: Rc<FdManager>,
         global_mgr: Rc<RefCell<GlobalManager>>,
+        loggers: Loggers,
     ) -> ClientManager {
         ClientManager {
             epoll_mgr,
             clients: Vec::new(),
             zombie_clients: Arc::new(Mutex::new(Vec::new())),
             global_mgr,
+            loggers,
         }
     }
 
@@ -364,7 +500,12 @@ impl ClientManager {
             childs_from_requests: display_req_child,
         };
 
-        let cx = ClientConnection::new(fd, display_object, self.zombie_clients.clone());
+        let cx = ClientConnection::new(
+            fd,
+            display_object,
+            self.zombie_clients.clone(),
+            self.loggers.clone(),
+        );
         let map = cx.map.clone();
         let user_data_map = cx.user_data_map.clone();
 
@@ -374,7 +515,8 @@ impl ClientManager {
             loop_thread: thread::current().id(), // init_client is only called by the display, which does not change threads
         };
 
-        let implementation = ClientImplementation { inner: client.clone(), map };
+        let implementation =
+            ClientImplementation { inner: client.clone(), map, loggers: self.loggers.clone() };
 
         // process any pending messages before inserting it into the event loop
         implementation.process_messages(data);
@@ -508,6 +650,7 @@ fn no_child(_: u16, _: u32, _: &ObjectMeta) -> Option<Object<ObjectMeta>> {
 struct ClientImplementation {
     inner: ClientInner,
     map: Arc<Mutex<ObjectMap<ObjectMeta>>>,
+    loggers: Loggers,
 }
 
 impl ClientImplementation {
@@ -522,12 +665,28 @@ impl ClientImplementation {
                 return;
             };
 
-            let msg = match ret {
+            let (msg, created_id) = match ret {
                 Ok(None) | Err(Error::Nix(::nix::Error::Sys(::nix::errno::Errno::EAGAIN))) => {
                     // Nothing more to read.
                     return;
                 }
-                Ok(Some(msg)) => msg,
+                Ok(Some(ret)) => ret,
+                Err(Error::Parse(_)) => {
+                    self.inner.post_error(
+                        1,
+                        super::display::DISPLAY_ERROR_INVALID_METHOD,
+                        "malformed request".into(),
+                    );
+                    return;
+                }
+                Err(Error::Protocol) => {
+                    self.inner.post_error(
+                        1,
+                        super::display::DISPLAY_ERROR_INVALID_OBJECT,
+                        "invalid new object id".into(),
+                    );
+                    return;
+                }
                 Err(_) => {
                     // On error, kill the client.
                     self.inner.kill();
@@ -553,6 +712,21 @@ impl ClientImplementation {
             };
 
             let object = res.object.clone();
+
+            log_message(
+                &self.loggers,
+                &ProtocolMessage {
+                    direction: MessageDirection::Request,
+                    interface: object.interface,
+                    object_id: id,
+                    opcode,
+                    name: object.requests.get(opcode as usize).map(|r| r.name).unwrap_or(""),
+                },
+            );
+            if let Some(created_id) = created_id {
+                self.inner.notify_resource_created(created_id, data.reborrow());
+            }
+
             let mut dispatcher = object.meta.dispatcher.get().borrow_mut();
 
             match dispatcher.dispatch(msg, res, &mut resourcemap, data.reborrow()) {
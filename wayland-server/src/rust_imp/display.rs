@@ -7,11 +7,15 @@ use std::os::unix::net::UnixListener;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use wayland_commons::ThreadGuard;
 
 use crate::display::get_runtime_dir;
-use crate::{Interface, Main, Resource};
+use crate::{DispatchData, Interface, Main, ProtocolMessage, Resource};
 
 use super::clients::ClientManager;
+use super::event_loop::{EventSourceInner, Loggers, ProtocolLoggerInner};
 use super::event_loop_glue::{FdManager, Token};
 use super::globals::GlobalManager;
 use super::{ClientInner, GlobalInner, WAYLAND_DEBUG};
@@ -26,6 +30,7 @@ pub(crate) struct DisplayInner {
     pub(crate) clients_mgr: Rc<RefCell<ClientManager>>,
     global_mgr: Rc<RefCell<GlobalManager>>,
     listeners: Vec<Token>,
+    loggers: Loggers,
 }
 
 impl DisplayInner {
@@ -41,10 +46,15 @@ impl DisplayInner {
         let global_mgr = Rc::new(RefCell::new(GlobalManager::new()));
         let epoll_mgr = Rc::new(FdManager::new().unwrap());
 
-        let clients_mgr =
-            Rc::new(RefCell::new(ClientManager::new(epoll_mgr.clone(), global_mgr.clone())));
+        let loggers: Loggers = Arc::new(ThreadGuard::new(RefCell::new(Vec::new())));
+
+        let clients_mgr = Rc::new(RefCell::new(ClientManager::new(
+            epoll_mgr.clone(),
+            global_mgr.clone(),
+            loggers.clone(),
+        )));
 
-        DisplayInner { epoll_mgr, clients_mgr, global_mgr, listeners: Vec::new() }
+        DisplayInner { epoll_mgr, clients_mgr, global_mgr, listeners: Vec::new(), loggers }
     }
 
     pub(crate) fn create_global<I, F1, F2>(
@@ -61,6 +71,34 @@ impl DisplayInner {
         self.global_mgr.borrow_mut().add_global(version, implementation, filter)
     }
 
+    pub(crate) fn set_global_filter<F>(&mut self, filter: Option<F>)
+    where
+        F: FnMut(ClientInner, &str) -> bool + 'static,
+    {
+        self.global_mgr.borrow_mut().set_display_filter(filter)
+    }
+
+    pub(crate) fn add_protocol_logger<F>(&mut self, logger: F) -> ProtocolLoggerInner
+    where
+        F: FnMut(&ProtocolMessage<'_>) + 'static,
+    {
+        ProtocolLoggerInner::add(&self.loggers, logger)
+    }
+
+    pub(crate) fn add_timer<F>(&mut self, callback: F) -> EventSourceInner
+    where
+        F: FnMut(DispatchData<'_>) + 'static,
+    {
+        EventSourceInner::add_timer(&self.epoll_mgr, callback)
+    }
+
+    pub(crate) fn add_signal<F>(&mut self, signal: i32, callback: F) -> EventSourceInner
+    where
+        F: FnMut(i32, DispatchData<'_>) + 'static,
+    {
+        EventSourceInner::add_signal(&self.epoll_mgr, signal, callback)
+    }
+
     pub(crate) fn flush_clients(&mut self, data: crate::DispatchData) {
         self.clients_mgr.borrow_mut().flush_all(data)
     }
@@ -172,6 +210,8 @@ impl Drop for DisplayInner {
             self.epoll_mgr.deregister(l);
         }
         self.clients_mgr.borrow_mut().kill_all();
+        // remove the timers and signal sources
+        self.epoll_mgr.clear();
     }
 }
 
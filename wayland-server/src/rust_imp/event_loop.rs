@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::os::unix::io::RawFd;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::time::Duration;
+
+use nix::sys::signal::{SigSet, Signal};
+use nix::sys::signalfd::{signalfd, SfdFlags, SIGNALFD_NEW};
+
+use wayland_commons::ThreadGuard;
+
+use crate::{DispatchData, ProtocolMessage};
+
+use super::event_loop_glue::{FdManager, Token};
+
+/// The file descriptor of an event source, closed along with it
+struct SourceFd(RawFd);
+
+impl Drop for SourceFd {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.0);
+    }
+}
+
+pub(crate) struct EventSourceInner {
+    // the fd is owned by the callback registered in the event loop, and is thus
+    // dropped once the source is removed or the event loop destroyed
+    fd: Weak<SourceFd>,
+    token: Option<Token>,
+    epoll_mgr: Weak<FdManager>,
+}
+
+impl EventSourceInner {
+    pub(crate) fn add_timer<F>(epoll_mgr: &Rc<FdManager>, mut callback: F) -> EventSourceInner
+    where
+        F: FnMut(DispatchData<'_>) + 'static,
+    {
+        let fd = unsafe {
+            libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_CLOEXEC | libc::TFD_NONBLOCK)
+        };
+        if fd < 0 {
+            return EventSourceInner::inactive();
+        }
+        let fd = Rc::new(SourceFd(fd));
+        let source_fd = fd.clone();
+        EventSourceInner::register(epoll_mgr, &fd, move |data| {
+            let mut expirations = [0u8; 8];
+            if nix::unistd::read(source_fd.0, &mut expirations).is_ok() {
+                callback(data);
+            }
+        })
+    }
+
+    pub(crate) fn add_signal<F>(
+        epoll_mgr: &Rc<FdManager>,
+        signal: i32,
+        mut callback: F,
+    ) -> EventSourceInner
+    where
+        F: FnMut(i32, DispatchData<'_>) + 'static,
+    {
+        let mut mask = SigSet::empty();
+        match Signal::try_from(signal) {
+            Ok(signal) => mask.add(signal),
+            Err(_) => return EventSourceInner::inactive(),
+        }
+        // like libwayland, block the signal in the thread adding the source
+        if mask.thread_block().is_err() {
+            return EventSourceInner::inactive();
+        }
+        let fd = match signalfd(SIGNALFD_NEW, &mask, SfdFlags::SFD_CLOEXEC | SfdFlags::SFD_NONBLOCK)
+        {
+            Ok(fd) => Rc::new(SourceFd(fd)),
+            Err(_) => return EventSourceInner::inactive(),
+        };
+        let source_fd = fd.clone();
+        EventSourceInner::register(epoll_mgr, &fd, move |mut data| {
+            // a signalfd_siginfo is 128 bytes long
+            let mut siginfo = [0u8; 128];
+            while let Ok(128) = nix::unistd::read(source_fd.0, &mut siginfo) {
+                callback(signal, data.reborrow());
+            }
+        })
+    }
+
+    fn register<F>(epoll_mgr: &Rc<FdManager>, fd: &Rc<SourceFd>, callback: F) -> EventSourceInner
+    where
+        F: FnMut(DispatchData<'_>) + 'static,
+    {
+        match epoll_mgr.register(fd.0, callback) {
+            Ok(token) => EventSourceInner {
+                fd: Rc::downgrade(fd),
+                token: Some(token),
+                epoll_mgr: Rc::downgrade(epoll_mgr),
+            },
+            Err(_) => EventSourceInner::inactive(),
+        }
+    }
+
+    fn inactive() -> EventSourceInner {
+        EventSourceInner { fd: Weak::new(), token: None, epoll_mgr: Weak::new() }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.fd.upgrade().is_some()
+    }
+
+    pub(crate) fn timer_update(&self, delay: Option<Duration>) -> bool {
+        let fd = match self.fd.upgrade() {
+            Some(fd) => fd,
+            None => return false,
+        };
+        let mut spec: libc::itimerspec = unsafe { std::mem::zeroed() };
+        if let Some(delay) = delay {
+            // follow libwayland: a millisecond precision, and a delay of 0 would disarm
+            // the timer
+            let ms = delay.as_millis().max(1).min(std::i32::MAX as u128) as i64;
+            spec.it_value.tv_sec = (ms / 1000) as libc::time_t;
+            spec.it_value.tv_nsec = ((ms % 1000) * 1_000_000) as libc::c_long;
+        }
+        unsafe { libc::timerfd_settime(fd.0, 0, &spec, std::ptr::null_mut()) == 0 }
+    }
+
+    pub(crate) fn remove(self) {
+        if let (Some(token), Some(epoll_mgr)) = (self.token, self.epoll_mgr.upgrade()) {
+            if self.fd.upgrade().is_some() {
+                epoll_mgr.deregister(token);
+            }
+        }
+    }
+}
+
+type LoggerCallback = Rc<RefCell<dyn FnMut(&ProtocolMessage<'_>)>>;
+
+/// The protocol loggers of a display, shared with its clients
+///
+/// Messages can be sent from other threads, they are only logged when they are sent
+/// from the thread of the display.
+pub(crate) type Loggers = Arc<ThreadGuard<RefCell<Vec<LoggerCallback>>>>;
+
+pub(crate) fn log_message(loggers: &Loggers, message: &ProtocolMessage<'_>) {
+    let callbacks = match loggers.try_get() {
+        Some(callbacks) => callbacks.borrow().clone(),
+        None => return,
+    };
+    for callback in callbacks {
+        // a logger sending an event from its callback is not logged by itself
+        if let Ok(mut callback) = callback.try_borrow_mut() {
+            (*callback)(message);
+        }
+    }
+}
+
+pub(crate) struct ProtocolLoggerInner {
+    callback: LoggerCallback,
+    loggers: std::sync::Weak<ThreadGuard<RefCell<Vec<LoggerCallback>>>>,
+}
+
+impl ProtocolLoggerInner {
+    pub(crate) fn add<F>(loggers: &Loggers, callback: F) -> ProtocolLoggerInner
+    where
+        F: FnMut(&ProtocolMessage<'_>) + 'static,
+    {
+        let callback = Rc::new(RefCell::new(callback)) as LoggerCallback;
+        loggers.get().borrow_mut().push(callback.clone());
+        ProtocolLoggerInner { callback, loggers: Arc::downgrade(loggers) }
+    }
+
+    pub(crate) fn destroy(self) {
+        if let Some(loggers) = self.loggers.upgrade() {
+            loggers.get().borrow_mut().retain(|c| !Rc::ptr_eq(c, &self.callback));
+        }
+    }
+}
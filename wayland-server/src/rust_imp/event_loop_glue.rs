@@ -87,6 +87,15 @@ impl FdManager {
         Ok(())
     }
 
+    /// Drop all the callbacks, closing the event sources they own
+    pub(crate) fn clear(&self) {
+        let callbacks = std::mem::take(&mut *self.callbacks.borrow_mut());
+        for (fd, _) in callbacks.iter().flatten() {
+            let _ = epoll_ctl(self.epoll_fd, EpollOp::EpollCtlDel, *fd, None);
+        }
+        drop(callbacks);
+    }
+
     pub(crate) fn get_poll_fd(&self) -> RawFd {
         self.epoll_fd
     }
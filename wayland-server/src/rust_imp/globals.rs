@@ -12,19 +12,43 @@ use super::resources::ObjectMeta;
 use super::{ClientInner, ResourceInner};
 
 type GlobalFilter = Rc<RefCell<dyn FnMut(ClientInner) -> bool>>;
+type DisplayFilter = Rc<RefCell<Option<Box<dyn FnMut(ClientInner, &str) -> bool>>>>;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum GlobalState {
+    Active,
+    // removed from the registries, but still bound normally
+    Disabled,
+    // removed from the registries, binds create inert resources
+    Destroyed,
+}
 
 pub(crate) struct GlobalInner<I: Interface> {
     _i: ::std::marker::PhantomData<*const I>,
-    destroyed_marker: Rc<Cell<bool>>,
+    state: Rc<Cell<GlobalState>>,
     id: u32,
     registries: Rc<RefCell<Vec<(u32, ClientInner)>>>,
     filter: Option<GlobalFilter>,
+    display_filter: DisplayFilter,
 }
 
 impl<I: Interface> GlobalInner<I> {
+    pub fn disable(&self) {
+        if self.state.get() == GlobalState::Active {
+            self.state.set(GlobalState::Disabled);
+            send_destroyed_global(
+                &self.registries.borrow(),
+                self.id,
+                I::NAME,
+                &self.display_filter,
+                self.filter.as_deref(),
+            );
+        }
+    }
+
     pub fn destroy(self) {
-        self.destroyed_marker.set(true);
-        send_destroyed_global(&self.registries.borrow(), self.id, self.filter.as_deref());
+        self.disable();
+        self.state.set(GlobalState::Destroyed);
     }
 }
 
@@ -33,7 +57,7 @@ type GlobalImplementation = dyn Fn(u32, u32, ClientInner, DispatchData) -> Resul
 struct GlobalData {
     version: u32,
     interface: &'static str,
-    destroyed: Rc<Cell<bool>>,
+    state: Rc<Cell<GlobalState>>,
     implem: Box<GlobalImplementation>,
     filter: Option<GlobalFilter>,
 }
@@ -41,11 +65,23 @@ struct GlobalData {
 pub(crate) struct GlobalManager {
     registries: Rc<RefCell<Vec<(u32, ClientInner)>>>,
     globals: Vec<GlobalData>,
+    display_filter: DisplayFilter,
 }
 
 impl GlobalManager {
     pub(crate) fn new() -> GlobalManager {
-        GlobalManager { registries: Rc::new(RefCell::new(Vec::new())), globals: Vec::new() }
+        GlobalManager {
+            registries: Rc::new(RefCell::new(Vec::new())),
+            globals: Vec::new(),
+            display_filter: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub(crate) fn set_display_filter<F>(&mut self, filter: Option<F>)
+    where
+        F: FnMut(ClientInner, &str) -> bool + 'static,
+    {
+        *self.display_filter.borrow_mut() = filter.map(|f| Box::new(f) as Box<_>);
     }
 
     pub(crate) fn add_global<I, F1, F2>(
@@ -60,23 +96,36 @@ impl GlobalManager {
         F2: FnMut(ClientInner) -> bool + 'static,
     {
         let implem = RefCell::new(implementation);
+        let state = Rc::new(Cell::new(GlobalState::Active));
+        let bind_state = state.clone();
         let data = GlobalData {
             version,
             interface: I::NAME,
-            destroyed: Rc::new(Cell::new(false)),
-            implem: Box::new(move |newid, version, client, data| {
+            state: state.clone(),
+            implem: Box::new(move |newid, version, client, mut data| {
+                // A client may bind a global it has not yet been notified the removal of,
+                // once it is destroyed the object is created inert, ignoring its requests.
+                let destroyed = bind_state.get() == GlobalState::Destroyed;
                 // insert the object in the map, and call the global bind callback
                 // This is done in two times to ensure the client lock is not locked during
                 // the callback
                 let map = if let Some(ref clientconn) = *client.data.lock().unwrap() {
-                    clientconn.map.lock().unwrap().insert_at(
-                        newid,
-                        Object::from_interface::<I>(version, ObjectMeta::new()),
-                    )?;
+                    let meta = if destroyed { ObjectMeta::inert() } else { ObjectMeta::new() };
+                    clientconn
+                        .map
+                        .lock()
+                        .unwrap()
+                        .insert_at(newid, Object::from_interface::<I>(version, meta))?;
                     Some(clientconn.map.clone())
                 } else {
                     None
                 };
+                if map.is_some() {
+                    client.notify_resource_created(newid, data.reborrow());
+                }
+                if destroyed {
+                    return Ok(());
+                }
                 if let Some(map) = map {
                     (&mut *implem.borrow_mut())(
                         Main::wrap(ResourceInner::from_id(newid, map, client).unwrap()),
@@ -89,36 +138,39 @@ impl GlobalManager {
             filter: filter.map(|f| Rc::new(RefCell::new(f)) as Rc<_>),
         };
 
-        let destroyed_marker = data.destroyed.clone();
-
         let id = self.globals.len() as u32 + 1;
 
         let filter = data.filter.clone();
-        {
-            send_new_global(&self.registries.borrow(), id, I::NAME, version, filter.as_deref());
-        }
+        send_new_global(
+            &self.registries.borrow(),
+            id,
+            I::NAME,
+            version,
+            &self.display_filter,
+            filter.as_deref(),
+        );
 
         self.globals.push(data);
 
         GlobalInner {
             _i: ::std::marker::PhantomData,
-            destroyed_marker,
+            state,
             id,
             registries: self.registries.clone(),
             filter,
+            display_filter: self.display_filter.clone(),
         }
     }
 
     pub(crate) fn new_registry(&mut self, id: u32, client: ClientInner) {
         let reg = (id, client);
         for (id, global) in self.globals.iter_mut().enumerate() {
-            if global.destroyed.get() {
+            if global.state.get() != GlobalState::Active {
                 continue;
             }
-            if let Some(ref filter) = global.filter {
-                if !(&mut *filter.borrow_mut())(reg.1.clone()) {
-                    continue;
-                }
+            if !is_visible(&reg.1, global.interface, &self.display_filter, global.filter.as_deref())
+            {
+                continue;
             }
             let interface = CString::new(global.interface.as_bytes().to_owned()).unwrap();
             send_global_msg(&reg, id as u32 + 1, interface, global.version);
@@ -141,12 +193,12 @@ impl GlobalManager {
         data: DispatchData,
     ) -> Result<(), ()> {
         if let Some(ref global_data) = self.globals.get((global_id - 1) as usize) {
-            if !global_data
-                .filter
-                .as_ref()
-                .map(|f| (&mut *f.borrow_mut())(client.clone()))
-                .unwrap_or(true)
-            {
+            if !is_visible(
+                &client,
+                global_data.interface,
+                &self.display_filter,
+                global_data.filter.as_deref(),
+            ) {
                 // client is not allowed to see this global
                 client.post_error(
                     registry_id,
@@ -213,24 +265,33 @@ fn send_global_msg(reg: &(u32, ClientInner), global_id: u32, interface: CString,
     }
 }
 
+/// Whether a global is advertised to a client, according to the filters of the display
+/// and of the global
+fn is_visible(
+    client: &ClientInner,
+    interface: &str,
+    display_filter: &DisplayFilter,
+    filter: Option<&RefCell<dyn FnMut(ClientInner) -> bool>>,
+) -> bool {
+    if let Some(ref mut display_filter) = *display_filter.borrow_mut() {
+        if !display_filter(client.clone(), interface) {
+            return false;
+        }
+    }
+    filter.map(|f| (&mut *f.borrow_mut())(client.clone())).unwrap_or(true)
+}
+
 fn send_new_global(
     registries: &[(u32, ClientInner)],
     global_id: u32,
     interface: &str,
     version: u32,
+    display_filter: &DisplayFilter,
     filter: Option<&RefCell<dyn FnMut(ClientInner) -> bool>>,
 ) {
     let iface = CString::new(interface.as_bytes().to_owned()).unwrap();
-    if let Some(filter) = filter {
-        let mut filter = filter.borrow_mut();
-        for reg in registries {
-            if !(&mut *filter)(reg.1.clone()) {
-                continue;
-            }
-            send_global_msg(reg, global_id, iface.clone(), version)
-        }
-    } else {
-        for reg in registries {
+    for reg in registries {
+        if is_visible(&reg.1, interface, display_filter, filter) {
             send_global_msg(reg, global_id, iface.clone(), version)
         }
     }
@@ -239,31 +300,20 @@ fn send_new_global(
 fn send_destroyed_global(
     registries: &[(u32, ClientInner)],
     global_id: u32,
+    interface: &str,
+    display_filter: &DisplayFilter,
     filter: Option<&RefCell<dyn FnMut(ClientInner) -> bool>>,
 ) {
-    if let Some(filter) = filter {
-        let mut filter = filter.borrow_mut();
-        for &(id, ref client) in registries {
-            if !(&mut *filter)(client.clone()) {
-                continue;
-            }
-            if let Some(ref mut clientconn) = *client.data.lock().unwrap() {
-                let _ = clientconn.write_message(&Message {
-                    sender_id: id,
-                    opcode: 1,
-                    args: smallvec![Argument::Uint(global_id)],
-                });
-            }
+    for &(id, ref client) in registries {
+        if !is_visible(client, interface, display_filter, filter) {
+            continue;
         }
-    } else {
-        for &(id, ref client) in registries {
-            if let Some(ref mut clientconn) = *client.data.lock().unwrap() {
-                let _ = clientconn.write_message(&Message {
-                    sender_id: id,
-                    opcode: 1,
-                    args: smallvec![Argument::Uint(global_id)],
-                });
-            }
+        if let Some(ref mut clientconn) = *client.data.lock().unwrap() {
+            let _ = clientconn.write_message(&Message {
+                sender_id: id,
+                opcode: 1,
+                args: smallvec![Argument::Uint(global_id)],
+            });
         }
     }
 }
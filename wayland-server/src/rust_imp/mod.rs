@@ -13,14 +13,16 @@ use crate::{DispatchData, Filter, Interface, Main, Resource};
 
 mod clients;
 mod display;
+mod event_loop;
 mod event_loop_glue;
 mod globals;
 mod resources;
 
 pub(crate) use self::clients::ClientInner;
 pub(crate) use self::display::DisplayInner;
+pub(crate) use self::event_loop::{EventSourceInner, ProtocolLoggerInner};
 pub(crate) use self::globals::GlobalInner;
-pub(crate) use self::resources::ResourceInner;
+pub(crate) use self::resources::{ResourceInner, WeakResourceInner};
 
 use self::resources::ResourceDestructor;
 
@@ -194,6 +196,34 @@ pub(crate) fn default_dispatcher() -> Arc<ThreadGuard<RefCell<dyn Dispatcher>>>
     Arc::new(ThreadGuard::new(RefCell::new(DefaultDisp)))
 }
 
+pub(crate) fn inert_dispatcher() -> Arc<ThreadGuard<RefCell<dyn Dispatcher>>> {
+    struct InertDisp;
+    impl Dispatcher for InertDisp {
+        fn dispatch(
+            &mut self,
+            msg: Message,
+            resource: ResourceInner,
+            _map: &mut ResourceMap,
+            _data: DispatchData,
+        ) -> Dispatched {
+            // the request is ignored, but the object must still be destroyed by destructors
+            if resource.object.requests[msg.opcode as usize].destructor {
+                resource.object.meta.alive.store(false, Ordering::Release);
+                let mut kill = false;
+                if let Some(ref mut data) = *resource.client.data.lock().unwrap() {
+                    kill = data.delete_id(resource.id).is_err();
+                }
+                if kill {
+                    resource.client.kill();
+                }
+            }
+            Dispatched::Yes
+        }
+    }
+
+    Arc::new(ThreadGuard::new(RefCell::new(InertDisp)))
+}
+
 pub(crate) fn make_destructor<I, E>(filter: Filter<E>) -> Arc<ThreadGuard<ResourceDestructor>>
 where
     I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
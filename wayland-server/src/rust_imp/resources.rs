@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 use crate::{Interface, Main, Resource};
 
@@ -9,6 +9,7 @@ use wayland_commons::map::{Object, ObjectMap, ObjectMetadata};
 use wayland_commons::user_data::UserData;
 use wayland_commons::{MessageGroup, ThreadGuard};
 
+use super::clients::WeakClientInner;
 use super::{ClientInner, Dispatcher, WAYLAND_DEBUG};
 
 pub(crate) type ResourceDestructor = RefCell<dyn FnMut(ResourceInner, crate::DispatchData<'_>)>;
@@ -19,11 +20,16 @@ pub(crate) struct ObjectMeta {
     pub(crate) destructor: Option<Arc<ThreadGuard<ResourceDestructor>>>,
     pub(crate) alive: Arc<AtomicBool>,
     user_data: Arc<UserData>,
+    inert: bool,
 }
 
 impl ObjectMetadata for ObjectMeta {
     fn child(&self) -> ObjectMeta {
-        ObjectMeta::new()
+        if self.inert {
+            ObjectMeta::inert()
+        } else {
+            ObjectMeta::new()
+        }
     }
 }
 
@@ -34,6 +40,18 @@ impl ObjectMeta {
             user_data: Arc::new(UserData::new()),
             dispatcher: super::default_dispatcher(),
             destructor: None,
+            inert: false,
+        }
+    }
+
+    /// Metadata of an object ignoring its requests, as well as those of its children
+    pub(crate) fn inert() -> ObjectMeta {
+        ObjectMeta {
+            alive: Arc::new(AtomicBool::new(true)),
+            user_data: Arc::new(UserData::new()),
+            dispatcher: super::inert_dispatcher(),
+            destructor: None,
+            inert: true,
         }
     }
 
@@ -43,6 +61,7 @@ impl ObjectMeta {
             user_data: Arc::new(UserData::new()),
             dispatcher: Arc::new(ThreadGuard::new(RefCell::new(disp))),
             destructor: None,
+            inert: false,
         }
     }
 }
@@ -113,6 +132,14 @@ impl ResourceInner {
         self.is_alive() && Arc::ptr_eq(&self.object.meta.alive, &other.object.meta.alive)
     }
 
+    pub(crate) fn downgrade(&self) -> WeakResourceInner {
+        WeakResourceInner {
+            id: self.id,
+            alive: Arc::downgrade(&self.object.meta.alive),
+            client: self.client.downgrade(),
+        }
+    }
+
     pub(crate) fn same_client_as(&self, other: &ResourceInner) -> bool {
         self.client.equals(&other.client)
     }
@@ -154,3 +181,30 @@ impl ResourceInner {
         self.client.set_destructor_for(self.id, super::make_destructor(filter));
     }
 }
+
+#[derive(Clone)]
+pub(crate) struct WeakResourceInner {
+    id: u32,
+    alive: Weak<AtomicBool>,
+    client: WeakClientInner,
+}
+
+impl WeakResourceInner {
+    pub(crate) fn upgrade(&self) -> Option<ResourceInner> {
+        let alive = self.alive.upgrade()?;
+        if !alive.load(Ordering::Acquire) {
+            return None;
+        }
+        let client = self.client.upgrade()?;
+        let object = {
+            let guard = client.data.lock().unwrap();
+            let map = guard.as_ref()?.map.lock().unwrap();
+            map.find(self.id)?
+        };
+        // the id may have been reused since, make sure this is still the same object
+        if !Arc::ptr_eq(&object.meta.alive, &alive) {
+            return None;
+        }
+        Some(ResourceInner { id: self.id, object, client })
+    }
+}
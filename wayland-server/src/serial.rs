@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::{AnonymousObject, Client, Interface, Resource, WeakResource};
+
+/// A counter generating serials
+///
+/// Serials are used by many protocols to identify events, for example so that a
+/// client can prove that it is reacting to a recent user input. This counter hands
+/// out increasing serials, wrapping around on overflow. It never produces `0`, as
+/// some clients use it to signify the absence of a serial.
+///
+/// The counter is thread-safe, and is typically shared by all the globals of
+/// a display, via an `Arc`.
+#[derive(Debug)]
+pub struct SerialCounter {
+    serial: AtomicU32,
+}
+
+impl SerialCounter {
+    /// Create a new counter
+    pub fn new() -> SerialCounter {
+        SerialCounter { serial: AtomicU32::new(1) }
+    }
+
+    /// Retrieve the next serial
+    pub fn next_serial(&self) -> u32 {
+        loop {
+            let serial = self.serial.fetch_add(1, Ordering::AcqRel);
+            if serial != 0 {
+                return serial;
+            }
+        }
+    }
+}
+
+impl Default for SerialCounter {
+    fn default() -> SerialCounter {
+        SerialCounter::new()
+    }
+}
+
+/// A record of a serial sent by a `SerialTracker`
+#[derive(Clone)]
+pub struct SerialRecord<K> {
+    /// The serial
+    pub serial: u32,
+    /// The kind of event this serial was sent with
+    pub kind: K,
+    /// The object the serial was sent with
+    ///
+    /// This handle does not keep the object or its client alive, the client the
+    /// serial was sent to can be retrieved from it as long as the object is alive.
+    pub resource: WeakResource<AnonymousObject>,
+    /// The protocol id of the object the serial was sent with
+    pub object_id: u32,
+    /// The interface of the object the serial was sent with
+    pub interface: &'static str,
+}
+
+impl<K> SerialRecord<K> {
+    /// Whether the object of this record is alive, and belongs to the client of `resource`
+    fn sent_to_client_of<I>(&self, resource: &Resource<I>) -> bool
+    where
+        I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    {
+        self.resource.upgrade().map(|recorded| recorded.same_client_as(resource)).unwrap_or(false)
+    }
+}
+
+/// A tracker of the recently sent serials
+///
+/// This tracker generates serials from a `SerialCounter`, and remembers the kind of
+/// event and the object each of them was sent with, so that you can later validate
+/// the serials provided by clients in their requests. You would typically have one
+/// tracker per seat, using a kind type like:
+///
+/// ```
+/// #[derive(Copy, Clone, PartialEq)]
+/// enum SerialKind {
+///     PointerEnter,
+///     ButtonPress,
+///     ButtonRelease,
+///     KeyboardEnter,
+///     Key,
+/// }
+/// ```
+///
+/// Only a limited number of serials are remembered: a serial is forgotten once enough
+/// newer serials have been recorded, and is then no longer considered valid.
+pub struct SerialTracker<K> {
+    counter: Arc<SerialCounter>,
+    records: Mutex<VecDeque<SerialRecord<K>>>,
+    capacity: usize,
+}
+
+impl<K: Clone + PartialEq> SerialTracker<K> {
+    /// Create a new tracker, remembering the last 64 serials
+    pub fn new(counter: Arc<SerialCounter>) -> SerialTracker<K> {
+        SerialTracker::with_capacity(counter, 64)
+    }
+
+    /// Create a new tracker, remembering a given number of serials
+    pub fn with_capacity(counter: Arc<SerialCounter>, capacity: usize) -> SerialTracker<K> {
+        let capacity = ::std::cmp::max(capacity, 1);
+        SerialTracker { counter, records: Mutex::new(VecDeque::with_capacity(capacity)), capacity }
+    }
+
+    /// Generate a new serial, to be sent with an event of given kind to given resource
+    pub fn next_serial<I>(&self, kind: K, resource: &Resource<I>) -> u32
+    where
+        I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    {
+        let serial = self.counter.next_serial();
+        self.record(serial, kind, resource);
+        serial
+    }
+
+    /// Record a serial that was generated by other means
+    pub fn record<I>(&self, serial: u32, kind: K, resource: &Resource<I>)
+    where
+        I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    {
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(SerialRecord {
+            serial,
+            kind,
+            resource: resource.downgrade().anonymize(),
+            object_id: resource.id(),
+            interface: I::NAME,
+        });
+    }
+
+    /// Retrieve the record of a serial
+    ///
+    /// Returns `None` if this serial was not recorded by this tracker, or is too old
+    /// to be remembered.
+    pub fn get(&self, serial: u32) -> Option<SerialRecord<K>> {
+        self.records.lock().unwrap().iter().rev().find(|r| r.serial == serial).cloned()
+    }
+
+    /// Check whether a serial was recently sent to the client of given resource, with an
+    /// event of given kind
+    ///
+    /// This accepts serials sent with any object of the client, as is needed to validate
+    /// requests against a seat: for example, you would check that the serial of a
+    /// `xdg_toplevel.move` request was sent with a button press, passing the `wl_seat` of
+    /// the request, while the serial was sent with a `wl_pointer` of the client. The seat
+    /// is the one of this tracker, so you need one tracker per seat. A serial is no longer
+    /// valid once the object it was sent with is destroyed.
+    ///
+    /// Use `validate_object` for serials that must come from a given object.
+    pub fn validate<I>(&self, serial: u32, kind: &K, resource: &Resource<I>) -> bool
+    where
+        I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    {
+        match self.get(serial) {
+            Some(record) => record.kind == *kind && record.sent_to_client_of(resource),
+            None => false,
+        }
+    }
+
+    /// Check whether a serial was recently sent to given resource, with an event of
+    /// given kind
+    ///
+    /// This is stricter than `validate`, for serials tied to a specific object: for
+    /// example, the serial of a `xdg_surface.ack_configure` request must have been sent
+    /// with a `xdg_surface.configure` event of the same `xdg_surface`.
+    pub fn validate_object<I>(&self, serial: u32, kind: &K, resource: &Resource<I>) -> bool
+    where
+        I: Interface + AsRef<Resource<I>> + From<Resource<I>>,
+    {
+        match self.get(serial) {
+            Some(record) => {
+                record.kind == *kind
+                    && record.interface == I::NAME
+                    && record.object_id == resource.id()
+                    // ids are only reused once the object is destroyed, which
+                    // `sent_to_client_of()` detects
+                    && record.sent_to_client_of(resource)
+            }
+            None => false,
+        }
+    }
+
+    /// Retrieve the record of the latest serial sent with an event of given kind
+    pub fn latest(&self, kind: &K) -> Option<SerialRecord<K>> {
+        self.records.lock().unwrap().iter().rev().find(|r| r.kind == *kind).cloned()
+    }
+
+    /// Forget all the serials sent to a given client
+    ///
+    /// The serials sent with destroyed objects are forgotten as well. They are never
+    /// matched, but you may want to call this when a client disconnects to free the
+    /// associated memory.
+    pub fn forget_client(&self, client: &Client) {
+        self.records.lock().unwrap().retain(|r| {
+            r.resource
+                .upgrade()
+                .and_then(|resource| resource.client())
+                .map(|c| !c.equals(client))
+                .unwrap_or(false)
+        });
+    }
+}
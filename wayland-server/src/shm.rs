@@ -0,0 +1,432 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Once;
+
+use nix::sys::{mman, signal};
+
+use crate::protocol::{wl_buffer, wl_shm, wl_shm_pool};
+use crate::{Display, Filter, Global, Main};
+
+/// Metadata of a buffer created from a `wl_shm_pool`
+#[derive(Copy, Clone, Debug)]
+pub struct BufferData {
+    /// Offset of the start of the buffer in the pool
+    pub offset: i32,
+    /// Width of the buffer, in pixels
+    pub width: i32,
+    /// Height of the buffer, in pixels
+    pub height: i32,
+    /// Stride of the buffer, in bytes
+    pub stride: i32,
+    /// Pixel format of the buffer
+    pub format: wl_shm::Format,
+}
+
+/// Errors that can occur when accessing the contents of a shm buffer
+#[derive(Debug)]
+pub enum BufferAccessError {
+    /// The buffer was not created by a `wl_shm` global from `init_shm_global`
+    NotManaged,
+    /// The memory of the buffer could not be accessed, because the client shrank
+    /// the file backing it
+    ///
+    /// A protocol error has been posted to the client.
+    BadMap,
+}
+
+impl ::std::error::Error for BufferAccessError {}
+
+impl fmt::Display for BufferAccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BufferAccessError::NotManaged => f.write_str("This buffer is not managed by wl_shm."),
+            BufferAccessError::BadMap => {
+                f.write_str("The memory of this buffer is not accessible.")
+            }
+        }
+    }
+}
+
+/// Create a `wl_shm` global
+///
+/// This global handles the creation of `wl_shm_pool`s and of the `wl_buffer`s from
+/// them, and checks the validity of the requests of the clients. The contents of
+/// the buffers can then be accessed with `with_buffer_contents`.
+///
+/// The formats `Argb8888` and `Xrgb8888` are always advertised, you can provide a
+/// list of additional formats you support.
+pub fn init_shm_global(
+    display: &mut Display,
+    formats: Vec<wl_shm::Format>,
+) -> Global<wl_shm::WlShm> {
+    let mut formats = formats;
+    for &format in &[wl_shm::Format::Argb8888, wl_shm::Format::Xrgb8888] {
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    let formats = Rc::new(formats);
+
+    display.create_global::<wl_shm::WlShm, _>(
+        1,
+        Filter::new(move |(shm, _): (Main<wl_shm::WlShm>, u32), _, _| {
+            for &format in formats.iter() {
+                shm.format(format);
+            }
+            let formats = formats.clone();
+            shm.quick_assign(move |shm, wl_shm::Request::CreatePool { id, fd, size }, _| {
+                create_pool(&shm, id, fd, size, formats.clone())
+            });
+        }),
+    )
+}
+
+/// Access the contents of a shm buffer
+///
+/// The provided closure is given the contents of the buffer, `stride * height` bytes
+/// starting at its offset in the pool, along with its metadata.
+///
+/// If the client shrank the file backing the pool, accessing its contents would
+/// raise a SIGBUS signal. This is caught, the closure will then read zeroes in place
+/// of the inaccessible memory, and this function returns `BufferAccessError::BadMap`
+/// after posting a protocol error to the client.
+pub fn with_buffer_contents<F, T>(
+    buffer: &wl_buffer::WlBuffer,
+    f: F,
+) -> Result<T, BufferAccessError>
+where
+    F: FnOnce(&[u8], BufferData) -> T,
+{
+    let buffer_data = match buffer.as_ref().user_data().get::<ShmBufferUserData>() {
+        Some(data) => data,
+        None => return Err(BufferAccessError::NotManaged),
+    };
+    let data = buffer_data.data;
+
+    let (ret, bad_map) = buffer_data.pool.with_data_slice(|slice| {
+        let start = data.offset as usize;
+        let end = start + (data.stride as usize * data.height as usize);
+        // the pool may have been lost if remapping it failed
+        slice.get(start..end).map(|contents| f(contents, data))
+    });
+
+    if let (Some(ret), false) = (ret, bad_map) {
+        Ok(ret)
+    } else {
+        buffer.as_ref().post_error(
+            wl_shm::Error::InvalidFd as u32,
+            "Error accessing the memory of the wl_shm_pool.".into(),
+        );
+        Err(BufferAccessError::BadMap)
+    }
+}
+
+struct ShmBufferUserData {
+    pool: Rc<Pool>,
+    data: BufferData,
+}
+
+fn create_pool(
+    shm: &wl_shm::WlShm,
+    pool: Main<wl_shm_pool::WlShmPool>,
+    fd: RawFd,
+    size: i32,
+    formats: Rc<Vec<wl_shm::Format>>,
+) {
+    if size <= 0 {
+        let _ = nix::unistd::close(fd);
+        shm.as_ref().post_error(
+            wl_shm::Error::InvalidStride as u32,
+            format!("Invalid size for a new wl_shm_pool: {}.", size),
+        );
+        return;
+    }
+    let mem = match Pool::new(fd, size as usize) {
+        Ok(mem) => Rc::new(mem),
+        Err(()) => {
+            shm.as_ref().post_error(
+                wl_shm::Error::InvalidFd as u32,
+                format!("Failed to mmap fd {} for a new wl_shm_pool.", fd),
+            );
+            return;
+        }
+    };
+
+    pool.quick_assign(move |pool, request, _| match request {
+        wl_shm_pool::Request::CreateBuffer { id, offset, width, height, stride, format } => {
+            if !formats.contains(&format) {
+                pool.as_ref().post_error(
+                    wl_shm::Error::InvalidFormat as u32,
+                    format!("Format {:?} is not supported.", format),
+                );
+                return;
+            }
+            let pool_size = mem.size() as i64;
+            if offset < 0
+                || width <= 0
+                || height <= 0
+                || i64::from(stride) < i64::from(width) * bytes_per_pixel(format) as i64
+                || offset as i64 + stride as i64 * height as i64 > pool_size
+            {
+                pool.as_ref().post_error(
+                    wl_shm::Error::InvalidStride as u32,
+                    format!(
+                        "Invalid buffer (offset: {}, width: {}, height: {}, stride: {}) for a pool of size {}.",
+                        offset, width, height, stride, pool_size
+                    ),
+                );
+                return;
+            }
+            let data = BufferData { offset, width, height, stride, format };
+            let pool = mem.clone();
+            id.as_ref().user_data().set(move || ShmBufferUserData { pool, data });
+            id.quick_assign(|_, _, _| {});
+        }
+        wl_shm_pool::Request::Resize { size } => {
+            if size < mem.size() as i32 {
+                pool.as_ref().post_error(
+                    wl_shm::Error::InvalidStride as u32,
+                    format!("A wl_shm_pool cannot be shrunk (requested size: {}).", size),
+                );
+                return;
+            }
+            if mem.resize(size as usize).is_err() {
+                pool.as_ref().post_error(
+                    wl_shm::Error::InvalidFd as u32,
+                    format!("Failed to resize the wl_shm_pool to {}.", size),
+                );
+            }
+        }
+        wl_shm_pool::Request::Destroy => {}
+    });
+}
+
+// The number of bytes used by a pixel of this format, or by the first plane
+// of a planar format
+fn bytes_per_pixel(format: wl_shm::Format) -> usize {
+    use wl_shm::Format::*;
+    match format {
+        C8 | Rgb332 | Bgr233 => 1,
+        Xrgb4444 | Xbgr4444 | Rgbx4444 | Bgrx4444 | Argb4444 | Abgr4444 | Rgba4444 | Bgra4444
+        | Xrgb1555 | Xbgr1555 | Rgbx5551 | Bgrx5551 | Argb1555 | Abgr1555 | Rgba5551 | Bgra5551
+        | Rgb565 | Bgr565 | Yuyv | Yvyu | Uyvy | Vyuy => 2,
+        Rgb888 | Bgr888 => 3,
+        Argb8888 | Xrgb8888 | Xbgr8888 | Rgbx8888 | Bgrx8888 | Abgr8888 | Rgba8888 | Bgra8888
+        | Xrgb2101010 | Xbgr2101010 | Rgbx1010102 | Bgrx1010102 | Argb2101010 | Abgr2101010
+        | Rgba1010102 | Bgra1010102 | Ayuv => 4,
+        // the planar formats have at least one byte per pixel in their first plane
+        _ => 1,
+    }
+}
+
+/*
+ * Memory mapping of the pools, with protection against SIGBUS
+ */
+
+struct Pool {
+    map: RefCell<MemMap>,
+    fd: RawFd,
+}
+
+impl Pool {
+    fn new(fd: RawFd, size: usize) -> Result<Pool, ()> {
+        match MemMap::new(fd, size) {
+            Ok(map) => Ok(Pool { map: RefCell::new(map), fd }),
+            Err(()) => {
+                let _ = nix::unistd::close(fd);
+                Err(())
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.map.borrow().size
+    }
+
+    fn resize(&self, size: usize) -> Result<(), ()> {
+        self.map.borrow_mut().remap(size)
+    }
+
+    // Returns the return value of the closure, and whether a SIGBUS was caught
+    fn with_data_slice<T, F: FnOnce(&[u8]) -> T>(&self, f: F) -> (T, bool) {
+        SIGBUS_INIT.call_once(|| unsafe { place_sigbus_handler() });
+
+        let map = self.map.borrow();
+        SIGBUS_GUARD.with(|guard| {
+            let (previous, triggered) = guard.get();
+            guard.set((&*map, false));
+            let slice: &[u8] = if map.ptr.is_null() {
+                &[]
+            } else {
+                unsafe { ::std::slice::from_raw_parts(map.ptr, map.size) }
+            };
+            let ret = f(slice);
+            let caught = guard.get().1;
+            guard.set((previous, triggered));
+            (ret, caught)
+        })
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.fd);
+    }
+}
+
+struct MemMap {
+    ptr: *mut u8,
+    fd: RawFd,
+    size: usize,
+}
+
+impl MemMap {
+    fn new(fd: RawFd, size: usize) -> Result<MemMap, ()> {
+        Ok(MemMap { ptr: unsafe { map(fd, size) }?, fd, size })
+    }
+
+    fn remap(&mut self, size: usize) -> Result<(), ()> {
+        if self.ptr.is_null() {
+            return Err(());
+        }
+        // munmap cannot fail, as we are unmapping a pre-existing map
+        let _ = unsafe { unmap(self.ptr, self.size) };
+        // remap the fd with the new size
+        match unsafe { map(self.fd, size) } {
+            Ok(ptr) => {
+                self.ptr = ptr;
+                self.size = size;
+                Ok(())
+            }
+            Err(()) => {
+                // set the map to an empty state
+                self.ptr = ptr::null_mut();
+                self.size = 0;
+                Err(())
+            }
+        }
+    }
+
+    fn contains(&self, ptr: *mut u8) -> bool {
+        ptr >= self.ptr && (ptr as usize) < (self.ptr as usize) + self.size
+    }
+
+    // replace the map with anonymous memory, to make accesses succeed
+    fn nullify(&self) -> Result<(), ()> {
+        unsafe { nullify_map(self.ptr, self.size) }
+    }
+}
+
+impl Drop for MemMap {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            let _ = unsafe { unmap(self.ptr, self.size) };
+        }
+    }
+}
+
+unsafe fn map(fd: RawFd, size: usize) -> Result<*mut u8, ()> {
+    let ret = mman::mmap(
+        ptr::null_mut(),
+        size,
+        mman::ProtFlags::PROT_READ,
+        mman::MapFlags::MAP_SHARED,
+        fd,
+        0,
+    );
+    ret.map(|p| p as *mut u8).map_err(|_| ())
+}
+
+unsafe fn unmap(ptr: *mut u8, size: usize) -> Result<(), ()> {
+    mman::munmap(ptr as *mut _, size).map_err(|_| ())
+}
+
+unsafe fn nullify_map(ptr: *mut u8, size: usize) -> Result<(), ()> {
+    let ret = mman::mmap(
+        ptr as *mut _,
+        size,
+        mman::ProtFlags::PROT_READ,
+        mman::MapFlags::MAP_ANONYMOUS | mman::MapFlags::MAP_PRIVATE | mman::MapFlags::MAP_FIXED,
+        -1,
+        0,
+    );
+    ret.map(|_| ()).map_err(|_| ())
+}
+
+thread_local!(
+    // a `const` initializer would require a more recent compiler than our minimum
+    #[allow(clippy::missing_const_for_thread_local)]
+    static SIGBUS_GUARD: Cell<(*const MemMap, bool)> = Cell::new((ptr::null(), false))
+);
+
+static SIGBUS_INIT: Once = Once::new();
+static mut OLD_SIGBUS_HANDLER: *mut signal::SigAction = ptr::null_mut();
+
+unsafe fn place_sigbus_handler() {
+    // create our sigbus handler
+    let action = signal::SigAction::new(
+        signal::SigHandler::SigAction(sigbus_handler),
+        signal::SaFlags::SA_NODEFER,
+        signal::SigSet::empty(),
+    );
+    match signal::sigaction(signal::Signal::SIGBUS, &action) {
+        Ok(old_signal) => {
+            OLD_SIGBUS_HANDLER = Box::into_raw(Box::new(old_signal));
+        }
+        Err(e) => panic!("sigaction failed for SIGBUS handler: {:?}", e),
+    }
+}
+
+unsafe fn reraise_sigbus() {
+    // reset the old sigaction
+    let _ = signal::sigaction(signal::Signal::SIGBUS, &*OLD_SIGBUS_HANDLER);
+    let _ = signal::raise(signal::Signal::SIGBUS);
+}
+
+extern "C" fn sigbus_handler(
+    _signum: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _context: *mut libc::c_void,
+) {
+    let faulty_ptr = unsafe { siginfo_si_addr(info) } as *mut u8;
+    SIGBUS_GUARD.with(|guard| {
+        let (memmap, _) = guard.get();
+        match unsafe { memmap.as_ref() }.map(|m| (m, m.contains(faulty_ptr))) {
+            Some((m, true)) => {
+                // we are in a faulty memory pool !
+                // remember that it was faulty
+                guard.set((memmap, true));
+                // nullify the pool
+                if m.nullify().is_err() {
+                    // something terrible occured !
+                    unsafe { reraise_sigbus() }
+                }
+            }
+            _ => {
+                // something else occured, let's die honorably
+                unsafe { reraise_sigbus() }
+            }
+        }
+    });
+}
+
+// older versions of libc do not expose si_addr on linux, so read it from
+// the beginning of the siginfo_t layout
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
+    #[repr(C)]
+    struct siginfo_t {
+        a: [libc::c_int; 3], // si_signo, si_errno, si_code
+        si_addr: *mut libc::c_void,
+    }
+
+    (*(info as *const siginfo_t)).si_addr
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
+    (*info).si_addr
+}
@@ -16,6 +16,7 @@ pub enum wl_display {}
 pub enum wl_event_loop {}
 pub enum wl_event_source {}
 pub enum wl_global {}
+pub enum wl_protocol_logger {}
 pub enum wl_resource {}
 pub enum wl_shm_buffer {}
 
@@ -28,6 +29,33 @@ pub type wl_notify_func_t = unsafe extern "C" fn(*mut wl_listener, *mut c_void)
 pub type wl_resource_destroy_func_t = unsafe extern "C" fn(*mut wl_resource) -> ();
 pub type wl_display_global_filter_func_t =
     unsafe extern "C" fn(*const wl_client, *const wl_global, *mut c_void) -> bool;
+pub type wl_protocol_logger_func_t =
+    unsafe extern "C" fn(*mut c_void, wl_protocol_logger_type, *const wl_protocol_logger_message) -> ();
+pub type wl_client_for_each_resource_iterator_func_t =
+    unsafe extern "C" fn(*mut wl_resource, *mut c_void) -> wl_iterator_result;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum wl_protocol_logger_type {
+    WL_PROTOCOL_LOGGER_REQUEST = 0,
+    WL_PROTOCOL_LOGGER_EVENT = 1,
+}
+
+#[repr(C)]
+pub struct wl_protocol_logger_message {
+    pub resource: *mut wl_resource,
+    pub message_opcode: c_int,
+    pub message: *const wl_message,
+    pub arguments_count: c_int,
+    pub arguments: *const wl_argument,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum wl_iterator_result {
+    WL_ITERATOR_STOP = 0,
+    WL_ITERATOR_CONTINUE = 1,
+}
 
 #[repr(C)]
 pub struct wl_listener {
@@ -74,6 +102,9 @@ external_library!(WaylandServer, "wayland-server",
         fn wl_display_init_shm(*mut wl_display) -> c_int,
         fn wl_display_add_client_created_listener(*mut wl_display, *mut wl_listener) -> (),
         fn wl_display_set_global_filter(*mut wl_display, wl_display_global_filter_func_t, *mut c_void) -> (),
+        fn wl_display_add_protocol_logger(*mut wl_display, wl_protocol_logger_func_t, *mut c_void) -> *mut wl_protocol_logger,
+    // wl_protocol_logger
+        fn wl_protocol_logger_destroy(*mut wl_protocol_logger) -> (),
     // wl_event_loop
         fn wl_event_loop_create() -> *mut wl_event_loop,
         fn wl_event_loop_destroy(*mut wl_event_loop) -> (),
@@ -174,6 +205,88 @@ lazy_static::lazy_static!(
     };
 );
 
+// Functions that only recent versions of libwayland-server provide. They are not part of
+// WAYLAND_SERVER_HANDLE, as a missing symbol would make the whole library unusable, but
+// are looked up separately and can be missing.
+#[cfg(feature = "server")]
+macro_rules! optional_functions(
+    ($structname:ident, $($name:ident($($arg:ty),*) -> $ret:ty),+ $(,)*) => (
+        /// Functions of `libwayland-server.so` that are not available in all its versions
+        ///
+        /// Each of them is `None` if the loaded library does not provide it.
+        pub struct $structname {
+            $(pub $name: Option<unsafe extern "C" fn($($arg),*) -> $ret>,)+
+        }
+
+        impl $structname {
+            unsafe fn load(handle: *mut c_void) -> $structname {
+                $structname {
+                    $($name: {
+                        let symbol = ::libc::dlsym(
+                            handle,
+                            concat!(stringify!($name), "\0").as_ptr() as *const c_char
+                        );
+                        if symbol.is_null() {
+                            None
+                        } else {
+                            Some(::std::mem::transmute::<*mut c_void, unsafe extern "C" fn($($arg),*) -> $ret>(symbol))
+                        }
+                    },)+
+                }
+            }
+
+            #[allow(dead_code)]
+            fn missing() -> $structname {
+                $structname { $($name: None,)+ }
+            }
+        }
+    )
+);
+
+#[cfg(feature = "server")]
+optional_functions!(WaylandServerOptional,
+    // wl_client
+        wl_client_add_resource_created_listener(*mut wl_client, *mut wl_listener) -> (),
+        wl_client_for_each_resource(*mut wl_client, wl_client_for_each_resource_iterator_func_t, *mut c_void) -> (),
+    // wl_global
+        wl_global_remove(*mut wl_global) -> (),
+        wl_global_get_interface(*const wl_global) -> *const wl_interface,
+    // wl_resource
+        wl_resource_get_class(*mut wl_resource) -> *const c_char,
+);
+
+#[cfg(all(feature = "server", not(feature = "dlopen")))]
+lazy_static::lazy_static!(
+    /// The optional functions of the linked libwayland-server
+    pub static ref WAYLAND_SERVER_OPTIONAL: WaylandServerOptional = unsafe {
+        WaylandServerOptional::load(::libc::RTLD_DEFAULT)
+    };
+);
+
+#[cfg(all(feature = "server", feature = "dlopen"))]
+lazy_static::lazy_static!(
+    /// The optional functions of the loaded libwayland-server
+    ///
+    /// They are all `None` if the library could not be loaded.
+    pub static ref WAYLAND_SERVER_OPTIONAL: WaylandServerOptional = {
+        if WAYLAND_SERVER_OPTION.is_none() {
+            return WaylandServerOptional::missing();
+        }
+        // the library is already loaded, this only gets another handle to it
+        let versions = ["libwayland-server.so\0",
+                        "libwayland-server.so.0\0"];
+        for ver in &versions {
+            unsafe {
+                let handle = ::libc::dlopen(ver.as_ptr() as *const c_char, ::libc::RTLD_LAZY);
+                if !handle.is_null() {
+                    return WaylandServerOptional::load(handle);
+                }
+            }
+        }
+        WaylandServerOptional::missing()
+    };
+);
+
 #[cfg(all(feature = "server", not(feature = "dlopen")))]
 pub fn is_lib_available() -> bool {
     true
@@ -195,11 +308,13 @@ pub mod signal {
     use std::os::raw::c_void;
     use std::ptr;
 
-    // TODO: Is this really not UB ?
     macro_rules! offset_of(
-        ($ty:ty, $field:ident) => {
-            &(*(ptr::null() as *const $ty)).$field as *const _ as usize
-        }
+        ($ty:ty, $field:ident) => {{
+            let base = ::std::mem::MaybeUninit::<$ty>::uninit();
+            let base_ptr = base.as_ptr();
+            // the fields are only used to compute their address, never read
+            &(*base_ptr).$field as *const _ as usize - base_ptr as usize
+        }}
     );
 
     macro_rules! container_of(